@@ -1,6 +1,10 @@
 use rustc_version::{version_meta, Channel};
 
 fn main() {
+    println!(
+        "cargo:rustc-check-cfg=cfg(CHANNEL_STABLE, CHANNEL_BETA, CHANNEL_NIGHTLY, CHANNEL_DEV)"
+    );
+
     // Set cfg flags depending on release channel
     let channel = match version_meta().unwrap().channel {
         Channel::Stable => "CHANNEL_STABLE",
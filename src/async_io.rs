@@ -0,0 +1,257 @@
+//! A readiness-driven async terminal for smol and other async-io based runtimes, see
+//! [`CommandExt`].
+//!
+//! Each half wraps its own master descriptor in an [`Async<OwnedFd>`], so reads and writes wait
+//! on the async-io reactor instead of a thread. Only waiting for the child takes a thread from
+//! the `blocking` pool.
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! use futures::io::AsyncReadExt;
+//! use pseudoterminal::async_io::CommandExt;
+//!
+//! # futures::executor::block_on(async {
+//! let mut terminal = Command::new("ls").spawn_terminal()?;
+//!
+//! let mut output = String::new();
+//! terminal.termout.as_mut().unwrap().read_to_string(&mut output).await?;
+//! # std::io::Result::Ok(())
+//! # });
+//! ```
+
+use std::io;
+use std::os::fd::{AsFd, OwnedFd};
+use std::pin::Pin;
+use std::process::{ChildStderr, Command, ExitStatus};
+use std::task::{ready, Context, Poll};
+
+use ::async_io::Async;
+use blocking::unblock;
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::sys::{
+    control_char, is_session_end, line_terminator, read_fd, send_control_char, send_eof_on_drop,
+    write_fd, ControlChar,
+};
+use crate::{TerminalController, TerminalOptions, TerminalSize};
+
+pub struct Terminal {
+    inner: crate::Terminal,
+    pub termin: Option<TerminalIn>,
+    pub termout: Option<TerminalOut>,
+    /// The child's stderr if `TerminalOptions::separate_stderr` routed it through a pipe.
+    pub termerr: Option<Async<ChildStderr>>,
+}
+
+impl Terminal {
+    /// Moves the I/O of a blocking terminal to async-io, e.g. one the child was attached to.
+    ///
+    /// Halves taken out of `terminal` before stay missing.
+    pub fn new(mut terminal: crate::Terminal) -> io::Result<Self> {
+        let termin = terminal.termin.take().map(TerminalIn::new).transpose()?;
+        let termout = terminal.termout.take().map(TerminalOut::new).transpose()?;
+        let termerr = terminal.termerr.take().map(Async::new).transpose()?;
+
+        Ok(Self {
+            inner: terminal,
+            termin,
+            termout,
+            termerr,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> Option<u32> {
+        self.inner.id()
+    }
+
+    /// Returns a handle for resizing, signalling and waiting that can be moved to another task.
+    pub fn controller(&self) -> TerminalController {
+        self.inner.controller()
+    }
+
+    pub fn set_term_size(&mut self, new_size: TerminalSize) -> io::Result<()> {
+        self.inner.set_term_size(new_size)
+    }
+
+    /// Waits for the child to exit, see [`crate::Terminal::wait`].
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        let controller = self.inner.controller();
+
+        unblock(move || controller.wait()).await
+    }
+
+    /// Delivers EOF to the child, then kills it and waits until it exited.
+    pub async fn close(self) -> io::Result<()> {
+        let Self {
+            inner,
+            termin,
+            termout,
+            termerr,
+        } = self;
+        drop((termin, termout, termerr));
+
+        unblock(move || inner.close()).await
+    }
+}
+
+pub trait CommandExt {
+    fn spawn_terminal(&mut self) -> io::Result<Terminal>;
+
+    /// Spawns the command in a new terminal configured by `options`.
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal>;
+}
+
+impl CommandExt for Command {
+    fn spawn_terminal(&mut self) -> io::Result<Terminal> {
+        self.spawn_terminal_with(&TerminalOptions::default())
+    }
+
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal> {
+        Terminal::new(crate::CommandExt::spawn_terminal_with(self, options)?)
+    }
+}
+
+/// The input of a terminal, dropping it delivers EOF to the child while it reads lines, see
+/// [`crate::TerminalIn`].
+///
+/// Writes go straight to the terminal, so there is nothing to flush.
+pub struct TerminalIn {
+    fd: Async<OwnedFd>,
+    closed: bool,
+}
+
+impl TerminalIn {
+    /// Takes over `termin`, delivering EOF from now on regardless of its clones.
+    ///
+    /// The terminal is switched to non-blocking I/O, see [`crate::TerminalIn::set_write_timeout`].
+    pub fn new(termin: crate::TerminalIn) -> io::Result<Self> {
+        Ok(Self {
+            fd: Async::new(OwnedFd::from(termin.into_file()?))?,
+            closed: false,
+        })
+    }
+
+    /// Delivers EOF to the child, see [`crate::TerminalIn::close`].
+    pub fn close(mut self) -> io::Result<()> {
+        self.closed = true;
+
+        send_control_char(self.fd.get_ref(), ControlChar::Eof)
+    }
+
+    /// Sends the terminal's EOF character, see [`crate::TerminalIn::send_eof`].
+    pub async fn send_eof(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Eof).await
+    }
+
+    /// Sends the terminal's interrupt character, see [`crate::TerminalIn::send_interrupt`].
+    pub async fn send_interrupt(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Interrupt).await
+    }
+
+    /// Writes `line` and a line terminator, see [`crate::TerminalIn::send_line`].
+    pub async fn send_line(&mut self, line: &str) -> io::Result<()> {
+        let mut data = line.as_bytes().to_vec();
+        data.extend_from_slice(line_terminator(self.fd.get_ref())?);
+
+        self.write_all(&data).await
+    }
+
+    async fn send_control_char(&mut self, char: ControlChar) -> io::Result<()> {
+        let bytes = control_char(self.fd.get_ref(), char)?;
+
+        self.write_all(&bytes).await
+    }
+
+    async fn write_all(&self, mut data: &[u8]) -> io::Result<()> {
+        while !data.is_empty() {
+            let written = self.fd.write_with(|fd| write_fd(fd.as_fd(), data)).await?;
+            if written == 0 {
+                return Err(io::ErrorKind::WriteZero.into());
+            }
+            data = &data[written..];
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for TerminalIn {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        send_eof_on_drop(self.fd.get_ref());
+    }
+}
+
+impl AsyncWrite for TerminalIn {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match write_fd(self.fd.get_ref().as_fd(), buf) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                result => return Poll::Ready(result),
+            }
+            ready!(self.fd.poll_writable(cx))?;
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    // Delivers EOF like `close()`, the terminal itself has no write side to shut down.
+    fn poll_close(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.closed {
+            return Poll::Ready(Ok(()));
+        }
+        self.closed = true;
+
+        Poll::Ready(send_control_char(self.fd.get_ref(), ControlChar::Eof))
+    }
+}
+
+/// The output of a terminal.
+pub struct TerminalOut {
+    fd: Async<OwnedFd>,
+    eio_as_eof: bool,
+}
+
+impl TerminalOut {
+    /// Takes over `termout`, output it buffered is lost.
+    pub fn new(termout: crate::TerminalOut) -> io::Result<Self> {
+        Ok(Self {
+            fd: Async::new(OwnedFd::from(termout.into_file()?))?,
+            eio_as_eof: true,
+        })
+    }
+
+    /// Controls whether the `EIO` Linux reports once the last slave is closed is turned into EOF.
+    ///
+    /// Enabled by default, see [`crate::TerminalOut::set_eio_as_eof`].
+    pub fn set_eio_as_eof(&mut self, enabled: bool) {
+        self.eio_as_eof = enabled;
+    }
+}
+
+impl AsyncRead for TerminalOut {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        loop {
+            match read_fd(self.fd.get_ref().as_fd(), buf) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                Err(err) if self.eio_as_eof && is_session_end(&err) => return Poll::Ready(Ok(0)),
+                result => return Poll::Ready(result),
+            }
+            ready!(self.fd.poll_readable(cx))?;
+        }
+    }
+}
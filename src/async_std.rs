@@ -0,0 +1,248 @@
+//! An async terminal for async-std, see [`CommandExt`].
+//!
+//! The halves are backed by [`async_std::fs::File`]s, which run the blocking reads and writes on
+//! async-std's thread pool, waiting for the child is done there as well.
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! use async_std::io::ReadExt;
+//! use pseudoterminal::async_std::CommandExt;
+//!
+//! # async_std::task::block_on(async {
+//! let mut terminal = Command::new("ls").spawn_terminal()?;
+//!
+//! let mut output = String::new();
+//! terminal.termout.as_mut().unwrap().read_to_string(&mut output).await?;
+//! # std::io::Result::Ok(())
+//! # });
+//! ```
+
+use std::io;
+use std::pin::Pin;
+use std::process::{Command, ExitStatus};
+use std::task::{ready, Context, Poll};
+
+use ::async_std::fs::File;
+use ::async_std::io::{Read, Write, WriteExt};
+use ::async_std::task::{self, spawn_blocking};
+
+use crate::sys::{
+    control_char, is_session_end, line_terminator, send_control_char, send_eof_on_drop, ControlChar,
+};
+use crate::{TerminalController, TerminalOptions, TerminalSize};
+
+pub struct Terminal {
+    inner: crate::Terminal,
+    pub termin: Option<TerminalIn>,
+    pub termout: Option<TerminalOut>,
+    /// The child's stderr if `TerminalOptions::separate_stderr` routed it through a pipe.
+    pub termerr: Option<File>,
+}
+
+impl Terminal {
+    /// Moves the I/O of a blocking terminal to async-std, e.g. one the child was attached to.
+    ///
+    /// Halves taken out of `terminal` before stay missing.
+    pub fn new(mut terminal: crate::Terminal) -> io::Result<Self> {
+        let termin = terminal.termin.take().map(TerminalIn::new).transpose()?;
+        let termout = terminal.termout.take().map(TerminalOut::new).transpose()?;
+        let termerr = terminal
+            .termerr
+            .take()
+            .map(|stderr| File::from(std::fs::File::from(stderr_handle(stderr))));
+
+        Ok(Self {
+            inner: terminal,
+            termin,
+            termout,
+            termerr,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> Option<u32> {
+        self.inner.id()
+    }
+
+    /// Returns a handle for resizing, signalling and waiting that can be moved to another task.
+    pub fn controller(&self) -> TerminalController {
+        self.inner.controller()
+    }
+
+    pub fn set_term_size(&mut self, new_size: TerminalSize) -> io::Result<()> {
+        self.inner.set_term_size(new_size)
+    }
+
+    /// Waits for the child to exit, see [`crate::Terminal::wait`].
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        let controller = self.inner.controller();
+
+        spawn_blocking(move || controller.wait()).await
+    }
+
+    /// Delivers EOF to the child, then kills it and waits until it exited.
+    pub async fn close(self) -> io::Result<()> {
+        let Self {
+            inner,
+            termin,
+            termout,
+            termerr,
+        } = self;
+        drop((termin, termout, termerr));
+
+        spawn_blocking(move || inner.close()).await
+    }
+}
+
+#[cfg(unix)]
+fn stderr_handle(stderr: std::process::ChildStderr) -> std::os::fd::OwnedFd {
+    stderr.into()
+}
+
+#[cfg(windows)]
+fn stderr_handle(stderr: std::process::ChildStderr) -> std::os::windows::io::OwnedHandle {
+    stderr.into()
+}
+
+pub trait CommandExt {
+    fn spawn_terminal(&mut self) -> io::Result<Terminal>;
+
+    /// Spawns the command in a new terminal configured by `options`.
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal>;
+}
+
+impl CommandExt for Command {
+    fn spawn_terminal(&mut self) -> io::Result<Terminal> {
+        self.spawn_terminal_with(&TerminalOptions::default())
+    }
+
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal> {
+        Terminal::new(crate::CommandExt::spawn_terminal_with(self, options)?)
+    }
+}
+
+/// The input of a terminal, dropping it flushes and delivers EOF to the child while it reads
+/// lines, see [`crate::TerminalIn`].
+pub struct TerminalIn {
+    file: File,
+    closed: bool,
+}
+
+impl TerminalIn {
+    /// Takes over `termin`, delivering EOF from now on regardless of its clones.
+    pub fn new(termin: crate::TerminalIn) -> io::Result<Self> {
+        Ok(Self {
+            file: termin.into_file()?.into(),
+            closed: false,
+        })
+    }
+
+    /// Flushes the input and delivers EOF to the child, see [`crate::TerminalIn::close`].
+    pub async fn close(mut self) -> io::Result<()> {
+        self.file.flush().await?;
+        self.closed = true;
+
+        send_control_char(&self.file, ControlChar::Eof)
+    }
+
+    /// Sends the terminal's EOF character, see [`crate::TerminalIn::send_eof`].
+    pub async fn send_eof(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Eof).await
+    }
+
+    /// Sends the terminal's interrupt character, see [`crate::TerminalIn::send_interrupt`].
+    pub async fn send_interrupt(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Interrupt).await
+    }
+
+    /// Writes `line` and a line terminator and flushes, see [`crate::TerminalIn::send_line`].
+    pub async fn send_line(&mut self, line: &str) -> io::Result<()> {
+        let mut data = line.as_bytes().to_vec();
+        data.extend_from_slice(line_terminator(&self.file)?);
+        self.file.write_all(&data).await?;
+
+        self.file.flush().await
+    }
+
+    async fn send_control_char(&mut self, char: ControlChar) -> io::Result<()> {
+        let bytes = control_char(&self.file, char)?;
+        self.file.write_all(&bytes).await?;
+
+        self.file.flush().await
+    }
+}
+
+impl Drop for TerminalIn {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        // The file would flush on drop anyway, doing it first keeps the EOF behind the input.
+        let _ = task::block_on(self.file.flush());
+        send_eof_on_drop(&self.file);
+    }
+}
+
+impl Write for TerminalIn {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.file).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.file).poll_flush(cx)
+    }
+
+    // Delivers EOF like `close()`, the terminal itself has no write side to shut down.
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if self.closed {
+            return Poll::Ready(Ok(()));
+        }
+        ready!(Pin::new(&mut self.file).poll_flush(cx))?;
+        self.closed = true;
+
+        Poll::Ready(send_control_char(&self.file, ControlChar::Eof))
+    }
+}
+
+/// The output of a terminal.
+///
+/// A read in progress keeps running on the thread pool even if the output is dropped.
+pub struct TerminalOut {
+    file: File,
+    eio_as_eof: bool,
+}
+
+impl TerminalOut {
+    /// Takes over `termout`, output it buffered is lost.
+    pub fn new(termout: crate::TerminalOut) -> io::Result<Self> {
+        Ok(Self {
+            file: termout.into_file()?.into(),
+            eio_as_eof: true,
+        })
+    }
+
+    /// Controls whether the `EIO` Linux reports once the last slave is closed is turned into EOF.
+    ///
+    /// Enabled by default, see [`crate::TerminalOut::set_eio_as_eof`].
+    pub fn set_eio_as_eof(&mut self, enabled: bool) {
+        self.eio_as_eof = enabled;
+    }
+}
+
+impl Read for TerminalOut {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match Pin::new(&mut self.file).poll_read(cx, buf) {
+            Poll::Ready(Err(err)) if self.eio_as_eof && is_session_end(&err) => Poll::Ready(Ok(0)),
+            poll => poll,
+        }
+    }
+}
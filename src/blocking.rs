@@ -1,105 +1,1914 @@
+use std::fmt;
+#[cfg(any(
+    feature = "async-std",
+    all(unix, feature = "async-io"),
+    all(target_os = "linux", feature = "io-uring")
+))]
 use std::fs::File;
-use std::io::{self, Read, Write};
-use std::process::{Child, Command};
+use std::io::{self, BufRead, Read, Write};
+#[cfg(unix)]
+use std::os::fd::{AsFd, RawFd};
+#[cfg(unix)]
+use std::path::PathBuf;
+#[cfg(unix)]
+use std::process::Stdio;
+use std::process::{Child, ChildStderr, Command, ExitStatus, Output};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+#[cfg(feature = "channels")]
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError, TryLockError};
+use std::thread;
+use std::time::{Duration, Instant};
 
-use crate::sys::{open_handle_and_io, TerminalHandle};
-use crate::TerminalSize;
+#[cfg(feature = "dump")]
+use crate::dump::{Direction, TrafficDump};
+use crate::error::{Context, Stage};
+#[cfg(feature = "keys")]
+use crate::keys::KeyEncoder;
+#[cfg(feature = "screen")]
+use crate::screen::{Screen, SharedScreen, DEFAULT_SIZE};
+#[cfg(feature = "scrollback")]
+use crate::scrollback::Scrollback;
+#[cfg(any(
+    feature = "async-std",
+    all(unix, feature = "async-io"),
+    all(target_os = "linux", feature = "io-uring")
+))]
+use crate::sys::into_file;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use crate::sys::write_all_when_writable;
+use crate::sys::{
+    configure_console, control_char, is_session_end, line_terminator, open_handle_and_io,
+    reap_in_background, send_control_char, send_eof_on_drop, set_input_nonblocking, wait_readable,
+    wait_writable, would_block, ControlChar, Readiness, ResizeFollower, TerminalHandle,
+    TerminalInput, TerminalOutput,
+};
+#[cfg(unix)]
+use crate::sys::{
+    configure_login_shell, interact, process_group_id, send_signal, session_id, ForkedChild,
+    ReadInterrupt,
+};
+#[cfg(feature = "title")]
+use crate::title::TitleWatcher;
+#[cfg(unix)]
+use crate::InteractOptions;
+#[cfg(feature = "utf8")]
+use crate::Utf8Decoder;
+use crate::{CloseBehavior, PtyError, TerminalOptions, TerminalSize};
+
+fn no_child() -> io::Error {
+    io::Error::other("the terminal has no child")
+}
+
+#[cfg(unix)]
+fn reaped() -> io::Error {
+    io::Error::other("the child exited and was reaped")
+}
+
+fn read_timed_out() -> io::Error {
+    io::Error::new(io::ErrorKind::TimedOut, "no output within the read timeout")
+}
+
+#[cfg(unix)]
+fn taken_io() -> io::Error {
+    io::Error::other("the terminal's I/O halves were taken")
+}
+
+// The terminal's child, either spawned from a `Command` or forked by `Terminal::spawn_fn()`.
+enum Process {
+    Command(Child),
+    #[cfg(unix)]
+    Forked(ForkedChild),
+}
+
+impl Process {
+    fn id(&self) -> u32 {
+        match self {
+            Process::Command(child) => child.id(),
+            #[cfg(unix)]
+            Process::Forked(child) => child.id(),
+        }
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        match self {
+            Process::Command(child) => child.wait(),
+            #[cfg(unix)]
+            Process::Forked(child) => child.wait(),
+        }
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match self {
+            Process::Command(child) => child.try_wait(),
+            #[cfg(unix)]
+            Process::Forked(child) => child.try_wait(),
+        }
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        match self {
+            Process::Command(child) => child.kill(),
+            #[cfg(unix)]
+            Process::Forked(child) => child.kill(),
+        }
+    }
+
+    fn take_stderr(&mut self) -> Option<ChildStderr> {
+        match self {
+            Process::Command(child) => child.stderr.take(),
+            #[cfg(unix)]
+            Process::Forked(_) => None,
+        }
+    }
+}
+
+#[cfg(feature = "channels")]
+fn write_chunks(termin: &mut TerminalIn, chunks: mpsc::Receiver<Vec<u8>>) -> io::Result<()> {
+    for chunk in chunks {
+        termin.write_all(&chunk)?;
+        termin.flush()?;
+    }
+
+    // Every sender was dropped.
+    Ok(())
+}
+
+fn read_to_end_in_background(
+    mut reader: impl Read + Send + 'static,
+) -> thread::JoinHandle<io::Result<Vec<u8>>> {
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).map(|_| output)
+    })
+}
+
+// Passes the bytes going through the inner reader or writer to the function.
+#[cfg(all(unix, feature = "expect"))]
+struct Observed<T, F>(T, F);
+
+#[cfg(all(unix, feature = "expect"))]
+impl<T: Write, F: FnMut(&[u8])> Write for Observed<T, F> {
+    // Input is passed on before it's written, so output it causes, like the echo, comes after.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (self.1)(buf);
+        self.0.write_all(buf)?;
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(all(unix, feature = "expect"))]
+impl<T: Read, F: FnMut(&[u8])> Read for Observed<T, F> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.0.read(buf)?;
+        (self.1)(&buf[..read]);
+
+        Ok(read)
+    }
+}
+
+/// The input sender, output receiver and worker handle returned by [`Terminal::into_channels`].
+#[cfg(feature = "channels")]
+pub type TerminalChannels = (
+    mpsc::Sender<Vec<u8>>,
+    mpsc::Receiver<Vec<u8>>,
+    thread::JoinHandle<io::Result<ExitStatus>>,
+);
+
+// The child, shared with the terminal's controllers. Empty for a terminal without a child and
+// once `into_parts()` took it out.
+struct SharedChild {
+    // Zero without a child and once it was reaped, kept outside the lock so signalling never waits
+    // for a `wait()`.
+    pid: AtomicU32,
+    // Kept outside the lock as well, so it can be read while a controller waits.
+    status: Mutex<Option<ExitStatus>>,
+    child: Mutex<Option<Process>>,
+}
+
+impl SharedChild {
+    fn new(child: Option<Process>) -> Arc<Self> {
+        Arc::new(SharedChild {
+            pid: AtomicU32::new(child.as_ref().map_or(0, Process::id)),
+            status: Mutex::new(None),
+            child: Mutex::new(child),
+        })
+    }
+
+    fn id(&self) -> Option<u32> {
+        match self.pid.load(Ordering::Relaxed) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
+
+    // The pid of a reaped child may belong to another process by now, so it isn't handed out.
+    #[cfg(unix)]
+    fn live_id(&self) -> io::Result<u32> {
+        match self.id() {
+            Some(pid) => Ok(pid),
+            None if self.exit_status().is_some() => Err(reaped()),
+            None => Err(no_child()),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Process>> {
+        self.child.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut Process) -> io::Result<T>) -> io::Result<T> {
+        match self.lock().as_mut() {
+            Some(child) => f(child),
+            None => Err(no_child()),
+        }
+    }
+
+    fn wait(&self) -> io::Result<ExitStatus> {
+        let status = self.with(Process::wait)?;
+        self.set_status(Some(status));
+
+        Ok(status)
+    }
+
+    fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        let status = self.with(Process::try_wait)?;
+        if status.is_some() {
+            self.set_status(status);
+        }
+
+        Ok(status)
+    }
+
+    // Never waits for the lock: a wait holding it records the status once the child exited, until
+    // then the child counts as running.
+    fn is_alive(&self) -> bool {
+        if self.id().is_none() {
+            return false;
+        }
+
+        let mut child = match self.child.try_lock() {
+            Ok(child) => child,
+            Err(TryLockError::Poisoned(err)) => err.into_inner(),
+            Err(TryLockError::WouldBlock) => return true,
+        };
+        match child.as_mut().map(Process::try_wait) {
+            Some(Ok(None)) => true,
+            Some(Ok(status)) => {
+                self.set_status(status);
+                false
+            }
+            Some(Err(_)) | None => false,
+        }
+    }
+
+    fn exit_status(&self) -> Option<ExitStatus> {
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_status(&self, status: Option<ExitStatus>) {
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner) = status;
+        if status.is_some() {
+            self.pid.store(0, Ordering::Relaxed);
+        }
+    }
+
+    fn take(&self) -> Option<Process> {
+        let child = self.lock().take();
+        self.pid.store(0, Ordering::Relaxed);
+
+        child
+    }
+}
+
+// Drops with the terminal or its last controller, nothing can wait for a running child after that.
+impl Drop for SharedChild {
+    fn drop(&mut self) {
+        let child = self.child.get_mut().unwrap_or_else(PoisonError::into_inner);
+        if let Some(child) = child {
+            if let Ok(None) = child.try_wait() {
+                reap_in_background(child.id());
+            }
+        }
+    }
+}
+
+// Spawns a command attached to a terminal, making sure the command lets go of the slave.
+fn spawn_child(
+    handle: &TerminalHandle,
+    cmd: &mut Command,
+    options: &TerminalOptions,
+) -> io::Result<Child> {
+    let attachment = handle.attach(cmd, options).stage(PtyError::Spawn)?;
+    let child = cmd
+        .spawn()
+        .context(format_args!("running {:?}", cmd.get_program()));
+    attachment.release(cmd);
+    let child = child.stage(PtyError::Spawn)?;
+
+    configure_console(child.id(), options).stage(PtyError::Spawn)?;
+
+    Ok(child)
+}
 
 pub struct Terminal {
     handle: TerminalHandle,
-    process: Child,
+    process: Arc<SharedChild>,
+    options: TerminalOptions,
     pub termin: Option<TerminalIn>,
     pub termout: Option<TerminalOut>,
+    /// The child's stderr if `TerminalOptions::separate_stderr` routed it through a pipe.
+    pub termerr: Option<ChildStderr>,
+    #[cfg(feature = "screen")]
+    screen: Option<SharedScreen>,
+    #[cfg(feature = "scrollback")]
+    scrollback: Option<Scrollback>,
 }
 
 impl Terminal {
     pub(crate) fn new(
         cmd: &mut Command,
+        options: &TerminalOptions,
         handle: TerminalHandle,
-        (termin, termout): (File, File),
+        (termin, termout): (TerminalInput, TerminalOutput),
     ) -> io::Result<Self> {
-        let process = cmd.spawn()?;
+        let mut process = spawn_child(&handle, cmd, options)?;
 
         Ok(Self {
             handle,
-            process,
-            termin: Some(TerminalIn(termin)),
-            termout: Some(TerminalOut(termout)),
+            termerr: process.stderr.take(),
+            process: SharedChild::new(Some(Process::Command(process))),
+            options: options.clone(),
+            termin: Some(TerminalIn::new(termin)),
+            termout: Some(TerminalOut::new(termout)),
+            #[cfg(feature = "screen")]
+            screen: None,
+            #[cfg(feature = "scrollback")]
+            scrollback: None,
+        })
+    }
+
+    /// Wraps an already open PTY master into a terminal without a child.
+    ///
+    /// # Safety
+    ///
+    /// `fd` has to be an open descriptor owned by the caller, the terminal takes over ownership.
+    #[cfg(unix)]
+    pub unsafe fn from_raw_master(fd: RawFd) -> io::Result<Self> {
+        let (handle, (termin, termout)) =
+            TerminalHandle::from_raw_master(fd).stage(PtyError::Open)?;
+
+        Ok(Self {
+            handle,
+            process: SharedChild::new(None),
+            options: TerminalOptions::default(),
+            termin: Some(TerminalIn::new(termin)),
+            termout: Some(TerminalOut::new(termout)),
+            termerr: None,
+            #[cfg(feature = "screen")]
+            screen: None,
+            #[cfg(feature = "scrollback")]
+            scrollback: None,
         })
     }
 
+    /// Creates a terminal configured by `options` without spawning a child.
+    ///
+    /// A command can be started later with [`attach`](Self::attach), or an external process can
+    /// open the slave itself, see [`slave_name`](Self::slave_name).
+    pub fn open(options: &TerminalOptions) -> io::Result<Self> {
+        let (handle, (termin, termout)) = open_handle_and_io(options).stage(PtyError::Open)?;
+
+        Ok(Self {
+            handle,
+            process: SharedChild::new(None),
+            options: options.clone(),
+            termin: Some(TerminalIn::new(termin)),
+            termout: Some(TerminalOut::new(termout)),
+            termerr: None,
+            #[cfg(feature = "screen")]
+            screen: None,
+            #[cfg(feature = "scrollback")]
+            scrollback: None,
+        })
+    }
+
+    /// Forks a child running `f` on a new terminal of `size` instead of exec'ing a command.
+    ///
+    /// The child's standard streams are connected to the terminal, which also becomes its
+    /// controlling terminal. The value returned by `f` is the child's exit code, a panic
+    /// exits with 101.
+    ///
+    /// # Safety
+    ///
+    /// Only the calling thread is duplicated into the child, `f` must not rely on anything held
+    /// by other threads at the time of the fork, e.g. locks or the allocator in some libcs.
+    #[cfg(unix)]
+    pub unsafe fn spawn_fn(size: TerminalSize, f: impl FnOnce() -> i32) -> io::Result<Self> {
+        let mut options = TerminalOptions::new();
+        options.size(size);
+
+        let (handle, (termin, termout)) = open_handle_and_io(&options).stage(PtyError::Open)?;
+        let child = handle.fork(f).stage(PtyError::Spawn)?;
+
+        Ok(Self {
+            handle,
+            process: SharedChild::new(Some(Process::Forked(child))),
+            options,
+            termin: Some(TerminalIn::new(termin)),
+            termout: Some(TerminalOut::new(termout)),
+            termerr: None,
+            #[cfg(feature = "screen")]
+            screen: None,
+            #[cfg(feature = "scrollback")]
+            scrollback: None,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of the child, `None` without a child and once
+    /// it was reaped.
+    pub fn id(&self) -> Option<u32> {
+        self.process.id()
+    }
+
+    /// Returns the process group of the child, see [`TerminalController::process_group_id`].
+    #[cfg(unix)]
+    pub fn process_group_id(&self) -> io::Result<u32> {
+        process_group_id(self.process.live_id()?)
+    }
+
+    /// Returns the session of the child, see [`TerminalController::session_id`].
+    #[cfg(unix)]
+    pub fn session_id(&self) -> io::Result<u32> {
+        session_id(self.process.live_id()?)
+    }
+
+    /// Returns a handle for resizing, signalling and waiting that can be moved to another thread.
+    pub fn controller(&self) -> TerminalController {
+        TerminalController {
+            handle: self.handle.clone(),
+            process: self.process.clone(),
+        }
+    }
+
+    /// Spawns `cmd` on a terminal without a running child, e.g. one made by [`open`](Self::open).
+    ///
+    /// The options the terminal was created with apply to `cmd` as well. Fails while a previous
+    /// child is still running.
+    ///
+    /// `cmd` can be spawned again afterwards, on this or another terminal.
+    pub fn attach(&mut self, cmd: &mut Command) -> io::Result<()> {
+        let mut slot = self.process.lock();
+
+        if let Some(previous) = slot.as_mut() {
+            if previous.try_wait()?.is_none() {
+                return Err(io::Error::other("the previous child is still running"));
+            }
+        }
+
+        let mut child = spawn_child(&self.handle, cmd, &self.options)?;
+
+        self.termerr = child.stderr.take();
+        self.process.pid.store(child.id(), Ordering::Relaxed);
+        self.process.set_status(None);
+        *slot = Some(Process::Command(child));
+
+        Ok(())
+    }
+
+    /// Spawns `cmd` on the same PTY once the previous child exited, see [`attach`](Self::attach).
+    pub fn respawn(&mut self, cmd: &mut Command) -> io::Result<()> {
+        self.attach(cmd)
+    }
+
     #[cfg(unix)]
     pub fn get_term_size(&mut self) -> io::Result<TerminalSize> {
         self.handle.get_term_size()
     }
 
+    /// Returns the path of the PTY's slave device.
+    #[cfg(unix)]
+    pub fn slave_name(&self) -> io::Result<PathBuf> {
+        self.handle.slave_name()
+    }
+
+    /// Opens a `Stdio` for the terminal's slave, see [`TerminalHandle::slave_stdio()`].
+    #[cfg(unix)]
+    pub fn slave_stdio(&self) -> io::Result<Stdio> {
+        self.handle.slave_stdio()
+    }
+
+    /// Returns the working directory of the terminal's foreground process group leader.
+    #[cfg(unix)]
+    pub fn foreground_cwd(&self) -> io::Result<PathBuf> {
+        self.handle.foreground_cwd()
+    }
+
+    /// Forwards the terminal's output to `fd` with `splice(2)` until the session ends.
+    ///
+    /// The output is moved inside the kernel and never shows up on `termout`. Returns the number
+    /// of bytes transferred. A non-blocking `fd` is waited on while it has no room.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn splice_to(&mut self, fd: impl AsFd) -> io::Result<u64> {
+        // Output `termout` already buffered would be skipped otherwise.
+        let mut buffered = Vec::new();
+        if let Some(termout) = &mut self.termout {
+            termout.take_buffered(&mut buffered);
+        }
+        if !buffered.is_empty() {
+            let mut output = std::fs::File::from(fd.as_fd().try_clone_to_owned()?);
+            write_all_when_writable(&mut output, &buffered)?;
+        }
+
+        Ok(buffered.len() as u64 + self.handle.splice_to(fd.as_fd())?)
+    }
+
+    /// Blocks until the last slave descriptor is closed, usually because the child exited.
+    ///
+    /// An error reported on the master ends the wait as well, reading from it reports the error.
+    #[cfg(unix)]
+    pub fn wait_for_hangup(&self) -> io::Result<()> {
+        self.handle.wait_for_hangup()
+    }
+
     pub fn set_term_size(&mut self, new_size: TerminalSize) -> io::Result<()> {
-        self.handle.set_term_size(new_size)
+        self.handle
+            .set_term_size(new_size)
+            .stage(PtyError::Resize)?;
+
+        // Out of range sizes were rejected above unless the policy clamps them.
+        #[cfg(feature = "screen")]
+        if let Some(screen) = &self.screen {
+            screen.set_size(new_size.clamp());
+        }
+
+        Ok(())
     }
 
-    pub fn close(mut self) -> io::Result<()> {
-        self.process.kill()?;
+    /// Keeps the terminal's size in sync with the terminal of the calling process, until the
+    /// returned follower is dropped.
+    ///
+    /// The size is copied right away and again on every `SIGWINCH` on Unix. On Windows the
+    /// console of the calling process is checked for size changes a few times per second.
+    pub fn follow_host_resize(&self) -> io::Result<ResizeFollower> {
+        ResizeFollower::start(self.handle.clone())
+    }
+
+    /// Records all traffic going through the terminal's input and output into `dump`.
+    #[cfg(feature = "dump")]
+    pub fn set_traffic_dump(&mut self, dump: TrafficDump) {
+        if let Some(termin) = self.termin.as_mut() {
+            termin.dump = Some(dump.clone());
+        }
+        if let Some(termout) = self.termout.as_mut() {
+            termout.dump = Some(dump);
+        }
+    }
+
+    /// Emulates the terminal's screen from now on, fed by everything read from `termout`.
+    ///
+    /// The screen starts out blank at the terminal's size, or 80x24 if it has none, and follows
+    /// [`set_term_size`](Self::set_term_size). Output read before is not on it, see
+    /// [`screen`](Self::screen).
+    #[cfg(feature = "screen")]
+    pub fn enable_screen(&mut self) -> io::Result<()> {
+        #[cfg(unix)]
+        let size = self.get_term_size()?;
+        #[cfg(windows)]
+        let size = self.options.size.unwrap_or(DEFAULT_SIZE);
+
+        let size = if size.rows == 0 || size.columns == 0 {
+            DEFAULT_SIZE
+        } else {
+            size
+        };
+
+        let screen = SharedScreen::new(size);
+        if let Some(termout) = self.termout.as_mut() {
+            termout.screen = Some(screen.clone());
+        }
+        self.screen = Some(screen);
 
         Ok(())
     }
+
+    /// Returns a snapshot of the emulated screen, `None` unless [`enable_screen`] was called.
+    ///
+    /// The screen shows the output read so far, so something has to keep reading `termout`.
+    ///
+    /// [`enable_screen`]: Self::enable_screen
+    #[cfg(feature = "screen")]
+    pub fn screen(&self) -> Option<Screen> {
+        self.screen.as_ref().map(SharedScreen::snapshot)
+    }
+
+    /// Keeps the last `capacity` bytes read from `termout` from now on, see
+    /// [`scrollback`](Self::scrollback).
+    ///
+    /// The oldest output is dropped a line at a time, so the history starts at the beginning of a
+    /// line unless a single line fills all of it. Calling this again starts a new history.
+    #[cfg(feature = "scrollback")]
+    pub fn enable_scrollback(&mut self, capacity: usize) {
+        let scrollback = Scrollback::new(capacity);
+        if let Some(termout) = self.termout.as_mut() {
+            termout.scrollback = Some(scrollback.clone());
+        }
+        self.scrollback = Some(scrollback);
+    }
+
+    /// Returns the output kept since [`enable_scrollback`], `None` unless it was called.
+    ///
+    /// Writing it to a fresh terminal emulator restores what the child drew, e.g. for a client
+    /// that connects to a running session.
+    ///
+    /// [`enable_scrollback`]: Self::enable_scrollback
+    #[cfg(feature = "scrollback")]
+    pub fn scrollback(&self) -> Option<Vec<u8>> {
+        self.scrollback.as_ref().map(Scrollback::contents)
+    }
+
+    /// Calls `callback` with each window title the child sets, see [`crate::title`].
+    ///
+    /// It's called from reads of `termout` that see the title change, and replaces the callback
+    /// set before.
+    #[cfg(feature = "title")]
+    pub fn on_title_change(&mut self, callback: impl FnMut(&str) + Send + 'static) {
+        if let Some(termout) = self.termout.as_mut() {
+            termout.title = Some(TitleWatcher::new(callback));
+        }
+    }
+
+    /// Hands the terminal of the calling process over to the child until its output ends.
+    ///
+    /// The host terminal is put in raw mode, so control keys like Ctrl-C reach the child instead
+    /// of signalling the calling process. Its size is copied to the PTY and kept in sync, and the
+    /// previous mode is restored once the child is done, even if this panics. `SIGTERM` and
+    /// `SIGHUP` restore it too before they terminate the calling process, unless it handles them.
+    ///
+    /// Only available on Unix, there is no Windows console version yet.
+    #[cfg(unix)]
+    pub fn interact(&mut self) -> io::Result<()> {
+        self.interact_with(&mut InteractOptions::new())
+    }
+
+    /// Like [`interact`](Self::interact), handling the control keys as configured by `options`.
+    #[cfg(unix)]
+    pub fn interact_with(&mut self, options: &mut InteractOptions) -> io::Result<()> {
+        let (mut termin, mut termout) = self.split().ok_or_else(taken_io)?;
+
+        let stop = ReadInterrupt::new()?;
+        let interrupt = termout.interrupt.replace(stop.clone());
+        let result = interact(&self.handle, &mut termin, &mut termout, &stop, options);
+        termout.interrupt = interrupt;
+        self.unsplit(termin, termout);
+
+        result
+    }
+
+    // Like `interact()`, passing the forwarded traffic to `on_input` and `on_output` as well.
+    #[cfg(all(unix, feature = "expect"))]
+    pub(crate) fn interact_observed(
+        &mut self,
+        on_input: impl FnMut(&[u8]) + Send,
+        on_output: impl FnMut(&[u8]),
+    ) -> io::Result<()> {
+        let (mut termin, mut termout) = self.split().ok_or_else(taken_io)?;
+
+        let stop = ReadInterrupt::new()?;
+        let interrupt = termout.interrupt.replace(stop.clone());
+        let result = interact(
+            &self.handle,
+            &mut Observed(&mut termin, on_input),
+            &mut Observed(&mut termout, on_output),
+            &stop,
+            &mut InteractOptions::new(),
+        );
+        termout.interrupt = interrupt;
+        self.unsplit(termin, termout);
+
+        result
+    }
+
+    /// Delivers EOF to the child and drops the terminal's input, see [`TerminalIn::close`].
+    ///
+    /// Fails if `termin` was taken, dropping the taken input has the same effect.
+    pub fn close_input(&mut self) -> io::Result<()> {
+        let termin = self
+            .termin
+            .take()
+            .ok_or_else(|| io::Error::other("the terminal's input was taken"))?;
+
+        termin.close()
+    }
+
+    /// Returns the key encoder for the child's `TERM`, see [`TerminalOptions::term`].
+    ///
+    /// Without a `TERM` in the options, the child inherited the one of the calling process.
+    #[cfg(feature = "keys")]
+    pub fn key_encoder(&self) -> KeyEncoder {
+        match &self.options.term {
+            Some(term) => KeyEncoder::for_term(&term.to_string_lossy()),
+            None => KeyEncoder::from_env(),
+        }
+    }
+
+    /// Takes both I/O halves out of the terminal, `None` if either of them was already taken.
+    pub fn split(&mut self) -> Option<(TerminalIn, TerminalOut)> {
+        if self.termin.is_none() || self.termout.is_none() {
+            return None;
+        }
+
+        Some((self.termin.take()?, self.termout.take()?))
+    }
+
+    /// Takes both I/O halves out of the terminal as one duplex stream, see [`split`](Self::split).
+    pub fn stream(&mut self) -> Option<TerminalStream> {
+        self.split()
+            .map(|(termin, termout)| TerminalStream::new(termin, termout))
+    }
+
+    /// Puts the halves returned by [`split`](Self::split) back into the terminal.
+    pub fn unsplit(&mut self, termin: TerminalIn, termout: TerminalOut) {
+        self.termin = Some(termin);
+        self.termout = Some(termout);
+    }
+
+    /// Waits for the child to exit and returns its exit status.
+    ///
+    /// The terminal's output is not drained, a child blocked on writing to a full terminal
+    /// will never exit unless `termout` is read from another thread.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.process.wait()
+    }
+
+    /// Returns the exit status of the child if it has exited, without blocking.
+    pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        self.process.try_wait()
+    }
+
+    /// Returns whether the child is still running, `false` without a child.
+    ///
+    /// Never blocks, also not while a controller waits for the child.
+    pub fn is_alive(&self) -> bool {
+        self.process.is_alive()
+    }
+
+    /// Returns the exit status of the child once it was reaped by a wait on the terminal or one
+    /// of its controllers.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.process.exit_status()
+    }
+
+    /// Waits for the child to exit while collecting everything it writes to the terminal.
+    ///
+    /// The terminal's output ends up in `stdout`, `stderr` stays empty unless it was separated
+    /// with [`TerminalOptions::separate_stderr`]. If `termout` was taken, no output is collected.
+    pub fn wait_with_output(self) -> io::Result<Output> {
+        let (handle, process, (_termin, termout)) = self.into_process_parts();
+        let mut process = process.ok_or_else(no_child)?;
+
+        let reader = termout.map(read_to_end_in_background);
+        let stderr_reader = process.take_stderr().map(read_to_end_in_background);
+
+        let status = process.wait();
+
+        // A pseudoconsole keeps the output open until it is closed, even after the child exited.
+        drop(handle);
+
+        let join = |reader: Option<thread::JoinHandle<io::Result<Vec<u8>>>>| match reader {
+            Some(reader) => reader
+                .join()
+                .map_err(|_| io::Error::other("the output reader panicked"))?,
+            None => Ok(Vec::new()),
+        };
+        let stdout = join(reader)?;
+        let stderr = join(stderr_reader)?;
+
+        Ok(Output {
+            status: status?,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Hands the terminal's I/O to worker threads, returning a sender of chunks to write, a
+    /// receiver of the output and a handle to the worker completing with the child's exit status.
+    ///
+    /// The output can be polled with [`mpsc::Receiver::try_recv`], e.g. from a GUI's main loop.
+    /// Dropping every sender delivers EOF to the child, the receiver disconnects once the output
+    /// ended. A failed write fails the worker, unless the session ended already. Fails if either
+    /// I/O half was taken or there is no child.
+    #[cfg(feature = "channels")]
+    pub fn into_channels(self) -> io::Result<TerminalChannels> {
+        if self.termin.is_none() || self.termout.is_none() {
+            return Err(io::Error::other("the terminal's I/O halves were taken"));
+        }
+        let (handle, process, (termin, termout)) = self.into_process_parts();
+        let mut process = process.ok_or_else(no_child)?;
+        let (mut termin, mut termout) = (termin.unwrap(), termout.unwrap());
+
+        let (input_sender, input_receiver) = mpsc::channel::<Vec<u8>>();
+        let (output_sender, output_receiver) = mpsc::channel();
+
+        // Input sent after the session ended is lost like with any other closed terminal.
+        let writer = thread::spawn(move || match write_chunks(&mut termin, input_receiver) {
+            Err(err) if is_session_end(&err) => Ok(()),
+            result => result.and_then(|()| termin.close()),
+        });
+        let reader = thread::spawn(move || {
+            let mut buf = [0; 8192];
+            loop {
+                let read = match termout.read(&mut buf) {
+                    Ok(0) => return Ok(()),
+                    Ok(read) => read,
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => return Err(err),
+                };
+
+                // Without a receiver the output is still drained, so the child doesn't stall.
+                let _ = output_sender.send(buf[..read].to_vec());
+            }
+        });
+
+        let worker = thread::spawn(move || {
+            let status = process.wait();
+
+            // A pseudoconsole keeps the output open until it is closed.
+            drop(handle);
+
+            reader
+                .join()
+                .map_err(|_| io::Error::other("the output reader panicked"))??;
+            // The writer keeps going as long as a sender is left, only a failure is reported.
+            if writer.is_finished() {
+                writer
+                    .join()
+                    .map_err(|_| io::Error::other("the input writer panicked"))??;
+            }
+
+            status
+        });
+
+        Ok((input_sender, output_receiver, worker))
+    }
+
+    /// Splits the terminal into its handle, the child and the I/O halves.
+    ///
+    /// A child forked by [`spawn_fn`](Self::spawn_fn) is no [`Child`], it is returned as `None`
+    /// and reaped in the background.
+    pub fn into_parts(
+        self,
+    ) -> (
+        TerminalHandle,
+        Option<Child>,
+        (Option<TerminalIn>, Option<TerminalOut>),
+    ) {
+        let (handle, process, io) = self.into_process_parts();
+
+        let process = process.and_then(|process| match process {
+            Process::Command(child) => Some(child),
+            #[cfg(unix)]
+            Process::Forked(mut child) => {
+                if let Ok(None) = child.try_wait() {
+                    reap_in_background(child.id());
+                }
+                None
+            }
+        });
+
+        (handle, process, io)
+    }
+
+    fn into_process_parts(
+        mut self,
+    ) -> (
+        TerminalHandle,
+        Option<Process>,
+        (Option<TerminalIn>, Option<TerminalOut>),
+    ) {
+        // Without the child, `Drop` has nothing left to kill.
+        let mut process = self.process.take();
+
+        // A separated stderr goes back to where `Command::spawn` put it.
+        if let Some(Process::Command(child)) = process.as_mut() {
+            child.stderr = self.termerr.take();
+        }
+
+        (
+            self.handle.clone(),
+            process,
+            (self.termin.take(), self.termout.take()),
+        )
+    }
+
+    /// Kills the child, if there is one, and waits for it to exit.
+    pub fn close(self) -> io::Result<()> {
+        if self.process.id().is_some() {
+            self.close_with(CloseBehavior::Force)?;
+        }
+
+        Ok(())
+    }
+
+    /// Shuts the child down as described by `behavior` and returns its exit status.
+    pub fn close_with(self, behavior: CloseBehavior) -> io::Result<ExitStatus> {
+        // The input is only dropped on return, its EOF would end children like `cat` on its own.
+        let (_handle, process, _io) = self.into_process_parts();
+        let mut process = process.ok_or_else(no_child)?;
+
+        match behavior {
+            CloseBehavior::Force => process.kill()?,
+            CloseBehavior::Graceful { signal, timeout } => {
+                // The pid of a reaped child may belong to another process by now.
+                if let Some(status) = process.try_wait()? {
+                    return Ok(status);
+                }
+
+                #[cfg(unix)]
+                send_signal(process.id(), signal)?;
+                #[cfg(windows)]
+                let _ = signal;
+                #[cfg(windows)]
+                drop(_handle);
+
+                let deadline = Instant::now() + timeout;
+                loop {
+                    if let Some(status) = process.try_wait()? {
+                        return Ok(status);
+                    }
+                    if Instant::now() >= deadline {
+                        break;
+                    }
+                    thread::sleep(Duration::from_millis(10));
+                }
+
+                process.kill()?;
+            }
+            CloseBehavior::WaitOnly => {}
+        }
+
+        process.wait()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        // Otherwise the child is reaped once its last controller is dropped as well.
+        if self.options.kill_on_drop {
+            let _ = self.process.with(Process::kill);
+            let _ = self.process.wait();
+        }
+    }
+}
+
+/// A cloneable handle to a terminal and its child, independent of the terminal's I/O.
+#[derive(Clone)]
+pub struct TerminalController {
+    handle: TerminalHandle,
+    process: Arc<SharedChild>,
+}
+
+impl TerminalController {
+    /// Returns the OS-assigned process identifier of the child, see [`Terminal::id`].
+    pub fn id(&self) -> Option<u32> {
+        self.process.id()
+    }
+
+    #[cfg(unix)]
+    pub fn get_term_size(&self) -> io::Result<TerminalSize> {
+        self.handle.get_term_size()
+    }
+
+    pub fn set_term_size(&self, new_size: TerminalSize) -> io::Result<()> {
+        self.handle.set_term_size(new_size).stage(PtyError::Resize)
+    }
+
+    /// Sends `signal` to the child, fails once the child was reaped.
+    #[cfg(unix)]
+    pub fn signal(&self, signal: i32) -> io::Result<()> {
+        send_signal(self.process.live_id()?, signal)
+    }
+
+    /// Returns the process group of the child.
+    ///
+    /// Equals the child's pid while it leads its own session, which it does with a controlling
+    /// terminal. Fails once the child was reaped.
+    #[cfg(unix)]
+    pub fn process_group_id(&self) -> io::Result<u32> {
+        process_group_id(self.process.live_id()?)
+    }
+
+    /// Returns the session of the child, fails once the child was reaped.
+    #[cfg(unix)]
+    pub fn session_id(&self) -> io::Result<u32> {
+        session_id(self.process.live_id()?)
+    }
+
+    /// Waits for the child to exit and returns its exit status.
+    ///
+    /// Other calls needing the child, on the terminal or any controller, block until this returns.
+    /// Fails once the child was taken out with [`Terminal::into_parts`].
+    pub fn wait(&self) -> io::Result<ExitStatus> {
+        self.process.wait()
+    }
+
+    /// Returns the exit status of the child if it has exited, without blocking.
+    pub fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        self.process.try_wait()
+    }
+
+    /// Returns whether the child is still running, see [`Terminal::is_alive`].
+    pub fn is_alive(&self) -> bool {
+        self.process.is_alive()
+    }
+
+    /// Returns the exit status of the child once it was reaped, see [`Terminal::exit_status`].
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.process.exit_status()
+    }
 }
 
 pub trait CommandExt {
     fn spawn_terminal(&mut self) -> io::Result<Terminal>;
+
+    /// Spawns the command in a new terminal configured by `options`.
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal>;
+
+    /// Spawns the command as a login shell of the current user.
+    ///
+    /// `argv[0]` is prefixed with `-` and `HOME`, `SHELL`, `USER`, `LOGNAME` and `TERM` are
+    /// filled in from the passwd entry unless they are already set on the command.
+    #[cfg(unix)]
+    fn spawn_login_terminal(&mut self) -> io::Result<Terminal>;
 }
 
 impl CommandExt for Command {
     fn spawn_terminal(&mut self) -> io::Result<Terminal> {
-        let (handle, (termin, termout)) = open_handle_and_io(self)?;
+        self.spawn_terminal_with(&TerminalOptions::default())
+    }
+
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal> {
+        let (handle, (termin, termout)) = open_handle_and_io(options).stage(PtyError::Open)?;
 
-        Terminal::new(self, handle, (termin, termout))
+        Terminal::new(self, options, handle, (termin, termout))
     }
+
+    #[cfg(unix)]
+    fn spawn_login_terminal(&mut self) -> io::Result<Terminal> {
+        configure_login_shell(self)?;
+
+        self.spawn_terminal()
+    }
+}
+
+/// The input of a terminal, dropping it and all its clones delivers EOF to the child while it
+/// reads lines, see [`close`](Self::close).
+///
+/// A child in raw mode would read the EOF character as input, so dropping leaves it alone on Unix,
+/// close the input explicitly to send the character anyway.
+pub struct TerminalIn {
+    file: TerminalInput,
+    // The number of inputs sharing the terminal, the last one dropped delivers EOF.
+    open: Arc<AtomicUsize>,
+    closed: bool,
+    write_timeout: Option<Duration>,
+    nonblocking: bool,
+    #[cfg(feature = "dump")]
+    dump: Option<TrafficDump>,
 }
 
-pub struct TerminalIn(File);
+impl TerminalIn {
+    fn new(file: TerminalInput) -> Self {
+        Self {
+            file,
+            open: Arc::new(AtomicUsize::new(1)),
+            closed: false,
+            write_timeout: None,
+            nonblocking: false,
+            #[cfg(feature = "dump")]
+            dump: None,
+        }
+    }
+
+    /// Returns a new input writing to the same terminal through a duplicated descriptor.
+    ///
+    /// Dropping the input delivers EOF only once each of its clones is dropped as well.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            file: self.file.try_clone()?,
+            open: {
+                self.open.fetch_add(1, Ordering::Relaxed);
+                self.open.clone()
+            },
+            closed: false,
+            write_timeout: self.write_timeout,
+            nonblocking: self.nonblocking,
+            #[cfg(feature = "dump")]
+            dump: self.dump.clone(),
+        })
+    }
+
+    /// Closes the input, the child reads EOF once it consumed everything written before.
+    ///
+    /// Unlike dropping, this delivers the EOF right away even if clones of the input are open.
+    /// A PTY can't be half-closed, so this sends the terminal's EOF character instead: Ctrl-D
+    /// unless the child configured another one on Unix, Ctrl-Z and Enter on Windows. On Unix the
+    /// first EOF only hands over a pending partial line, so finish the line first.
+    pub fn close(mut self) -> io::Result<()> {
+        self.closed = true;
+        self.open.fetch_sub(1, Ordering::AcqRel);
+
+        send_control_char(&self.file, ControlChar::Eof)
+    }
+
+    /// Sends the terminal's EOF character, Ctrl-D unless the child configured another one.
+    ///
+    /// Unlike [`close`](Self::close), the input stays usable, e.g. to leave a nested shell.
+    pub fn send_eof(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Eof)
+    }
+
+    /// Sends the terminal's interrupt character, Ctrl-C unless the child configured another one.
+    ///
+    /// The terminal turns it into `SIGINT` for its foreground process group, unless the child
+    /// disabled signal characters, e.g. by switching to raw mode.
+    pub fn send_interrupt(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Interrupt)
+    }
+
+    /// Writes `line` followed by whatever ends a line in the terminal's current mode and flushes.
+    ///
+    /// That is the carriage return the Enter key sends, or a newline if the terminal reads whole
+    /// lines without translating carriage returns.
+    pub fn send_line(&mut self, line: &str) -> io::Result<()> {
+        let mut data = line.as_bytes().to_vec();
+        data.extend_from_slice(line_terminator(&self.file)?);
+        self.write_all(&data)?;
+
+        self.flush()
+    }
+
+    fn send_control_char(&mut self, char: ControlChar) -> io::Result<()> {
+        let bytes = control_char(&self.file, char)?;
+        self.write_all(&bytes)?;
+
+        self.flush()
+    }
+
+    /// Makes writes fail with [`io::ErrorKind::TimedOut`] once the child took no input for
+    /// `timeout`, e.g. because it was stopped or the terminal was paused with Ctrl-S.
+    ///
+    /// `None`, the default, blocks until the child reads. Like
+    /// [`TcpStream`](std::net::TcpStream), a zero timeout is rejected. A timeout switches the
+    /// terminal to non-blocking I/O for good, which the terminal's output handles transparently.
+    pub fn set_write_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        if timeout == Some(Duration::ZERO) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot set a 0 duration timeout",
+            ));
+        }
+
+        if timeout.is_some() {
+            self.set_nonblocking()?;
+        }
+        self.write_timeout = timeout;
+
+        Ok(())
+    }
+
+    /// Returns the timeout set by [`set_write_timeout`](Self::set_write_timeout).
+    pub fn write_timeout(&self) -> Option<Duration> {
+        self.write_timeout
+    }
+
+    /// Waits until the terminal takes more input, returns `false` if `timeout` elapsed first.
+    ///
+    /// Pairs with [`try_write`](Self::try_write) for non-blocking I/O without an async runtime.
+    /// Pipes report no such readiness on Windows, there this only paces retries for up to 10ms.
+    pub fn wait_writable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        wait_writable(&self.file, timeout)
+    }
+
+    /// Writes as much of `buf` as the terminal takes right away, failing with
+    /// [`io::ErrorKind::WouldBlock`] if it takes nothing.
+    ///
+    /// Switches the terminal to non-blocking I/O, see [`set_write_timeout`](Self::set_write_timeout).
+    pub fn try_write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.set_nonblocking()?;
+
+        let written = would_block(self.file.write(buf), buf.len())?;
+        self.record(&buf[..written]);
+
+        Ok(written)
+    }
+
+    // Hands the descriptor over to whoever delivers EOF from now on.
+    #[cfg(any(
+        feature = "async-std",
+        all(unix, feature = "async-io"),
+        all(target_os = "linux", feature = "io-uring")
+    ))]
+    pub(crate) fn into_file(mut self) -> io::Result<File> {
+        self.closed = true;
+        self.open.fetch_sub(1, Ordering::AcqRel);
+
+        into_file(self.file.try_clone()?)
+    }
+
+    fn set_nonblocking(&mut self) -> io::Result<()> {
+        if !self.nonblocking {
+            set_input_nonblocking(&self.file)?;
+            self.nonblocking = true;
+        }
+
+        Ok(())
+    }
+
+    // Retries `write` until the terminal takes input, at most for the write timeout. The
+    // descriptor can be non-blocking even without a timeout, if a clone switched it.
+    fn write_when_ready(
+        &mut self,
+        len: usize,
+        mut write: impl FnMut(&mut TerminalInput) -> io::Result<usize>,
+    ) -> io::Result<usize> {
+        let deadline = self.write_timeout.map(|timeout| Instant::now() + timeout);
+
+        loop {
+            match would_block(write(&mut self.file), len) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {}
+                result => return result,
+            }
+
+            let timeout =
+                deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+            if !wait_writable(&self.file, timeout)? {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    "the terminal took no input within the write timeout",
+                ));
+            }
+        }
+    }
+
+    #[cfg(feature = "dump")]
+    fn record(&self, data: &[u8]) {
+        if let Some(dump) = &self.dump {
+            dump.record(Direction::Input, data);
+        }
+    }
+
+    #[cfg(not(feature = "dump"))]
+    fn record(&self, _data: &[u8]) {}
+}
+
+impl Drop for TerminalIn {
+    fn drop(&mut self) {
+        if self.closed || self.open.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+
+        send_eof_on_drop(&self.file);
+    }
+}
 
 impl Write for TerminalIn {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
-        self.0.write(buf)
+        let written = self.write_when_ready(buf.len(), |file| file.write(buf))?;
+        self.record(&buf[..written]);
+
+        Ok(written)
     }
 
     fn flush(&mut self) -> io::Result<()> {
-        self.0.flush()
+        self.file.flush()
     }
 
     fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
-        self.0.write_vectored(bufs)
+        let len = bufs.iter().map(|buf| buf.len()).sum();
+        let written = self.write_when_ready(len, |file| file.write_vectored(bufs))?;
+
+        let mut remaining = written;
+        for buf in bufs {
+            let len = remaining.min(buf.len());
+            self.record(&buf[..len]);
+            remaining -= len;
+        }
+
+        Ok(written)
     }
+}
 
-    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
-        self.0.write_all(buf)
+/// The output of a terminal.
+///
+/// Implements [`BufRead`] to peek at pending output, buffered bytes are returned by every later
+/// read first.
+pub struct TerminalOut {
+    file: TerminalOutput,
+    // Output read by `fill_buf()`, the bytes before `pos` have been consumed.
+    buffer: Vec<u8>,
+    pos: usize,
+    eio_as_eof: bool,
+    read_timeout: Option<Duration>,
+    #[cfg(unix)]
+    interrupt: Option<ReadInterrupt>,
+    #[cfg(feature = "dump")]
+    dump: Option<TrafficDump>,
+    #[cfg(feature = "screen")]
+    screen: Option<SharedScreen>,
+    #[cfg(feature = "scrollback")]
+    scrollback: Option<Scrollback>,
+    #[cfg(feature = "title")]
+    title: Option<TitleWatcher>,
+}
+
+impl TerminalOut {
+    fn new(file: TerminalOutput) -> Self {
+        Self {
+            file,
+            buffer: Vec::new(),
+            pos: 0,
+            eio_as_eof: true,
+            read_timeout: None,
+            #[cfg(unix)]
+            interrupt: None,
+            #[cfg(feature = "dump")]
+            dump: None,
+            #[cfg(feature = "screen")]
+            screen: None,
+            #[cfg(feature = "scrollback")]
+            scrollback: None,
+            #[cfg(feature = "title")]
+            title: None,
+        }
     }
 
-    fn write_fmt(&mut self, fmt: std::fmt::Arguments<'_>) -> io::Result<()> {
-        self.0.write_fmt(fmt)
+    /// Returns a new output reading from the same terminal through a duplicated descriptor.
+    ///
+    /// Both share a single stream, every byte is read by only one of them. Neither buffered
+    /// output nor read interrupters are shared with the clone.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            file: self.file.try_clone()?,
+            buffer: Vec::new(),
+            pos: 0,
+            eio_as_eof: self.eio_as_eof,
+            read_timeout: self.read_timeout,
+            #[cfg(unix)]
+            interrupt: None,
+            #[cfg(feature = "dump")]
+            dump: self.dump.clone(),
+            #[cfg(feature = "screen")]
+            screen: self.screen.clone(),
+            #[cfg(feature = "scrollback")]
+            scrollback: self.scrollback.clone(),
+            #[cfg(feature = "title")]
+            title: self.title.clone(),
+        })
+    }
+
+    /// Returns a handle that can cancel a read blocked on this output from another thread.
+    ///
+    /// Once a handle exists, every read first waits for the terminal to become readable.
+    #[cfg(unix)]
+    pub fn read_interrupter(&mut self) -> io::Result<ReadInterrupter> {
+        let interrupt = match &self.interrupt {
+            Some(interrupt) => interrupt.clone(),
+            None => self.interrupt.insert(ReadInterrupt::new()?).clone(),
+        };
+
+        Ok(ReadInterrupter(interrupt))
+    }
+
+    /// Makes reads fail with [`io::ErrorKind::TimedOut`] once no output arrived for `timeout`.
+    ///
+    /// `None`, the default, blocks until there is output. Like [`TcpStream`](std::net::TcpStream),
+    /// a zero timeout is rejected.
+    pub fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        if timeout == Some(Duration::ZERO) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "cannot set a 0 duration timeout",
+            ));
+        }
+
+        self.read_timeout = timeout;
+
+        Ok(())
+    }
+
+    /// Returns the timeout set by [`set_read_timeout`](Self::set_read_timeout).
+    pub fn read_timeout(&self) -> Option<Duration> {
+        self.read_timeout
+    }
+
+    /// Appends all output that is ready to `buf` without blocking, returns the number of bytes
+    /// read.
+    ///
+    /// Returns 0 if there is no output pending, or once the output ended.
+    pub fn read_available(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let mut chunk = [0; 4096];
+        let mut total = self.take_buffered(buf);
+
+        while self.is_readable()? {
+            let read = match self.file.read(&mut chunk) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => break,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                result => self.map_session_end(result)?,
+            };
+            if read == 0 {
+                break;
+            }
+
+            self.record(&chunk[..read]);
+            buf.extend_from_slice(&chunk[..read]);
+            total += read;
+        }
+
+        Ok(total)
+    }
+
+    /// Waits until reading won't block, returns `false` if `timeout` elapsed first.
+    ///
+    /// Output that is already buffered, the end of the output and errors count as readable. On
+    /// Unix a [`ReadInterrupter`] cancels the wait with a [`ReadInterrupted`] error.
+    #[cfg(unix)]
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        if self.pos < self.buffer.len() {
+            return Ok(true);
+        }
+
+        match wait_readable(self.file.as_fd(), self.interrupt.as_ref(), timeout)? {
+            Readiness::Readable => Ok(true),
+            Readiness::Interrupted => Err(io::Error::other(ReadInterrupted)),
+            Readiness::TimedOut => Ok(false),
+        }
+    }
+
+    /// Waits until reading won't block, returns `false` if `timeout` elapsed first.
+    ///
+    /// Output that is already buffered, the end of the output and errors count as readable.
+    #[cfg(not(unix))]
+    pub fn wait_readable(&self, timeout: Option<Duration>) -> io::Result<bool> {
+        if self.pos < self.buffer.len() {
+            return Ok(true);
+        }
+
+        Ok(wait_readable(&self.file, timeout)? == Readiness::Readable)
+    }
+
+    #[cfg(unix)]
+    fn is_readable(&self) -> io::Result<bool> {
+        Ok(wait_readable(self.file.as_fd(), None, Some(Duration::ZERO))? == Readiness::Readable)
+    }
+
+    #[cfg(not(unix))]
+    fn is_readable(&self) -> io::Result<bool> {
+        Ok(wait_readable(&self.file, Some(Duration::ZERO))? == Readiness::Readable)
+    }
+
+    /// Returns an iterator over the lines of the output, without their line endings.
+    ///
+    /// A `\r` right before the `\n` is dropped as well, see [`lines_with`](Self::lines_with) to
+    /// split differently. Shadows [`BufRead::lines`], which keeps the `\r`.
+    pub fn lines(&mut self) -> Lines<'_> {
+        self.lines_with(LineEndings::default())
+    }
+
+    /// Returns an iterator over the lines of the output, split as described by `endings`.
+    pub fn lines_with(&mut self, endings: LineEndings) -> Lines<'_> {
+        Lines {
+            termout: self,
+            splitter: LineSplitter::new(endings),
+        }
+    }
+
+    /// Returns an iterator over the output decoded as UTF-8, one chunk per read.
+    ///
+    /// Unlike decoding each read on its own, characters split across reads are kept whole.
+    /// Invalid sequences are replaced, see [`utf8_chunks_with`](Self::utf8_chunks_with) to fail
+    /// on them instead.
+    #[cfg(feature = "utf8")]
+    pub fn utf8_chunks(&mut self) -> Utf8Chunks<'_> {
+        self.utf8_chunks_with(Utf8Decoder::new())
+    }
+
+    /// Returns an iterator over the output decoded by `decoder`.
+    #[cfg(feature = "utf8")]
+    pub fn utf8_chunks_with(&mut self, decoder: Utf8Decoder) -> Utf8Chunks<'_> {
+        Utf8Chunks {
+            termout: self,
+            decoder,
+            finished: false,
+        }
+    }
+
+    // Moves the buffered output to `buf`.
+    fn take_buffered(&mut self, buf: &mut Vec<u8>) -> usize {
+        let buffered = &self.buffer[self.pos..];
+        buf.extend_from_slice(buffered);
+
+        let len = buffered.len();
+        self.consume(len);
+
+        len
+    }
+
+    // Reads once the terminal has output. The descriptor is non-blocking once the terminal's
+    // input switched it, so reads failing with `WouldBlock` wait for readiness as well.
+    fn read_when_ready(
+        &mut self,
+        mut read: impl FnMut(&mut TerminalOutput) -> io::Result<usize>,
+    ) -> io::Result<usize> {
+        self.wait_for_output(false)?;
+
+        loop {
+            match read(&mut self.file) {
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    self.wait_for_output(true)?
+                }
+                result => return self.map_session_end(result),
+            }
+        }
+    }
+
+    // Only waits with an interrupter or timeout, or if `always` is set.
+    #[cfg(unix)]
+    fn wait_for_output(&self, always: bool) -> io::Result<()> {
+        if !always && self.interrupt.is_none() && self.read_timeout.is_none() {
+            return Ok(());
+        }
+
+        match wait_readable(
+            self.file.as_fd(),
+            self.interrupt.as_ref(),
+            self.read_timeout,
+        )? {
+            Readiness::Readable => Ok(()),
+            Readiness::Interrupted => Err(io::Error::other(ReadInterrupted)),
+            Readiness::TimedOut => Err(read_timed_out()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    fn wait_for_output(&self, always: bool) -> io::Result<()> {
+        if !always && self.read_timeout.is_none() {
+            return Ok(());
+        }
+
+        match wait_readable(&self.file, self.read_timeout)? {
+            Readiness::Readable => Ok(()),
+            Readiness::TimedOut => Err(read_timed_out()),
+        }
+    }
+
+    /// Controls whether the `EIO` Linux reports once the last slave is closed is turned into EOF.
+    ///
+    /// Enabled by default, so reading until EOF behaves the same on every platform.
+    pub fn set_eio_as_eof(&mut self, enabled: bool) {
+        self.eio_as_eof = enabled;
+    }
+
+    // Output buffered by `fill_buf()` is lost.
+    #[cfg(any(
+        feature = "async-std",
+        all(unix, feature = "async-io"),
+        all(target_os = "linux", feature = "io-uring")
+    ))]
+    pub(crate) fn into_file(self) -> io::Result<File> {
+        into_file(self.file)
+    }
+
+    fn map_session_end(&self, result: io::Result<usize>) -> io::Result<usize> {
+        match result {
+            Err(err) if self.eio_as_eof && is_session_end(&err) => Ok(0),
+            result => result,
+        }
+    }
+
+    #[cfg(any(
+        feature = "dump",
+        feature = "screen",
+        feature = "scrollback",
+        feature = "title"
+    ))]
+    fn record(&self, data: &[u8]) {
+        #[cfg(feature = "dump")]
+        if let Some(dump) = &self.dump {
+            dump.record(Direction::Output, data);
+        }
+        #[cfg(feature = "screen")]
+        if let Some(screen) = &self.screen {
+            screen.process(data);
+        }
+        #[cfg(feature = "scrollback")]
+        if let Some(scrollback) = &self.scrollback {
+            scrollback.record(data);
+        }
+        #[cfg(feature = "title")]
+        if let Some(title) = &self.title {
+            title.feed(data);
+        }
     }
-}
 
-pub struct TerminalOut(File);
+    #[cfg(not(any(
+        feature = "dump",
+        feature = "screen",
+        feature = "scrollback",
+        feature = "title"
+    )))]
+    fn record(&self, _data: &[u8]) {}
+}
 
 impl Read for TerminalOut {
     fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        self.0.read(buf)
+        if self.pos < self.buffer.len() {
+            let read = (&self.buffer[self.pos..]).read(buf)?;
+            self.consume(read);
+            return Ok(read);
+        }
+
+        let read = self.read_when_ready(|file| file.read(buf))?;
+        self.record(&buf[..read]);
+
+        Ok(read)
     }
 
     fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
-        self.0.read_vectored(bufs)
+        if self.pos < self.buffer.len() {
+            let read = (&self.buffer[self.pos..]).read_vectored(bufs)?;
+            self.consume(read);
+            return Ok(read);
+        }
+
+        let read = self.read_when_ready(|file| file.read_vectored(bufs))?;
+
+        let mut remaining = read;
+        for buf in bufs.iter() {
+            let len = remaining.min(buf.len());
+            self.record(&buf[..len]);
+            remaining -= len;
+        }
+
+        Ok(read)
     }
+}
+
+impl BufRead for TerminalOut {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        const BUFFER_SIZE: usize = 8192;
+
+        if self.pos == self.buffer.len() {
+            let mut buffer = std::mem::take(&mut self.buffer);
+            buffer.resize(BUFFER_SIZE, 0);
 
-    fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
-        self.0.read_to_end(buf)
+            let read = self.read_when_ready(|file| file.read(&mut buffer));
+            buffer.truncate(*read.as_ref().unwrap_or(&0));
+            self.buffer = buffer;
+            self.pos = 0;
+
+            read?;
+            self.record(&self.buffer);
+        }
+
+        Ok(&self.buffer[self.pos..])
     }
 
-    fn read_to_string(&mut self, buf: &mut String) -> io::Result<usize> {
-        self.0.read_to_string(buf)
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.buffer.len());
     }
+}
 
-    fn read_exact(&mut self, buf: &mut [u8]) -> io::Result<()> {
-        self.0.read_exact(buf)
+/// Where [`Lines`] splits the terminal's output.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LineEndings {
+    /// Lines end at `\n`, dropping the `\r` a terminal usually puts before it.
+    #[default]
+    Newline,
+    /// Lines end at `\n`, `\r\n` or a lone `\r`, e.g. to see each redraw of a progress bar.
+    Any,
+    /// Lines end at `\n`, carriage returns are kept.
+    Raw,
+}
+
+/// An iterator over the lines of a terminal's output, see [`TerminalOut::lines`].
+///
+/// Lines are decoded as UTF-8, replacing invalid sequences. A line that was pending when a
+/// read failed, e.g. because of a read timeout, is completed by the next call.
+pub struct Lines<'a> {
+    termout: &'a mut TerminalOut,
+    splitter: LineSplitter,
+}
+
+impl Iterator for Lines<'_> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let buf = match self.termout.fill_buf() {
+                Ok(buf) => buf,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err)),
+            };
+
+            if buf.is_empty() {
+                return self.splitter.finish().map(Ok);
+            }
+
+            let (consumed, line) = self.splitter.split(buf);
+            self.termout.consume(consumed);
+
+            if let Some(line) = line {
+                return Some(Ok(line));
+            }
+        }
     }
 }
+
+/// An iterator over the output of a terminal decoded as UTF-8, see
+/// [`TerminalOut::utf8_chunks`].
+///
+/// Chunks are never empty. A character that was pending when the output ended is decoded last.
+#[cfg(feature = "utf8")]
+pub struct Utf8Chunks<'a> {
+    termout: &'a mut TerminalOut,
+    decoder: Utf8Decoder,
+    finished: bool,
+}
+
+#[cfg(feature = "utf8")]
+impl Iterator for Utf8Chunks<'_> {
+    type Item = io::Result<String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.finished {
+            let buf = match self.termout.fill_buf() {
+                Ok(buf) => buf,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Some(Err(err)),
+            };
+
+            let decoded = if buf.is_empty() {
+                let finished = self.decoder.finish();
+                self.finished = finished.is_ok();
+                finished
+            } else {
+                let len = buf.len();
+                let decoded = self.decoder.decode(buf);
+                self.termout.consume(len);
+                decoded
+            };
+
+            match decoded {
+                Ok(text) if text.is_empty() => {}
+                result => return Some(result),
+            }
+        }
+
+        None
+    }
+}
+
+// Splits output into lines as described by `LineEndings`, for the blocking and async lines.
+pub(crate) struct LineSplitter {
+    endings: LineEndings,
+    pending: Vec<u8>,
+    // The last line ended at a `\r`, a `\n` right after it belongs to that line.
+    after_cr: bool,
+}
+
+impl LineSplitter {
+    pub(crate) fn new(endings: LineEndings) -> Self {
+        Self {
+            endings,
+            pending: Vec::new(),
+            after_cr: false,
+        }
+    }
+
+    // Takes output from the start of the non-empty `buf`, returns how much of it was consumed
+    // and the line completed by it.
+    pub(crate) fn split(&mut self, buf: &[u8]) -> (usize, Option<String>) {
+        if std::mem::take(&mut self.after_cr) && buf[0] == b'\n' {
+            return (1, None);
+        }
+
+        let end = match self.endings {
+            LineEndings::Any => buf.iter().position(|&b| b == b'\n' || b == b'\r'),
+            LineEndings::Newline | LineEndings::Raw => buf.iter().position(|&b| b == b'\n'),
+        };
+        let Some(end) = end else {
+            self.pending.extend_from_slice(buf);
+            return (buf.len(), None);
+        };
+
+        self.pending.extend_from_slice(&buf[..end]);
+        self.after_cr = buf[end] == b'\r';
+
+        if self.endings == LineEndings::Newline && self.pending.ends_with(b"\r") {
+            self.pending.pop();
+        }
+
+        (end + 1, Some(self.take_line()))
+    }
+
+    // The output ended, a last line may lack its line ending.
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        (!self.pending.is_empty()).then(|| self.take_line())
+    }
+
+    fn take_line(&mut self) -> String {
+        let line = std::mem::take(&mut self.pending);
+
+        String::from_utf8(line)
+            .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+    }
+}
+
+/// Both halves of a terminal's I/O in one duplex stream, for APIs expecting `Read + Write`.
+pub struct TerminalStream {
+    termin: TerminalIn,
+    termout: TerminalOut,
+}
+
+impl TerminalStream {
+    pub fn new(termin: TerminalIn, termout: TerminalOut) -> Self {
+        Self { termin, termout }
+    }
+
+    /// Splits the stream back into its halves, e.g. for [`Terminal::unsplit`].
+    pub fn into_split(self) -> (TerminalIn, TerminalOut) {
+        (self.termin, self.termout)
+    }
+}
+
+impl Read for TerminalStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.termout.read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        self.termout.read_vectored(bufs)
+    }
+}
+
+impl BufRead for TerminalStream {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.termout.fill_buf()
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.termout.consume(amt)
+    }
+}
+
+impl Write for TerminalStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.termin.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.termin.flush()
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        self.termin.write_vectored(bufs)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.termin.write_all(buf)
+    }
+}
+
+/// Cancels reads of a [`TerminalOut`], see [`TerminalOut::read_interrupter`].
+#[cfg(unix)]
+#[derive(Clone)]
+pub struct ReadInterrupter(ReadInterrupt);
+
+#[cfg(unix)]
+impl ReadInterrupter {
+    /// Makes the pending read, or the next one if none is pending, fail with [`ReadInterrupted`].
+    pub fn interrupt_read(&self) -> io::Result<()> {
+        self.0.interrupt()
+    }
+}
+
+/// The error a read cancelled by [`ReadInterrupter::interrupt_read`] fails with.
+///
+/// Reported with [`io::ErrorKind::Other`] rather than `Interrupted`, which `read_exact` and
+/// friends would silently retry. Check for it with
+/// `err.get_ref().is_some_and(|err| err.is::<ReadInterrupted>())`.
+#[derive(Debug)]
+pub struct ReadInterrupted;
+
+impl fmt::Display for ReadInterrupted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("read interrupted")
+    }
+}
+
+impl std::error::Error for ReadInterrupted {}
@@ -0,0 +1,38 @@
+use std::fmt::{self, Write};
+
+/// Renders terminal bytes with control characters shown symbolically.
+///
+/// Escape is shown as `ESC`, other C0 controls in caret notation (`^C`), and bytes outside of
+/// ASCII as `\xNN`, so `b"\x1b[2J\x03"` renders as `ESC[2J^C`. Carriage returns, line feeds and
+/// tabs keep their familiar `\r`, `\n` and `\t` escapes.
+#[derive(Clone, Copy)]
+pub struct DebugBytes<'a>(pub &'a [u8]);
+
+impl fmt::Display for DebugBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for &byte in self.0 {
+            match byte {
+                b'\x1b' => f.write_str("ESC")?,
+                b'\r' => f.write_str("\\r")?,
+                b'\n' => f.write_str("\\n")?,
+                b'\t' => f.write_str("\\t")?,
+                b'\\' => f.write_str("\\\\")?,
+                0x00..=0x1f => {
+                    f.write_char('^')?;
+                    f.write_char((byte + b'@') as char)?;
+                }
+                0x7f => f.write_str("^?")?,
+                0x20..=0x7e => f.write_char(byte as char)?,
+                _ => write!(f, "\\x{byte:02x}")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for DebugBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "\"{self}\"")
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::DebugBytes;
+
+const MAGIC: &[u8; 8] = b"PTYDUMP1";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Bytes written to the terminal.
+    Input,
+    /// Bytes read from the terminal.
+    Output,
+}
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct Record {
+    pub direction: Direction,
+    /// Time since the Unix epoch at which the bytes went through the terminal.
+    pub timestamp: Duration,
+    pub data: Vec<u8>,
+}
+
+impl fmt::Debug for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Record")
+            .field("direction", &self.direction)
+            .field("timestamp", &self.timestamp)
+            .field("data", &DebugBytes(&self.data))
+            .finish()
+    }
+}
+
+/// A shared sink recording terminal traffic.
+///
+/// Each record is stored as a direction byte, the timestamp in microseconds as a little endian
+/// `u64`, the payload length as a little endian `u32` and the payload itself, following an
+/// eight byte file header. Use [`decode`] to read a dump back.
+#[derive(Clone)]
+pub struct TrafficDump(Arc<Mutex<Box<dyn Write + Send>>>);
+
+impl TrafficDump {
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+
+    pub fn new(writer: impl Write + Send + 'static) -> io::Result<Self> {
+        let mut writer: Box<dyn Write + Send> = Box::new(writer);
+        writer.write_all(MAGIC)?;
+
+        Ok(Self(Arc::new(Mutex::new(writer))))
+    }
+
+    pub(crate) fn record(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+
+        let mut header = [0; 13];
+        header[0] = match direction {
+            Direction::Input => 0,
+            Direction::Output => 1,
+        };
+        header[1..9].copy_from_slice(&(timestamp.as_micros() as u64).to_le_bytes());
+        header[9..].copy_from_slice(&(data.len() as u32).to_le_bytes());
+
+        // Dumping is a debugging aid, failing to record must not break the terminal itself.
+        let mut writer = self
+            .0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let _ = writer
+            .write_all(&header)
+            .and_then(|_| writer.write_all(data))
+            .and_then(|_| writer.flush());
+    }
+}
+
+/// Reads back the records of a dump written by [`TrafficDump`].
+pub fn decode<R: Read>(mut reader: R) -> io::Result<Decoder<R>> {
+    let mut magic = [0; 8];
+    reader.read_exact(&mut magic)?;
+
+    if &magic != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a terminal traffic dump",
+        ));
+    }
+
+    Ok(Decoder(reader))
+}
+
+pub struct Decoder<R>(R);
+
+impl<R: Read> Decoder<R> {
+    fn read_record(&mut self) -> io::Result<Option<Record>> {
+        let mut header = [0; 13];
+        match self.0.read_exact(&mut header[..1]) {
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            result => result?,
+        }
+        self.0.read_exact(&mut header[1..])?;
+
+        let direction = match header[0] {
+            0 => Direction::Input,
+            1 => Direction::Output,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "invalid record direction",
+                ))
+            }
+        };
+        let micros = u64::from_le_bytes(header[1..9].try_into().unwrap());
+        let len = u32::from_le_bytes(header[9..].try_into().unwrap());
+
+        let mut data = vec![0; len as usize];
+        self.0.read_exact(&mut data)?;
+
+        Ok(Some(Record {
+            direction,
+            timestamp: Duration::from_micros(micros),
+            data,
+        }))
+    }
+}
+
+impl<R: Read> Iterator for Decoder<R> {
+    type Item = io::Result<Record>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_record().transpose()
+    }
+}
@@ -0,0 +1,140 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+/// The stage of setting up or driving a terminal that failed.
+///
+/// Terminals keep returning [`io::Error`]s, with this as their payload for errors of a known
+/// stage. Converting an `io::Error` recovers it, other errors end up as [`PtyError::Io`]:
+///
+/// ```no_run
+/// # use std::process::Command;
+/// use pseudoterminal::{CommandExt, PtyError};
+///
+/// match Command::new("vi").spawn_terminal().map_err(PtyError::from) {
+///     Err(PtyError::Open(err)) => eprintln!("no PTY available: {err}"),
+///     Err(PtyError::Spawn(err)) => eprintln!("vi didn't start: {err}"),
+///     Err(err) => eprintln!("{err}"),
+///     Ok(_terminal) => {}
+/// }
+/// ```
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum PtyError {
+    /// Allocating the PTY or pseudoconsole failed.
+    Open(io::Error),
+    /// Starting the child on the terminal failed.
+    Spawn(io::Error),
+    /// Resizing the terminal failed.
+    Resize(io::Error),
+    /// Any other I/O error.
+    Io(io::Error),
+}
+
+impl PtyError {
+    /// Returns the underlying I/O error.
+    pub fn io_error(&self) -> &io::Error {
+        match self {
+            PtyError::Open(err)
+            | PtyError::Spawn(err)
+            | PtyError::Resize(err)
+            | PtyError::Io(err) => err,
+        }
+    }
+
+    /// Returns the kind of the underlying I/O error.
+    pub fn kind(&self) -> io::ErrorKind {
+        self.io_error().kind()
+    }
+}
+
+impl fmt::Display for PtyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PtyError::Open(err) => write!(f, "failed to open the terminal: {err}"),
+            PtyError::Spawn(err) => write!(f, "failed to spawn the child: {err}"),
+            PtyError::Resize(err) => write!(f, "failed to resize the terminal: {err}"),
+            PtyError::Io(err) => err.fmt(f),
+        }
+    }
+}
+
+impl Error for PtyError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(self.io_error())
+    }
+}
+
+impl From<io::Error> for PtyError {
+    fn from(err: io::Error) -> Self {
+        if !err.get_ref().is_some_and(|inner| inner.is::<PtyError>()) {
+            return PtyError::Io(err);
+        }
+
+        match err.into_inner().map(|inner| inner.downcast::<PtyError>()) {
+            Some(Ok(err)) => *err,
+            _ => unreachable!("checked to carry a `PtyError`"),
+        }
+    }
+}
+
+impl From<PtyError> for io::Error {
+    fn from(err: PtyError) -> Self {
+        match err {
+            PtyError::Io(err) => err,
+            err => io::Error::new(err.kind(), err),
+        }
+    }
+}
+
+// Tags the error of a fallible step with its stage, e.g. `.stage(PtyError::Spawn)?`.
+pub(crate) trait Stage<T> {
+    fn stage(self, stage: fn(io::Error) -> PtyError) -> io::Result<T>;
+}
+
+impl<T> Stage<T> for io::Result<T> {
+    fn stage(self, stage: fn(io::Error) -> PtyError) -> io::Result<T> {
+        self.map_err(|err| {
+            // The innermost stage is the most specific one.
+            if err.get_ref().is_some_and(|inner| inner.is::<PtyError>()) {
+                return err;
+            }
+
+            stage(err).into()
+        })
+    }
+}
+
+// Names the step an error came from, e.g. `.context("grantpt()")?`, keeping its kind.
+pub(crate) trait Context<T> {
+    fn context(self, context: impl fmt::Display) -> io::Result<T>;
+}
+
+impl<T, E: Into<io::Error>> Context<T> for Result<T, E> {
+    fn context(self, context: impl fmt::Display) -> io::Result<T> {
+        self.map_err(|err| {
+            let source = err.into();
+            let context = context.to_string();
+
+            io::Error::new(source.kind(), ContextError { context, source })
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ContextError {
+    context: String,
+    source: io::Error,
+}
+
+impl fmt::Display for ContextError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.context, self.source)
+    }
+}
+
+impl Error for ContextError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        Some(&self.source)
+    }
+}
@@ -0,0 +1,681 @@
+//! Expect-style automation of a terminal's child, see [`Session`].
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! use pseudoterminal::expect::Session;
+//! use pseudoterminal::CommandExt;
+//!
+//! let mut session = Session::from(Command::new("passwd").spawn_terminal()?);
+//!
+//! session.exp_string("password:")?;
+//! session.send_line("hunter2")?;
+//! session.exp_eof()?;
+//! # std::io::Result::Ok(())
+//! ```
+//!
+//! Expectations wait for as long as it takes unless the session has a
+//! [timeout](Session::set_timeout) or they are given one, running out of time fails with a
+//! [`TimeoutError`] holding the output that didn't match.
+//!
+//! A [`ReplSession`] runs commands in an interactive interpreter like python or psql and returns
+//! their output. A [`Script`] recorded from a manual session replays what was typed.
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::ops::Range;
+#[cfg(unix)]
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use regex::bytes::Regex;
+
+use crate::osc::OscScanner;
+use crate::shell_integration::ShellEvent;
+use crate::{Terminal, TerminalIn, TerminalOut};
+
+/// A terminal driven by waiting for its output and answering it.
+///
+/// Output read past a match is kept for the next expectation, the output before a match is
+/// returned along with it. Matching works on the raw bytes, so output that isn't valid UTF-8 can
+/// still be matched and is decoded lossily in the results.
+pub struct Session {
+    terminal: Terminal,
+    // Output read but not matched yet.
+    buffer: Vec<u8>,
+    eof: bool,
+    timeout: Option<Duration>,
+}
+
+impl From<Terminal> for Session {
+    fn from(terminal: Terminal) -> Self {
+        Self {
+            terminal,
+            buffer: Vec::new(),
+            eof: false,
+            timeout: None,
+        }
+    }
+}
+
+impl Session {
+    /// Writes `data` to the child as is.
+    pub fn send(&mut self, data: impl AsRef<[u8]>) -> io::Result<()> {
+        let termin = self.termin()?;
+        termin.write_all(data.as_ref())?;
+
+        termin.flush()
+    }
+
+    /// Writes `line` and a line terminator, see [`TerminalIn::send_line`].
+    pub fn send_line(&mut self, line: &str) -> io::Result<()> {
+        self.termin()?.send_line(line)
+    }
+
+    /// Sends the terminal's interrupt character, see [`TerminalIn::send_interrupt`].
+    pub fn send_interrupt(&mut self) -> io::Result<()> {
+        self.termin()?.send_interrupt()
+    }
+
+    /// Waits until the output contains `needle`, returns the output before it.
+    pub fn exp_string(&mut self, needle: &str) -> io::Result<String> {
+        self.exp_string_within(needle, self.timeout)
+    }
+
+    /// Waits at most `timeout` until the output contains `needle`, see
+    /// [`exp_string`](Self::exp_string).
+    pub fn exp_string_with_timeout(
+        &mut self,
+        needle: &str,
+        timeout: Duration,
+    ) -> io::Result<String> {
+        self.exp_string_within(needle, Some(timeout))
+    }
+
+    fn exp_string_within(&mut self, needle: &str, timeout: Option<Duration>) -> io::Result<String> {
+        let needle = needle.as_bytes();
+        let (before, _) = self.expect(timeout, |output| {
+            find(output, needle).map(|start| start..start + needle.len())
+        })?;
+
+        Ok(before)
+    }
+
+    /// Waits until the output matches `regex`, returns the output before the match and the
+    /// match itself.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if `regex` is no valid regular expression.
+    pub fn exp_regex(&mut self, regex: &str) -> io::Result<(String, String)> {
+        self.exp_matching(&compile(regex)?, self.timeout)
+    }
+
+    /// Waits at most `timeout` until the output matches `regex`, see
+    /// [`exp_regex`](Self::exp_regex).
+    pub fn exp_regex_with_timeout(
+        &mut self,
+        regex: &str,
+        timeout: Duration,
+    ) -> io::Result<(String, String)> {
+        self.exp_matching(&compile(regex)?, Some(timeout))
+    }
+
+    fn exp_matching(
+        &mut self,
+        regex: &Regex,
+        timeout: Option<Duration>,
+    ) -> io::Result<(String, String)> {
+        self.expect(timeout, |output| {
+            regex.find(output).map(|found| found.range())
+        })
+    }
+
+    /// Waits for the next shell integration mark, returns the output before it along with the
+    /// mark, see [`crate::shell_integration`].
+    pub fn exp_shell_event(&mut self) -> io::Result<(String, ShellEvent)> {
+        self.exp_shell_event_within(self.timeout)
+    }
+
+    /// Waits at most `timeout` for the next shell integration mark, see
+    /// [`exp_shell_event`](Self::exp_shell_event).
+    pub fn exp_shell_event_with_timeout(
+        &mut self,
+        timeout: Duration,
+    ) -> io::Result<(String, ShellEvent)> {
+        self.exp_shell_event_within(Some(timeout))
+    }
+
+    fn exp_shell_event_within(
+        &mut self,
+        timeout: Option<Duration>,
+    ) -> io::Result<(String, ShellEvent)> {
+        let mut event = None;
+        let (before, _) = self.expect(timeout, |output| {
+            let mut found = None;
+            OscScanner::default().scan(output, |payload, range| {
+                if found.is_none() {
+                    event = ShellEvent::from_osc(payload);
+                    found = event.map(|_| range);
+                }
+            });
+
+            found
+        })?;
+
+        Ok((before, event.expect("a mark was found")))
+    }
+
+    /// Waits until the output ended, returns everything that wasn't matched yet.
+    pub fn exp_eof(&mut self) -> io::Result<String> {
+        self.exp_eof_within(self.timeout)
+    }
+
+    /// Waits at most `timeout` until the output ended, see [`exp_eof`](Self::exp_eof).
+    pub fn exp_eof_with_timeout(&mut self, timeout: Duration) -> io::Result<String> {
+        self.exp_eof_within(Some(timeout))
+    }
+
+    fn exp_eof_within(&mut self, timeout: Option<Duration>) -> io::Result<String> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        while !self.eof {
+            self.fill(timeout, deadline)?;
+        }
+
+        let rest = lossy(&self.buffer);
+        self.buffer.clear();
+
+        Ok(rest)
+    }
+
+    /// Sets how long expectations without a timeout of their own wait, `None` waits forever.
+    ///
+    /// `None` by default.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// Returns the output read but not matched yet.
+    pub fn buffer(&self) -> &[u8] {
+        &self.buffer
+    }
+
+    pub fn get_ref(&self) -> &Terminal {
+        &self.terminal
+    }
+
+    pub fn get_mut(&mut self) -> &mut Terminal {
+        &mut self.terminal
+    }
+
+    /// Returns the terminal, output that wasn't matched yet is lost.
+    pub fn into_inner(self) -> Terminal {
+        self.terminal
+    }
+
+    /// Drops the output read but not matched yet.
+    pub fn clear(&mut self) {
+        self.buffer.clear();
+    }
+
+    fn expect(
+        &mut self,
+        timeout: Option<Duration>,
+        mut find: impl FnMut(&[u8]) -> Option<Range<usize>>,
+    ) -> io::Result<(String, String)> {
+        let deadline = timeout.map(|timeout| Instant::now() + timeout);
+        loop {
+            if let Some(found) = find(&self.buffer) {
+                let before = lossy(&self.buffer[..found.start]);
+                let matched = lossy(&self.buffer[found.clone()]);
+                self.buffer.drain(..found.end);
+
+                return Ok((before, matched));
+            }
+
+            if self.eof {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "the output ended before it matched",
+                ));
+            }
+            self.fill(timeout, deadline)?;
+        }
+    }
+
+    // Reads the next chunk of output into the buffer, failing with a `TimeoutError` if there is
+    // none before `deadline`.
+    fn fill(&mut self, timeout: Option<Duration>, deadline: Option<Instant>) -> io::Result<()> {
+        if let (Some(timeout), Some(deadline)) = (timeout, deadline) {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || !self.termout()?.wait_readable(Some(remaining))? {
+                return Err(io::Error::new(
+                    io::ErrorKind::TimedOut,
+                    TimeoutError {
+                        timeout,
+                        unmatched: self.buffer.clone(),
+                    },
+                ));
+            }
+        }
+
+        let termout = self.termout()?;
+
+        let mut chunk = [0; 4096];
+        let read = loop {
+            match termout.read(&mut chunk) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                result => break result?,
+            }
+        };
+
+        self.eof = read == 0;
+        self.buffer.extend_from_slice(&chunk[..read]);
+
+        Ok(())
+    }
+
+    fn termin(&mut self) -> io::Result<&mut TerminalIn> {
+        self.terminal
+            .termin
+            .as_mut()
+            .ok_or_else(|| io::Error::other("the terminal's input was taken"))
+    }
+
+    fn termout(&mut self) -> io::Result<&mut TerminalOut> {
+        self.terminal
+            .termout
+            .as_mut()
+            .ok_or_else(|| io::Error::other("the terminal's output was taken"))
+    }
+}
+
+/// The error an expectation running out of time fails with, reported as
+/// [`io::ErrorKind::TimedOut`].
+///
+/// Get it with `err.get_ref().and_then(|err| err.downcast_ref::<TimeoutError>())`. The output it
+/// holds also stays in the session's [`buffer`](Session::buffer) for the next expectation.
+#[derive(Clone, Debug)]
+pub struct TimeoutError {
+    timeout: Duration,
+    unmatched: Vec<u8>,
+}
+
+impl TimeoutError {
+    /// Returns how long the expectation waited.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns the output read but not matched when the time ran out.
+    pub fn unmatched(&self) -> &[u8] {
+        &self.unmatched
+    }
+}
+
+impl fmt::Display for TimeoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "no match within {:?}, unmatched output: {:?}",
+            self.timeout,
+            lossy(&self.unmatched)
+        )
+    }
+}
+
+impl Error for TimeoutError {}
+
+/// An interactive interpreter driven one command at a time, e.g. python, psql or gdb.
+///
+/// The session knows the interpreter's prompt, so [`execute`](Self::execute) can tell where the
+/// output of a command ends.
+pub struct ReplSession {
+    session: Session,
+    prompt: Regex,
+    // Whether the last prompt seen is the one after the last command.
+    in_sync: bool,
+}
+
+impl ReplSession {
+    /// Waits for the first prompt of `session`'s child, which has to match the regex `prompt`.
+    ///
+    /// The prompt should be specific enough not to show up in the output of commands.
+    pub fn new(mut session: Session, prompt: &str) -> io::Result<Self> {
+        let prompt = compile(prompt)?;
+        session.exp_matching(&prompt, session.timeout)?;
+
+        Ok(Self {
+            session,
+            prompt,
+            in_sync: true,
+        })
+    }
+
+    /// Runs `cmd` and returns its output up to the next prompt, with `\r\n` turned into `\n`.
+    ///
+    /// The terminal's echo of `cmd` is not part of the output. If a previous command failed, the
+    /// session is [resynchronized](Self::resync) first.
+    pub fn execute(&mut self, cmd: &str) -> io::Result<String> {
+        if !self.in_sync {
+            self.resync()?;
+        }
+
+        self.in_sync = false;
+        self.session.send_line(cmd)?;
+        let (output, _) = self
+            .session
+            .exp_matching(&self.prompt, self.session.timeout)?;
+        self.in_sync = true;
+
+        let output = output.replace("\r\n", "\n");
+        let echo = format!("{cmd}\n");
+
+        Ok(match output.strip_prefix(&echo) {
+            Some(output) => output.to_owned(),
+            None => output,
+        })
+    }
+
+    /// Interrupts whatever the interpreter is doing and waits for its prompt, dropping the
+    /// output that wasn't returned yet.
+    pub fn resync(&mut self) -> io::Result<()> {
+        self.in_sync = false;
+        self.session.clear();
+        self.session.send_interrupt()?;
+        self.session
+            .exp_matching(&self.prompt, self.session.timeout)?;
+        self.in_sync = true;
+
+        Ok(())
+    }
+
+    pub fn get_ref(&self) -> &Session {
+        &self.session
+    }
+
+    pub fn get_mut(&mut self) -> &mut Session {
+        &mut self.session
+    }
+
+    pub fn into_inner(self) -> Session {
+        self.session
+    }
+}
+
+/// Answers prompts in order, e.g. the questions of an installer, see [`run`](Self::run).
+///
+/// ```no_run
+/// # use std::process::Command;
+/// use pseudoterminal::expect::{Chat, Session};
+/// use pseudoterminal::CommandExt;
+///
+/// let mut session = Session::from(Command::new("./install.sh").spawn_terminal()?);
+///
+/// Chat::new()
+///     .step("Install to .*\\?", "/opt/app")?
+///     .step(r"Continue\? \[y/N\]", "y")?
+///     .run(&mut session)?;
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Chat {
+    steps: Vec<ChatStep>,
+    timeout: Duration,
+}
+
+#[derive(Clone, Debug)]
+struct ChatStep {
+    prompt: Regex,
+    response: String,
+    timeout: Option<Duration>,
+}
+
+impl Default for Chat {
+    fn default() -> Self {
+        Self {
+            steps: Vec::new(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl Chat {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a step waiting for output matching the regex `prompt` and answering it with the
+    /// line `response`.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if `prompt` is no valid regular expression.
+    pub fn step(&mut self, prompt: &str, response: &str) -> io::Result<&mut Self> {
+        self.add_step(prompt, response, None)
+    }
+
+    /// Adds a step like [`step`](Self::step) waiting at most `timeout` for its prompt.
+    pub fn step_with_timeout(
+        &mut self,
+        prompt: &str,
+        response: &str,
+        timeout: Duration,
+    ) -> io::Result<&mut Self> {
+        self.add_step(prompt, response, Some(timeout))
+    }
+
+    /// Sets how long the steps without a timeout of their own wait for their prompt.
+    ///
+    /// 30 seconds by default.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Answers each prompt once it showed up in the output of `session`.
+    ///
+    /// Fails with a [`TimeoutError`] if a prompt doesn't show up in time.
+    pub fn run(&self, session: &mut Session) -> io::Result<()> {
+        for step in &self.steps {
+            let timeout = step.timeout.unwrap_or(self.timeout);
+            session.exp_matching(&step.prompt, Some(timeout))?;
+            session.send_line(&step.response)?;
+        }
+
+        Ok(())
+    }
+
+    fn add_step(
+        &mut self,
+        prompt: &str,
+        response: &str,
+        timeout: Option<Duration>,
+    ) -> io::Result<&mut Self> {
+        self.steps.push(ChatStep {
+            prompt: compile(prompt)?,
+            response: response.to_owned(),
+            timeout,
+        });
+
+        Ok(self)
+    }
+}
+
+/// A step of a [`Script`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ScriptStep {
+    /// Waits until the output contains the string.
+    Expect(String),
+    /// Writes the bytes to the child.
+    Send(Vec<u8>),
+    /// Waits until the output ended.
+    Eof,
+}
+
+/// Input to send and the output to wait for before sending it, like the scripts of autoexpect.
+///
+/// [`record`](Self::record) one from a manual session, then [`run`](Self::run) it or print it
+/// as a Tcl expect script to edit, its steps are the part following `spawn`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Script {
+    pub steps: Vec<ScriptStep>,
+}
+
+impl Script {
+    /// Hands the terminal of the calling process over to the child like
+    /// [`Terminal::interact`], recording what is typed and the prompts it answers.
+    ///
+    /// Typed input is grouped until a line or control key is sent, the output echoed in
+    /// between is not taken for a prompt. Each prompt is the last line of output before the
+    /// input.
+    #[cfg(unix)]
+    pub fn record(terminal: &mut Terminal) -> io::Result<Self> {
+        let recorder = Mutex::new(Recorder::default());
+        let lock = || recorder.lock().unwrap_or_else(PoisonError::into_inner);
+
+        terminal.interact_observed(|input| lock().input(input), |output| lock().output(output))?;
+
+        let mut recorder = recorder
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        recorder.flush_input();
+        recorder.script.steps.push(ScriptStep::Eof);
+
+        Ok(recorder.script)
+    }
+
+    /// Replays the steps against `session`.
+    pub fn run(&self, session: &mut Session) -> io::Result<()> {
+        for step in &self.steps {
+            match step {
+                ScriptStep::Expect(needle) => drop(session.exp_string(needle)?),
+                ScriptStep::Send(data) => session.send(data)?,
+                ScriptStep::Eof => drop(session.exp_eof()?),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Formats the steps as Tcl expect commands.
+impl fmt::Display for Script {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            match step {
+                ScriptStep::Expect(needle) => {
+                    writeln!(f, "expect -exact \"{}\"", TclEscaped(needle.as_bytes()))?
+                }
+                ScriptStep::Send(data) => writeln!(f, "send -- \"{}\"", TclEscaped(data))?,
+                ScriptStep::Eof => writeln!(f, "expect eof")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The contents of a double quoted Tcl string.
+struct TclEscaped<'a>(&'a [u8]);
+
+impl fmt::Display for TclEscaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for chunk in self.0.utf8_chunks() {
+            for char in chunk.valid().chars() {
+                match char {
+                    '\\' | '"' | '[' | ']' | '$' | '{' | '}' => write!(f, "\\{char}")?,
+                    '\r' => f.write_str("\\r")?,
+                    '\n' => f.write_str("\\n")?,
+                    '\t' => f.write_str("\\t")?,
+                    // Octal escapes end after three digits, unlike hexadecimal ones.
+                    char if char.is_ascii_control() => write!(f, "\\{:03o}", char as u8)?,
+                    char => write!(f, "{char}")?,
+                }
+            }
+            for &byte in chunk.invalid() {
+                write!(f, "\\{byte:03o}")?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Turns the traffic of an interactive session into script steps.
+#[cfg(unix)]
+#[derive(Default)]
+struct Recorder {
+    script: Script,
+    // The output since the last input was sent.
+    output: Vec<u8>,
+    // Input typed but not sent as a step yet.
+    input: Vec<u8>,
+}
+
+#[cfg(unix)]
+impl Recorder {
+    fn output(&mut self, data: &[u8]) {
+        // Output while typing is the echo of the input.
+        if self.input.is_empty() {
+            self.output.extend_from_slice(data);
+        }
+    }
+
+    fn input(&mut self, data: &[u8]) {
+        if self.input.is_empty() {
+            if let Some(prompt) = last_line(&self.output) {
+                self.script.steps.push(ScriptStep::Expect(lossy(prompt)));
+            }
+            self.output.clear();
+        }
+
+        self.input.extend_from_slice(data);
+        // Enter, the arrow keys and the like complete what was typed.
+        if data
+            .iter()
+            .any(|&byte| byte.is_ascii_control() && !matches!(byte, b'\t' | 0x08 | 0x7f))
+        {
+            self.flush_input();
+        }
+    }
+
+    fn flush_input(&mut self) {
+        if !self.input.is_empty() {
+            let input = std::mem::take(&mut self.input);
+            self.script.steps.push(ScriptStep::Send(input));
+        }
+    }
+}
+
+// The last line of `output` that has more than a line ending.
+#[cfg(unix)]
+fn last_line(output: &[u8]) -> Option<&[u8]> {
+    output
+        .rsplit(|&byte| byte == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .map(|line| match line.iter().rposition(|&byte| byte == b'\r') {
+            // What a carriage return in the middle of a line went back over was overwritten.
+            Some(start) => &line[start + 1..],
+            None => line,
+        })
+        .find(|line| !line.is_empty())
+}
+
+fn compile(regex: &str) -> io::Result<Regex> {
+    Regex::new(regex).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    if needle.is_empty() {
+        return Some(0);
+    }
+
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
@@ -0,0 +1,380 @@
+//! Runtime-agnostic async I/O implementing the [`futures_io`] traits.
+//!
+//! Each half of a blocking [`Terminal`](crate::Terminal) is moved to a background thread doing
+//! its blocking I/O, which wakes the task waiting for it. This works with any executor at the
+//! cost of a thread per half, `non_blocking` integrates with tokio instead.
+//!
+//! These types are a shim over threads, not readiness-driven I/O: an executor never polls the
+//! terminal itself. `async_io` waits on a reactor instead where the `async-io` feature is enabled.
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! use futures::io::AsyncReadExt;
+//! use pseudoterminal::CommandExt;
+//!
+//! # futures::executor::block_on(async {
+//! let mut terminal = Command::new("ls").spawn_terminal()?;
+//! let mut termout = pseudoterminal::futures::TerminalOut::new(terminal.termout.take().unwrap())?;
+//!
+//! let mut output = String::new();
+//! termout.read_to_string(&mut output).await?;
+//! # std::io::Result::Ok(())
+//! # });
+//! ```
+
+use std::io::{self, Read, Write};
+use std::pin::Pin;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+#[cfg(unix)]
+use crate::ReadInterrupter;
+
+const CHUNK_SIZE: usize = 8192;
+
+// State shared by a half and its thread, `changed` wakes the thread.
+struct Shared<T> {
+    state: Mutex<T>,
+    changed: Condvar,
+}
+
+impl<T> Shared<T> {
+    fn new(state: T) -> Arc<Self> {
+        Arc::new(Shared {
+            state: Mutex::new(state),
+            changed: Condvar::new(),
+        })
+    }
+
+    fn lock(&self) -> MutexGuard<'_, T> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn wait<'a>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+        self.changed
+            .wait(guard)
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn wake(waker: &mut Option<Waker>) {
+    if let Some(waker) = waker.take() {
+        waker.wake();
+    }
+}
+
+#[derive(Default)]
+struct ReadState {
+    // Output the thread read, the bytes before `pos` have been returned already.
+    buffer: Vec<u8>,
+    pos: usize,
+    error: Option<io::Error>,
+    eof: bool,
+    // The task waits for the thread to read another chunk.
+    requested: bool,
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+/// The output of a terminal, read by a background thread.
+pub struct TerminalOut {
+    shared: Arc<Shared<ReadState>>,
+    #[cfg(unix)]
+    interrupter: ReadInterrupter,
+}
+
+impl TerminalOut {
+    /// Moves `termout` to a new thread reading it whenever the task polls for output.
+    ///
+    /// Dropping this stops the thread, on Windows only once the pending read returned.
+    pub fn new(mut termout: crate::TerminalOut) -> io::Result<Self> {
+        #[cfg(unix)]
+        let interrupter = termout.read_interrupter()?;
+
+        let shared = Shared::new(ReadState::default());
+        let thread_shared = shared.clone();
+
+        thread::Builder::new()
+            .name("pseudoterminal-reader".into())
+            .spawn(move || read_on_request(&mut termout, &thread_shared))?;
+
+        Ok(Self {
+            shared,
+            #[cfg(unix)]
+            interrupter,
+        })
+    }
+}
+
+fn read_on_request(termout: &mut crate::TerminalOut, shared: &Shared<ReadState>) {
+    let mut chunk = vec![0; CHUNK_SIZE];
+
+    loop {
+        let mut state = shared.lock();
+        while !state.requested && !state.closed {
+            state = shared.wait(state);
+        }
+        if state.closed {
+            return;
+        }
+        drop(state);
+
+        let result = termout.read(&mut chunk);
+
+        let mut state = shared.lock();
+        if state.closed {
+            return;
+        }
+        match result {
+            Ok(0) => state.eof = true,
+            Ok(read) => {
+                state.buffer.clear();
+                state.buffer.extend_from_slice(&chunk[..read]);
+                state.pos = 0;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => state.error = Some(err),
+        }
+        state.requested = false;
+        wake(&mut state.waker);
+    }
+}
+
+impl AsyncRead for TerminalOut {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.shared.lock();
+
+        if state.pos < state.buffer.len() {
+            let read = buf.len().min(state.buffer.len() - state.pos);
+            buf[..read].copy_from_slice(&state.buffer[state.pos..state.pos + read]);
+            state.pos += read;
+
+            return Poll::Ready(Ok(read));
+        }
+        if let Some(err) = state.error.take() {
+            return Poll::Ready(Err(err));
+        }
+        if state.eof {
+            return Poll::Ready(Ok(0));
+        }
+
+        state.waker = Some(cx.waker().clone());
+        if !state.requested {
+            state.requested = true;
+            self.shared.changed.notify_one();
+        }
+
+        Poll::Pending
+    }
+}
+
+impl Drop for TerminalOut {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_one();
+
+        // Unblocks a pending read, the thread drops the output once it returned.
+        #[cfg(unix)]
+        let _ = self.interrupter.interrupt_read();
+    }
+}
+
+#[derive(Default)]
+struct WriteState {
+    // Input accepted from the task, not yet handed to the thread.
+    buffer: Vec<u8>,
+    // The thread is writing what it took from `buffer`.
+    writing: bool,
+    error: Option<io::Error>,
+    waker: Option<Waker>,
+    closed: bool,
+    // The task asked for EOF, the thread delivers it once everything before was written.
+    closing: bool,
+    eof_sent: bool,
+}
+
+/// The input of a terminal, written by a background thread.
+///
+/// Writes are buffered and complete before the thread wrote them, a failed write is reported by
+/// the next write or flush.
+pub struct TerminalIn {
+    shared: Arc<Shared<WriteState>>,
+}
+
+impl TerminalIn {
+    /// Moves `termin` to a new thread writing what the task writes.
+    ///
+    /// Dropping this lets the thread write the remaining input, then it drops `termin`, which
+    /// delivers EOF to the child unless clones of `termin` are still open. Closing this delivers
+    /// EOF right away, see [`crate::TerminalIn::close`].
+    pub fn new(termin: crate::TerminalIn) -> io::Result<Self> {
+        let shared = Shared::new(WriteState::default());
+        let thread_shared = shared.clone();
+
+        thread::Builder::new()
+            .name("pseudoterminal-writer".into())
+            .spawn(move || write_on_request(termin, &thread_shared))?;
+
+        Ok(Self { shared })
+    }
+}
+
+fn write_on_request(mut termin: crate::TerminalIn, shared: &Shared<WriteState>) {
+    loop {
+        let mut state = shared.lock();
+        while state.buffer.is_empty() && !state.closed && !state.closing {
+            state = shared.wait(state);
+        }
+        if state.buffer.is_empty() {
+            if !state.closing {
+                return;
+            }
+            drop(state);
+
+            let result = termin.close();
+
+            let mut state = shared.lock();
+            state.eof_sent = true;
+            if let Err(err) = result {
+                state.error = Some(err);
+            }
+            wake(&mut state.waker);
+            return;
+        }
+        let data = std::mem::take(&mut state.buffer);
+        state.writing = true;
+        drop(state);
+
+        let result = termin.write_all(&data).and_then(|_| termin.flush());
+
+        let mut state = shared.lock();
+        state.writing = false;
+        if let Err(err) = result {
+            state.error = Some(err);
+        }
+        wake(&mut state.waker);
+    }
+}
+
+impl AsyncWrite for TerminalIn {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut state = self.shared.lock();
+
+        if let Some(err) = state.error.take() {
+            return Poll::Ready(Err(err));
+        }
+        if state.closing {
+            return Poll::Ready(Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "the terminal's input was closed",
+            )));
+        }
+        if buf.is_empty() {
+            return Poll::Ready(Ok(0));
+        }
+        if state.buffer.len() >= CHUNK_SIZE {
+            state.waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let written = buf.len().min(CHUNK_SIZE - state.buffer.len());
+        state.buffer.extend_from_slice(&buf[..written]);
+        self.shared.changed.notify_one();
+
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.shared.lock();
+
+        if let Some(err) = state.error.take() {
+            return Poll::Ready(Err(err));
+        }
+        if state.buffer.is_empty() && !state.writing {
+            return Poll::Ready(Ok(()));
+        }
+
+        state.waker = Some(cx.waker().clone());
+        Poll::Pending
+    }
+
+    // Delivers EOF like `crate::TerminalIn::close`, after the thread wrote the pending input.
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let mut state = self.shared.lock();
+
+        if let Some(err) = state.error.take() {
+            return Poll::Ready(Err(err));
+        }
+        if state.eof_sent {
+            return Poll::Ready(Ok(()));
+        }
+
+        state.closing = true;
+        state.waker = Some(cx.waker().clone());
+        self.shared.changed.notify_one();
+        Poll::Pending
+    }
+}
+
+impl Drop for TerminalIn {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_one();
+    }
+}
+
+/// Both halves of a terminal's I/O in one duplex stream, for APIs expecting
+/// `AsyncRead + AsyncWrite`.
+pub struct TerminalStream {
+    termin: TerminalIn,
+    termout: TerminalOut,
+}
+
+impl TerminalStream {
+    pub fn new(termin: TerminalIn, termout: TerminalOut) -> Self {
+        Self { termin, termout }
+    }
+
+    pub fn into_split(self) -> (TerminalIn, TerminalOut) {
+        (self.termin, self.termout)
+    }
+}
+
+impl AsyncRead for TerminalStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.termout).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for TerminalStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.termin).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.termin).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.termin).poll_close(cx)
+    }
+}
@@ -0,0 +1,389 @@
+//! Escape sequences for special keys and mouse events, encoded the way a terminal sends them to
+//! its programs.
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! use pseudoterminal::keys::{Key, Modifiers};
+//! use pseudoterminal::CommandExt;
+//!
+//! let mut terminal = Command::new("vi").spawn_terminal()?;
+//! let keys = terminal.key_encoder();
+//!
+//! let termin = terminal.termin.as_mut().unwrap();
+//! keys.write_key(termin, Key::Down, Modifiers::NONE)?;
+//! keys.write_key(termin, Key::Char('x'), Modifiers::NONE)?;
+//! # std::io::Result::Ok(())
+//! ```
+
+use std::io::{self, Write};
+use std::ops::{BitOr, BitOrAssign};
+
+/// A key of a keyboard, as far as terminals tell them apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Key {
+    Char(char),
+    Enter,
+    Tab,
+    Backspace,
+    Escape,
+    Up,
+    Down,
+    Right,
+    Left,
+    Home,
+    End,
+    Insert,
+    Delete,
+    PageUp,
+    PageDown,
+    /// A function key, `F(1)` through `F(12)`.
+    F(u8),
+}
+
+/// Modifier keys held down along with a key, combined with `|`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub const NONE: Self = Modifiers(0);
+    pub const SHIFT: Self = Modifiers(1);
+    pub const ALT: Self = Modifiers(2);
+    pub const CTRL: Self = Modifiers(4);
+
+    /// Returns whether all modifiers of `other` are held down.
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    pub const fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    // The modifier parameter of xterm's `CSI 1 ; m X` sequences.
+    fn parameter(self) -> u8 {
+        1 + self.0
+    }
+}
+
+impl BitOr for Modifiers {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Modifiers(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Modifiers {
+    fn bitor_assign(&mut self, rhs: Self) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A mouse button, the wheel counts as four of them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseButton {
+    Left,
+    Middle,
+    Right,
+    WheelUp,
+    WheelDown,
+    WheelLeft,
+    WheelRight,
+}
+
+/// What the mouse did.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MouseEventKind {
+    Press(MouseButton),
+    Release(MouseButton),
+    /// Motion with a button held down.
+    Drag(MouseButton),
+    /// Motion without any button held down, only reported in any-event tracking mode.
+    Move,
+}
+
+/// A mouse event at a cell of the terminal, counted from 0 at the top left.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MouseEvent {
+    pub kind: MouseEventKind,
+    pub column: u16,
+    pub row: u16,
+    pub modifiers: Modifiers,
+}
+
+impl MouseEvent {
+    pub fn new(kind: MouseEventKind, column: u16, row: u16) -> Self {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: Modifiers::NONE,
+        }
+    }
+
+    // The button parameter of an SGR report, `Cb`.
+    fn sgr_button(&self) -> u16 {
+        let button = |button| match button {
+            MouseButton::Left => 0,
+            MouseButton::Middle => 1,
+            MouseButton::Right => 2,
+            MouseButton::WheelUp => 64,
+            MouseButton::WheelDown => 65,
+            MouseButton::WheelLeft => 66,
+            MouseButton::WheelRight => 67,
+        };
+
+        let mut code = match self.kind {
+            MouseEventKind::Press(pressed) | MouseEventKind::Release(pressed) => button(pressed),
+            MouseEventKind::Drag(held) => button(held) + 32,
+            MouseEventKind::Move => 3 + 32,
+        };
+
+        for (modifier, bit) in [
+            (Modifiers::SHIFT, 4),
+            (Modifiers::ALT, 8),
+            (Modifiers::CTRL, 16),
+        ] {
+            if self.modifiers.contains(modifier) {
+                code += bit;
+            }
+        }
+
+        code
+    }
+}
+
+// Terminals sharing a key encoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum Family {
+    #[default]
+    Xterm,
+    Linux,
+    Rxvt,
+    Vt220,
+}
+
+/// Encodes keys for a type of terminal, see
+/// [`Terminal::key_encoder`](crate::Terminal::key_encoder).
+///
+/// Defaults to xterm's encoding, which most terminal emulators share.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct KeyEncoder {
+    family: Family,
+    application_cursor: bool,
+}
+
+impl KeyEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the encoder for the terminal type `term`, the value of `TERM`.
+    ///
+    /// Unknown types get xterm's encoding.
+    pub fn for_term(term: &str) -> Self {
+        let family = match term {
+            "linux" => Family::Linux,
+            term if term.starts_with("rxvt") => Family::Rxvt,
+            term if term.starts_with("vt") => Family::Vt220,
+            _ => Family::Xterm,
+        };
+
+        KeyEncoder {
+            family,
+            application_cursor: false,
+        }
+    }
+
+    /// Returns the encoder for the `TERM` of the calling process.
+    pub fn from_env() -> Self {
+        std::env::var("TERM").map_or_else(|_| Self::new(), |term| Self::for_term(&term))
+    }
+
+    /// Sends the cursor keys in application mode, which programs switch to with `smkx`.
+    pub fn application_cursor(&mut self, enabled: bool) -> &mut Self {
+        self.application_cursor = enabled;
+        self
+    }
+
+    /// Returns the bytes the terminal sends for `key`, `None` if it has no such key.
+    ///
+    /// Modifiers the terminal can't encode for a key are dropped, except for Alt which prefixes
+    /// any key with an escape.
+    pub fn encode(&self, key: Key, modifiers: Modifiers) -> Option<Vec<u8>> {
+        let alt = modifiers.contains(Modifiers::ALT);
+
+        let mut encoded = match key {
+            Key::Char(char) => return Some(encode_char(char, modifiers)),
+            Key::Enter => b"\r".to_vec(),
+            Key::Tab if modifiers.contains(Modifiers::SHIFT) => b"\x1b[Z".to_vec(),
+            Key::Tab => b"\t".to_vec(),
+            Key::Backspace if modifiers.contains(Modifiers::CTRL) => b"\x08".to_vec(),
+            Key::Backspace if self.family == Family::Vt220 => b"\x08".to_vec(),
+            Key::Backspace => b"\x7f".to_vec(),
+            Key::Escape => b"\x1b".to_vec(),
+            key => return self.encode_sequence(key, modifiers),
+        };
+
+        if alt {
+            encoded.insert(0, b'\x1b');
+        }
+
+        Some(encoded)
+    }
+
+    /// Writes the bytes for `key` to `input`, e.g. a [`TerminalIn`](crate::TerminalIn), and
+    /// flushes it.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the terminal has no such key.
+    pub fn write_key(
+        &self,
+        input: &mut impl Write,
+        key: Key,
+        modifiers: Modifiers,
+    ) -> io::Result<()> {
+        let encoded = self.encode(key, modifiers).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("the terminal has no {key:?} key"),
+            )
+        })?;
+
+        input.write_all(&encoded)?;
+        input.flush()
+    }
+
+    /// Returns the report of a mouse event in SGR encoding (mode 1006).
+    ///
+    /// Terminals use the same encoding regardless of their type, but only send reports once the
+    /// program enabled mouse tracking, e.g. with `CSI ? 1000 h` and `CSI ? 1006 h`.
+    pub fn encode_mouse(&self, event: MouseEvent) -> Vec<u8> {
+        let end = match event.kind {
+            MouseEventKind::Release(_) => 'm',
+            _ => 'M',
+        };
+
+        // SGR reports count cells from 1.
+        format!(
+            "\x1b[<{};{};{}{end}",
+            event.sgr_button(),
+            u32::from(event.column) + 1,
+            u32::from(event.row) + 1,
+        )
+        .into_bytes()
+    }
+
+    /// Writes the report of a mouse event to `input` and flushes it, see
+    /// [`encode_mouse`](Self::encode_mouse).
+    pub fn write_mouse(&self, input: &mut impl Write, event: MouseEvent) -> io::Result<()> {
+        input.write_all(&self.encode_mouse(event))?;
+        input.flush()
+    }
+
+    // Keys sent as escape sequences, which only xterm modifies with a parameter.
+    fn encode_sequence(&self, key: Key, modifiers: Modifiers) -> Option<Vec<u8>> {
+        enum Sequence {
+            // `ESC O X`, or `CSI X` unless in application mode.
+            Cursor(u8),
+            // `ESC O X`, `CSI 1 ; m X` when modified.
+            Ss3(u8),
+            // `CSI n ~`
+            Tilde(u8),
+            // Sent as is, never modified.
+            Fixed(&'static [u8]),
+        }
+
+        let sequence = match (self.family, key) {
+            (_, Key::Up) => Sequence::Cursor(b'A'),
+            (_, Key::Down) => Sequence::Cursor(b'B'),
+            (_, Key::Right) => Sequence::Cursor(b'C'),
+            (_, Key::Left) => Sequence::Cursor(b'D'),
+
+            (Family::Xterm, Key::Home) => Sequence::Cursor(b'H'),
+            (Family::Xterm, Key::End) => Sequence::Cursor(b'F'),
+            (Family::Rxvt, Key::Home) => Sequence::Tilde(7),
+            (Family::Rxvt, Key::End) => Sequence::Tilde(8),
+            (_, Key::Home) => Sequence::Tilde(1),
+            (_, Key::End) => Sequence::Tilde(4),
+
+            (_, Key::Insert) => Sequence::Tilde(2),
+            (_, Key::Delete) => Sequence::Tilde(3),
+            (_, Key::PageUp) => Sequence::Tilde(5),
+            (_, Key::PageDown) => Sequence::Tilde(6),
+
+            (Family::Linux, Key::F(n @ 1..=5)) => Sequence::Fixed(
+                [b"\x1b[[A", b"\x1b[[B", b"\x1b[[C", b"\x1b[[D", b"\x1b[[E"][n as usize - 1],
+            ),
+            (Family::Rxvt, Key::F(n @ 1..=4)) => Sequence::Tilde(10 + n),
+            (Family::Vt220, Key::F(5)) => return None,
+            (_, Key::F(n @ 1..=4)) => Sequence::Ss3(b'O' + n),
+            (_, Key::F(5)) => Sequence::Tilde(15),
+            (_, Key::F(n @ 6..=12)) => {
+                Sequence::Tilde([17, 18, 19, 20, 21, 23, 24][n as usize - 6])
+            }
+            (_, Key::F(_)) => return None,
+
+            (_, Key::Char(_) | Key::Enter | Key::Tab | Key::Backspace | Key::Escape) => {
+                unreachable!("encoded as characters")
+            }
+        };
+
+        let modified = self.family == Family::Xterm && !modifiers.is_empty();
+        let alt = !modified && modifiers.contains(Modifiers::ALT);
+
+        let mut encoded = if alt { vec![b'\x1b'] } else { Vec::new() };
+        match sequence {
+            Sequence::Cursor(final_byte) | Sequence::Ss3(final_byte) if modified => {
+                write!(encoded, "\x1b[1;{}", modifiers.parameter()).unwrap();
+                encoded.push(final_byte);
+            }
+            Sequence::Cursor(final_byte) if !self.application_cursor => {
+                encoded.extend_from_slice(b"\x1b[");
+                encoded.push(final_byte);
+            }
+            Sequence::Cursor(final_byte) | Sequence::Ss3(final_byte) => {
+                encoded.extend_from_slice(b"\x1bO");
+                encoded.push(final_byte);
+            }
+            Sequence::Tilde(number) if modified => {
+                write!(encoded, "\x1b[{number};{}~", modifiers.parameter()).unwrap();
+            }
+            Sequence::Tilde(number) => write!(encoded, "\x1b[{number}~").unwrap(),
+            Sequence::Fixed(bytes) => encoded.extend_from_slice(bytes),
+        }
+
+        Some(encoded)
+    }
+}
+
+// Ctrl turns a character into its control code the way xterm does, e.g. Ctrl-Space into NUL.
+fn encode_char(char: char, modifiers: Modifiers) -> Vec<u8> {
+    let char = if modifiers.contains(Modifiers::SHIFT) {
+        char.to_ascii_uppercase()
+    } else {
+        char
+    };
+
+    let control = match char {
+        _ if !modifiers.contains(Modifiers::CTRL) => None,
+        'a'..='z' | 'A'..='Z' | '@' | '[' | '\\' | ']' | '^' | '_' => {
+            Some(char.to_ascii_uppercase() as u8 & 0x1f)
+        }
+        ' ' | '2' => Some(0),
+        '3'..='7' => Some(char as u8 - b'3' + 0x1b),
+        '/' => Some(0x1f),
+        '8' | '?' => Some(0x7f),
+        _ => None,
+    };
+
+    let mut encoded = Vec::with_capacity(5);
+    if modifiers.contains(Modifiers::ALT) {
+        encoded.push(b'\x1b');
+    }
+    match control {
+        Some(byte) => encoded.push(byte),
+        None => encoded.extend_from_slice(char.encode_utf8(&mut [0; 4]).as_bytes()),
+    }
+
+    encoded
+}
@@ -1,15 +1,72 @@
 #![cfg_attr(all(doc, CHANNEL_NIGHTLY), feature(doc_auto_cfg))]
 #![cfg_attr(windows, feature(windows_process_extensions_raw_attribute))]
 
+use std::time::Duration;
+
+#[cfg(all(unix, feature = "async-io"))]
+pub mod async_io;
+#[cfg(feature = "async-std")]
+pub mod async_std;
 mod blocking;
+#[cfg(feature = "debug-bytes")]
+mod debug_bytes;
+#[cfg(feature = "dump")]
+pub mod dump;
+mod error;
+#[cfg(feature = "expect")]
+pub mod expect;
+#[cfg(feature = "futures-io")]
+pub mod futures;
+#[cfg(feature = "keys")]
+pub mod keys;
 #[cfg(feature = "non-blocking")]
 pub mod non_blocking;
+mod options;
+#[cfg(any(feature = "title", feature = "shell-integration"))]
+mod osc;
+mod pty;
+#[cfg(feature = "screen")]
+pub mod screen;
+#[cfg(feature = "scrollback")]
+mod scrollback;
+#[cfg(feature = "shell-integration")]
+pub mod shell_integration;
+mod size;
 mod sys;
+#[cfg(feature = "testing")]
+pub mod testing;
+#[cfg(feature = "title")]
+pub mod title;
+#[cfg(all(target_os = "linux", feature = "io-uring"))]
+pub mod uring;
+#[cfg(feature = "utf8")]
+mod utf8;
 
 pub use blocking::*;
+#[cfg(feature = "debug-bytes")]
+pub use debug_bytes::DebugBytes;
+pub use error::PtyError;
+pub use options::TerminalOptions;
+#[cfg(unix)]
+pub use options::{InteractOptions, InterruptPolicy, SuspendPolicy};
+pub use pty::Pty;
+#[cfg(unix)]
+pub use pty::{PtyMaster, PtyPair, PtySlave};
+pub use size::{ParseSizeError, SizeError, SizePolicy, TerminalSize};
+pub use sys::{ResizeFollower, TerminalHandle};
+#[cfg(feature = "utf8")]
+pub use utf8::Utf8Decoder;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct TerminalSize {
-    pub rows: u16,
-    pub columns: u16,
+/// How `close_with()` shuts down the child of a terminal.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CloseBehavior {
+    /// Kills the child right away.
+    Force,
+    /// Asks the child to exit and kills it if it is still running after `timeout`.
+    ///
+    /// On Unix the child is sent `signal`, e.g. `libc::SIGTERM`. On Windows the pseudoconsole
+    /// is closed instead, which sends `CTRL_CLOSE_EVENT` to the processes attached to it.
+    Graceful { signal: i32, timeout: Duration },
+    /// Waits for the child to exit on its own.
+    WaitOnly,
 }
@@ -1,58 +1,1053 @@
-use std::pin::Pin;
-use std::process::Command as StdCommand;
+use std::cell::Cell;
+use std::future::{self, Future};
+use std::io::IoSlice;
+use std::pin::{pin, Pin};
+use std::process::{Command as StdCommand, ExitStatus, Output};
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::task::Poll;
+use std::thread;
+use std::time::Duration;
 
-use tokio::fs::File;
-use tokio::io::{self, AsyncRead, AsyncWrite};
-use tokio::process::{Child, Command};
+use tokio::io::{self, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::process::{Child, ChildStderr, Command};
+#[cfg(feature = "broadcast")]
+use tokio::sync::broadcast;
+#[cfg(feature = "channels")]
+use tokio::sync::mpsc;
+use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard, Notify};
+use tokio::task::JoinHandle;
 
-use crate::sys::open_handle_and_io;
-use crate::sys::TerminalHandle;
+#[cfg(any(feature = "stream", feature = "broadcast", feature = "channels"))]
+use bytes::Bytes;
+#[cfg(feature = "stream")]
+use bytes::BytesMut;
+#[cfg(feature = "stream")]
+use futures_core::Stream;
+#[cfg(feature = "stream")]
+use futures_sink::Sink;
+#[cfg(feature = "stream")]
+use tokio_util::codec::{BytesCodec, FramedWrite};
+#[cfg(feature = "stream")]
+use tokio_util::io::{poll_read_buf, ReaderStream};
+#[cfg(feature = "cancellation")]
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "stream")]
+use crate::blocking::LineSplitter;
+#[cfg(feature = "dump")]
+use crate::dump::{Direction, TrafficDump};
+use crate::error::{Context, Stage};
+#[cfg(feature = "scrollback")]
+use crate::scrollback::Scrollback;
+use crate::sys::{
+    async_master, configure_console, control_char, into_file, is_session_end, line_terminator,
+    open_handle_and_io, send_control_char, send_eof_on_drop, ControlChar,
+};
+#[cfg(unix)]
+use crate::sys::{configure_login_shell, interact, send_signal, ReadInterrupt};
+use crate::sys::{AsyncMaster, TerminalHandle};
+#[cfg(feature = "title")]
+use crate::title::TitleWatcher;
+#[cfg(unix)]
+use crate::InteractOptions;
+#[cfg(feature = "stream")]
+use crate::LineEndings;
+use crate::{CloseBehavior, PtyError, ResizeFollower, TerminalOptions, TerminalSize};
+
+const CHUNK_SIZE: usize = 8192;
+
+// The child, shared with the terminal's controllers. Only `into_parts()` takes it out.
+struct SharedChild {
+    // Zero once the child was reaped, kept outside the lock so signalling never waits.
+    pid: AtomicU32,
+    // A blocking lock, so the status can be read without waiting for a `wait()` to return.
+    status: std::sync::Mutex<Option<ExitStatus>>,
+    child: Mutex<Option<Child>>,
+    // The calls waiting for the child, a wait holding it lets go until they got it.
+    wanted: AtomicUsize,
+    want: Notify,
+}
+
+// Counts a call as waiting for the child while alive, also if its future is dropped meanwhile.
+struct Want<'a>(&'a SharedChild);
+
+impl<'a> Want<'a> {
+    fn new(child: &'a SharedChild) -> Self {
+        child.wanted.fetch_add(1, Ordering::AcqRel);
+        child.want.notify_waiters();
+
+        Self(child)
+    }
+}
+
+impl Drop for Want<'_> {
+    fn drop(&mut self) {
+        self.0.wanted.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+fn taken() -> io::Error {
+    io::Error::other("the child was taken out of the terminal")
+}
+
+#[cfg(unix)]
+fn reaped() -> io::Error {
+    io::Error::other("the child exited and was reaped")
+}
+
+impl SharedChild {
+    fn new(child: Child) -> Arc<Self> {
+        Arc::new(SharedChild {
+            pid: AtomicU32::new(child.id().unwrap_or(0)),
+            status: std::sync::Mutex::new(None),
+            child: Mutex::new(Some(child)),
+            wanted: AtomicUsize::new(0),
+            want: Notify::new(),
+        })
+    }
+
+    fn id(&self) -> Option<u32> {
+        match self.pid.load(Ordering::Relaxed) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
+
+    async fn lock(&self) -> io::Result<MappedMutexGuard<'_, Child>> {
+        MutexGuard::try_map(self.lock_slot().await, Option::as_mut).map_err(|_| taken())
+    }
+
+    // Takes the child from a wait holding it, see `wait()`.
+    async fn lock_slot(&self) -> MutexGuard<'_, Option<Child>> {
+        let _want = Want::new(self);
+
+        self.child.lock().await
+    }
+
+    // Waits for the child without keeping it from other calls: the wait lets go of the child as
+    // soon as another call needs it, and takes it back once that returned.
+    async fn wait(&self) -> io::Result<ExitStatus> {
+        loop {
+            // Created before locking, so a call wanting the child meanwhile isn't missed.
+            let mut wanted = pin!(self.want.notified());
+            let mut slot = self.child.lock().await;
+
+            if let Some(status) = self.exit_status() {
+                return Ok(status);
+            }
+            if self.wanted.load(Ordering::Acquire) > 0 {
+                drop(slot);
+                tokio::task::yield_now().await;
+                continue;
+            }
+
+            let mut reaped = pin!(slot.as_mut().ok_or_else(taken)?.wait());
+            let status = future::poll_fn(|cx| match reaped.as_mut().poll(cx) {
+                Poll::Ready(status) => Poll::Ready(Some(status)),
+                Poll::Pending => wanted.as_mut().poll(cx).map(|()| None),
+            })
+            .await;
+
+            if let Some(status) = status {
+                let status = status?;
+                self.set_status(status);
+                return Ok(status);
+            }
+        }
+    }
+
+    async fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        let status = self.lock().await?.try_wait()?;
+        if let Some(status) = status {
+            self.set_status(status);
+        }
+
+        Ok(status)
+    }
+
+    // A wait holding the lock records the status once the child exited, until then the child
+    // counts as running.
+    fn is_alive(&self) -> bool {
+        if self.id().is_none() {
+            return false;
+        }
+
+        let mut child = match self.child.try_lock() {
+            Ok(child) => child,
+            Err(_) => return true,
+        };
+        match child.as_mut().map(Child::try_wait) {
+            Some(Ok(None)) => true,
+            Some(Ok(Some(status))) => {
+                self.set_status(status);
+                false
+            }
+            Some(Err(_)) | None => false,
+        }
+    }
+
+    fn exit_status(&self) -> Option<ExitStatus> {
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn set_status(&self, status: ExitStatus) {
+        *self.status.lock().unwrap_or_else(PoisonError::into_inner) = Some(status);
+        self.pid.store(0, Ordering::Relaxed);
+    }
+
+    // Killing fails once the child was reaped.
+    async fn start_kill(&self) -> io::Result<()> {
+        let mut child = self.lock().await?;
+        match child.try_wait()? {
+            Some(status) => self.set_status(status),
+            None => child.start_kill()?,
+        }
+
+        Ok(())
+    }
+}
+
+/// The number of bytes [`Terminal::bridge`] copied in each direction.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BridgeStats {
+    pub to_terminal: u64,
+    pub from_terminal: u64,
+}
+
+async fn bridge<S>(
+    termin: &mut TerminalIn,
+    termout: &mut TerminalOut,
+    stream: &mut S,
+) -> io::Result<BridgeStats>
+where
+    S: AsyncRead + AsyncWrite + Unpin + ?Sized,
+{
+    let (mut stream_read, mut stream_write) = io::split(stream);
+    let to_terminal = Cell::new(0);
+    let from_terminal = Cell::new(0);
+
+    let input = async {
+        let mut buf = [0; CHUNK_SIZE];
+        loop {
+            let read = stream_read.read(&mut buf).await?;
+            if read == 0 {
+                return io::Result::Ok(());
+            }
+            termin.write_all(&buf[..read]).await?;
+            termin.flush().await?;
+            to_terminal.set(to_terminal.get() + read as u64);
+        }
+    };
+    let output = async {
+        let mut buf = [0; CHUNK_SIZE];
+        loop {
+            let read = termout.read(&mut buf).await?;
+            if read == 0 {
+                return stream_write.shutdown().await;
+            }
+            stream_write.write_all(&buf[..read]).await?;
+            stream_write.flush().await?;
+            from_terminal.set(from_terminal.get() + read as u64);
+        }
+    };
+
+    until_output_ends(input, output).await?;
+
+    Ok(BridgeStats {
+        to_terminal: to_terminal.get(),
+        from_terminal: from_terminal.get(),
+    })
+}
+
+// Drives `input` alongside `output` until the latter completes, `input` may finish earlier.
+async fn until_output_ends(
+    input: impl Future<Output = io::Result<()>>,
+    output: impl Future<Output = io::Result<()>>,
+) -> io::Result<()> {
+    let (mut input, mut output) = (pin!(input), pin!(output));
+    let mut input_done = false;
+
+    future::poll_fn(|cx| {
+        if !input_done {
+            if let Poll::Ready(result) = input.as_mut().poll(cx) {
+                result?;
+                input_done = true;
+            }
+        }
+
+        output.as_mut().poll(cx)
+    })
+    .await
+}
+
+/// The input sender, output receiver and pump handle returned by [`Terminal::into_channels`].
+#[cfg(feature = "channels")]
+pub type TerminalChannels = (
+    mpsc::Sender<Bytes>,
+    mpsc::Receiver<Bytes>,
+    JoinHandle<io::Result<ExitStatus>>,
+);
+
+#[cfg(feature = "channels")]
+async fn pump_channels(
+    mut termin: TerminalIn,
+    mut termout: TerminalOut,
+    mut receiver: mpsc::Receiver<Bytes>,
+    sender: mpsc::Sender<Bytes>,
+) -> io::Result<()> {
+    let input = async {
+        while let Some(chunk) = receiver.recv().await {
+            termin.write_all(&chunk).await?;
+            termin.flush().await?;
+        }
+
+        // Every sender was dropped.
+        termin.close().await
+    };
+    let output = async {
+        let mut buf = vec![0; CHUNK_SIZE];
+        loop {
+            let read = termout.read(&mut buf).await?;
+            if read == 0 {
+                return Ok(());
+            }
+
+            // Without a receiver the output is still drained, so the child doesn't stall.
+            let _ = sender.send(Bytes::copy_from_slice(&buf[..read])).await;
+        }
+    };
+
+    until_output_ends(input, output).await
+}
+
+#[cfg(feature = "broadcast")]
+async fn pump_output(mut termout: TerminalOut, sender: broadcast::Sender<Bytes>) -> io::Result<()> {
+    let mut buf = vec![0; CHUNK_SIZE];
+    loop {
+        let read = match termout.read(&mut buf).await {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+
+        // Without subscribers the chunk is dropped, like output nobody reads.
+        let _ = sender.send(Bytes::copy_from_slice(&buf[..read]));
+    }
+}
+
+fn read_to_end_in_background(
+    mut reader: impl AsyncRead + Send + Unpin + 'static,
+) -> JoinHandle<io::Result<Vec<u8>>> {
+    tokio::spawn(async move {
+        let mut output = Vec::new();
+        reader.read_to_end(&mut output).await.map(|_| output)
+    })
+}
 
 pub struct Terminal {
     handle: TerminalHandle,
-    process: Child,
+    process: Arc<SharedChild>,
     pub termin: Option<TerminalIn>,
     pub termout: Option<TerminalOut>,
+    /// The child's stderr if `TerminalOptions::separate_stderr` routed it through a pipe.
+    pub termerr: Option<ChildStderr>,
+    #[cfg(feature = "scrollback")]
+    scrollback: Option<Scrollback>,
 }
 
 impl Terminal {
     pub(crate) fn new(
         cmd: StdCommand,
+        options: &TerminalOptions,
         handle: TerminalHandle,
-        (termin, termout): (File, File),
+        (termin, termout): (AsyncMaster, AsyncMaster),
     ) -> io::Result<Self> {
-        let process = Command::from(cmd).spawn()?;
+        let mut cmd = Command::from(cmd);
+        let mut process = cmd
+            .kill_on_drop(options.kill_on_drop)
+            .spawn()
+            .context(format_args!("running {:?}", cmd.as_std().get_program()))
+            .stage(PtyError::Spawn)?;
 
-        Ok(Self {
+        let pid = process.id();
+        let terminal = Self {
             handle,
-            process,
-            termin: Some(TerminalIn(termin)),
-            termout: Some(TerminalOut(termout)),
+            termerr: process.stderr.take(),
+            process: SharedChild::new(process),
+            termin: Some(TerminalIn::new(termin)),
+            termout: Some(TerminalOut::new(termout)),
+            #[cfg(feature = "scrollback")]
+            scrollback: None,
+        };
+
+        if let Some(pid) = pid {
+            configure_console(pid, options).stage(PtyError::Spawn)?;
+        }
+
+        Ok(terminal)
+    }
+
+    /// Returns the OS-assigned process identifier of the child, `None` once it was reaped.
+    pub fn id(&self) -> Option<u32> {
+        self.process.id()
+    }
+
+    /// Returns a handle for resizing, signalling and waiting that can be moved to another task.
+    pub fn controller(&self) -> TerminalController {
+        TerminalController {
+            handle: self.handle.clone(),
+            process: self.process.clone(),
+        }
+    }
+
+    /// Waits until the last slave descriptor is closed, usually because the child exited.
+    #[cfg(unix)]
+    pub async fn wait_for_hangup(&self) -> io::Result<()> {
+        let handle = self.handle.clone();
+
+        tokio::task::spawn_blocking(move || handle.wait_for_hangup())
+            .await
+            .map_err(io::Error::other)?
+    }
+
+    /// Keeps the terminal's size in sync with the terminal of the calling process, see
+    /// [`crate::Terminal::follow_host_resize`].
+    pub fn follow_host_resize(&self) -> io::Result<ResizeFollower> {
+        ResizeFollower::start(self.handle.clone())
+    }
+
+    /// Records all traffic going through the terminal's input and output into `dump`.
+    #[cfg(feature = "dump")]
+    pub fn set_traffic_dump(&mut self, dump: TrafficDump) {
+        if let Some(termin) = self.termin.as_mut() {
+            termin.dump = Some(dump.clone());
+        }
+        if let Some(termout) = self.termout.as_mut() {
+            termout.dump = Some(dump);
+        }
+    }
+
+    /// Calls `callback` with each window title the child sets, see
+    /// [`crate::Terminal::on_title_change`].
+    #[cfg(feature = "title")]
+    pub fn on_title_change(&mut self, callback: impl FnMut(&str) + Send + 'static) {
+        if let Some(termout) = self.termout.as_mut() {
+            termout.title = Some(TitleWatcher::new(callback));
+        }
+    }
+
+    /// Keeps the last `capacity` bytes read from `termout` from now on, see
+    /// [`crate::Terminal::enable_scrollback`].
+    #[cfg(feature = "scrollback")]
+    pub fn enable_scrollback(&mut self, capacity: usize) {
+        let scrollback = Scrollback::new(capacity);
+        if let Some(termout) = self.termout.as_mut() {
+            termout.scrollback = Some(scrollback.clone());
+        }
+        self.scrollback = Some(scrollback);
+    }
+
+    /// Returns the output kept since [`enable_scrollback`](Self::enable_scrollback), `None`
+    /// unless it was called.
+    #[cfg(feature = "scrollback")]
+    pub fn scrollback(&self) -> Option<Vec<u8>> {
+        self.scrollback.as_ref().map(Scrollback::contents)
+    }
+
+    /// Delivers EOF to the child and drops the terminal's input, see [`TerminalIn::close`].
+    ///
+    /// Fails if `termin` was taken, closing the taken input has the same effect.
+    pub async fn close_input(&mut self) -> io::Result<()> {
+        let termin = self
+            .termin
+            .take()
+            .ok_or_else(|| io::Error::other("the terminal's input was taken"))?;
+
+        termin.close().await
+    }
+
+    /// Takes the output out of the terminal and turns it into one stream of its output and the
+    /// child's lifecycle, see [`TerminalEvent`].
+    ///
+    /// The stream ends after both `Hangup` and `Exited` were yielded, it waits for the child like
+    /// [`exited`](Self::exited). Fails if `termout` was taken.
+    #[cfg(feature = "stream")]
+    pub fn events(&mut self) -> io::Result<TerminalEvents> {
+        let termout = self
+            .termout
+            .take()
+            .ok_or_else(|| io::Error::other("the terminal's output was taken"))?;
+
+        Ok(TerminalEvents {
+            termout: Some(termout),
+            buffer: BytesMut::new(),
+            exit: Some(Box::pin(self.exited())),
         })
     }
 
-    pub async fn close(mut self) -> io::Result<()> {
-        self.process.kill().await?;
+    /// Takes the output out of the terminal and pumps it into a broadcast channel on a task of
+    /// its own, so several consumers can read all of it, e.g. a UI, a logger and a recorder.
+    ///
+    /// The channel keeps the last `capacity` chunks for subscribers falling behind, see
+    /// [`OutputBroadcast`]. Fails if `termout` was taken, must be called within a tokio runtime.
+    #[cfg(feature = "broadcast")]
+    pub fn broadcast_output(&mut self, capacity: usize) -> io::Result<OutputBroadcast> {
+        let termout = self
+            .termout
+            .take()
+            .ok_or_else(|| io::Error::other("the terminal's output was taken"))?;
+        let (sender, receiver) = broadcast::channel(capacity);
+
+        Ok(OutputBroadcast {
+            receiver,
+            pump: tokio::spawn(pump_output(termout, sender)),
+        })
+    }
+
+    /// Takes both I/O halves out of the terminal, `None` if either of them was already taken.
+    pub fn split(&mut self) -> Option<(TerminalIn, TerminalOut)> {
+        if self.termin.is_none() || self.termout.is_none() {
+            return None;
+        }
+
+        Some((self.termin.take()?, self.termout.take()?))
+    }
+
+    /// Splits the terminal into owned I/O halves and a controller, e.g. to move each into a task
+    /// of its own.
+    ///
+    /// The child lives on in the controller, a separated stderr is dropped unless `termerr` was
+    /// taken before. Fails if either I/O half was taken.
+    pub fn into_split(mut self) -> io::Result<(TerminalIn, TerminalOut, TerminalController)> {
+        let (termin, termout) = self
+            .split()
+            .ok_or_else(|| io::Error::other("the terminal's I/O halves were taken"))?;
+
+        Ok((termin, termout, self.controller()))
+    }
+
+    /// Takes both I/O halves out of the terminal as one duplex stream, see [`split`](Self::split).
+    pub fn stream(&mut self) -> Option<TerminalStream> {
+        self.split()
+            .map(|(termin, termout)| TerminalStream::new(termin, termout))
+    }
+
+    /// Copies data between the terminal and `stream` in both directions until the session ends.
+    ///
+    /// Once the terminal's output ends, `stream` is shut down for writing and the bridge returns,
+    /// no matter whether `stream` is done sending. EOF from `stream` only stops the copy towards
+    /// the terminal. Fails if either I/O half was taken.
+    pub async fn bridge<S>(&mut self, stream: &mut S) -> io::Result<BridgeStats>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + ?Sized,
+    {
+        let (mut termin, mut termout) = self
+            .split()
+            .ok_or_else(|| io::Error::other("the terminal's I/O halves were taken"))?;
+
+        let result = bridge(&mut termin, &mut termout, stream).await;
+        self.unsplit(termin, termout);
+
+        result
+    }
+
+    /// Hands the terminal of the calling process over to the child, see
+    /// [`crate::Terminal::interact`].
+    ///
+    /// This runs the blocking forwarding loop on tokio's blocking pool rather than on the runtime.
+    /// The loop bypasses the I/O halves, which have to be in place, so traffic dumps don't see it.
+    /// Dropping the future doesn't stop the loop, it goes on until the child's output ends. Like
+    /// the blocking version, it's only available on Unix.
+    #[cfg(unix)]
+    pub async fn interact(&mut self) -> io::Result<()> {
+        self.interact_with(&mut InteractOptions::new()).await
+    }
+
+    /// Like [`interact`](Self::interact), handling the control keys as configured by `options`,
+    /// see [`crate::Terminal::interact_with`].
+    ///
+    /// The blocking thread holds `options` while the loop runs. They are left at their defaults
+    /// until the loop ends, and for good if the future is dropped first.
+    #[cfg(unix)]
+    pub async fn interact_with(&mut self, options: &mut InteractOptions) -> io::Result<()> {
+        if self.termin.is_none() || self.termout.is_none() {
+            return Err(io::Error::other("the terminal's I/O halves were taken"));
+        }
+
+        let handle = self.handle.clone();
+        let (mut input, mut output) = (handle.master(), handle.master());
+        let stop = ReadInterrupt::new()?;
+        let mut taken = std::mem::take(options);
+
+        let (result, taken) = tokio::task::spawn_blocking(move || {
+            let result = interact(&handle, &mut input, &mut output, &stop, &mut taken);
+            (result, taken)
+        })
+        .await
+        .map_err(io::Error::other)?;
+        *options = taken;
+
+        result
+    }
+
+    /// Hands the terminal's I/O to pump tasks, returning a sender of chunks to write, a receiver
+    /// of the output and a handle to the pumps completing with the child's exit status.
+    ///
+    /// Each channel holds up to `capacity` chunks. Dropping every sender delivers EOF to the
+    /// child, the receiver yields `None` once the output ended. Fails if either I/O half was
+    /// taken, must be called within a tokio runtime.
+    #[cfg(feature = "channels")]
+    pub fn into_channels(mut self, capacity: usize) -> io::Result<TerminalChannels> {
+        let (termin, termout) = self
+            .split()
+            .ok_or_else(|| io::Error::other("the terminal's I/O halves were taken"))?;
+        let (input_sender, input_receiver) = mpsc::channel(capacity);
+        let (output_sender, output_receiver) = mpsc::channel(capacity);
+
+        let pumps = tokio::spawn(async move {
+            let (handle, mut process, _) = self.into_parts().await;
+
+            let status = tokio::spawn(async move {
+                let status = process.wait().await;
+                // A pseudoconsole keeps the output open until it is closed.
+                drop(handle);
+                status
+            });
+
+            pump_channels(termin, termout, input_receiver, output_sender).await?;
+            status.await.map_err(io::Error::other)?
+        });
+
+        Ok((input_sender, output_receiver, pumps))
+    }
+
+    /// Puts the halves returned by [`split`](Self::split) back into the terminal.
+    pub fn unsplit(&mut self, termin: TerminalIn, termout: TerminalOut) {
+        self.termin = Some(termin);
+        self.termout = Some(termout);
+    }
+
+    /// Waits for the child to exit and returns its exit status.
+    ///
+    /// Cancel safe, so it can be raced against reads from `termout` in `select!`.
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        self.process.wait().await
+    }
+
+    /// Returns a future completing with the child's exit status, independent of the terminal.
+    ///
+    /// It can be moved to another task while the I/O halves are used elsewhere. Like
+    /// [`TerminalController::wait`], it doesn't hold up other calls needing the child, e.g.
+    /// [`close`](Self::close).
+    pub fn exited(&self) -> impl Future<Output = io::Result<ExitStatus>> + Send + 'static {
+        let process = self.process.clone();
+
+        async move { process.wait().await }
+    }
+
+    /// Waits for the child to exit, shutting the session down once `token` is cancelled.
+    ///
+    /// On cancellation the input is closed, if it wasn't taken, and the child is shut down as
+    /// described by `behavior`, e.g. signalled and killed after a timeout with
+    /// [`CloseBehavior::Graceful`]. Take out the I/O halves beforehand to serve them elsewhere,
+    /// e.g. on the connection owning the session.
+    #[cfg(feature = "cancellation")]
+    pub async fn wait_until_cancelled(
+        mut self,
+        token: CancellationToken,
+        behavior: CloseBehavior,
+    ) -> io::Result<ExitStatus> {
+        if let Some(status) = token.run_until_cancelled(self.exited()).await {
+            return status;
+        }
+
+        if let Some(termin) = self.termin.take() {
+            // Fails if the child is gone already, which the close below reports anyway.
+            let _ = termin.close().await;
+        }
+
+        self.close_with(behavior).await
+    }
+
+    /// Returns whether the child is still running.
+    ///
+    /// Never waits, also not while a controller waits for the child.
+    pub fn is_alive(&self) -> bool {
+        self.process.is_alive()
+    }
+
+    /// Returns the exit status of the child once it was reaped by a wait on the terminal or one
+    /// of its controllers.
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.process.exit_status()
+    }
+
+    /// Waits for the child to exit while collecting everything it writes to the terminal.
+    ///
+    /// See [`crate::Terminal::wait_with_output`].
+    pub async fn wait_with_output(self) -> io::Result<Output> {
+        let (handle, mut process, (_termin, termout)) = self.into_parts().await;
+
+        let reader = termout.map(read_to_end_in_background);
+        let stderr_reader = process.stderr.take().map(read_to_end_in_background);
+
+        let status = process.wait().await;
+
+        // A pseudoconsole keeps the output open until it is closed, even after the child exited.
+        drop(handle);
+
+        let join = |reader: Option<JoinHandle<io::Result<Vec<u8>>>>| async {
+            match reader {
+                Some(reader) => reader.await.map_err(io::Error::other)?,
+                None => Ok(Vec::new()),
+            }
+        };
+        let stdout = join(reader).await?;
+        let stderr = join(stderr_reader).await?;
+
+        Ok(Output {
+            status: status?,
+            stdout,
+            stderr,
+        })
+    }
+
+    /// Splits the terminal into its handle, the child and the I/O halves.
+    ///
+    /// Waits for calls on controllers that currently use the child, a pending wait for it fails.
+    pub async fn into_parts(
+        self,
+    ) -> (
+        TerminalHandle,
+        Child,
+        (Option<TerminalIn>, Option<TerminalOut>),
+    ) {
+        let mut process = self
+            .process
+            .lock_slot()
+            .await
+            .take()
+            .expect("the child is only taken out by into_parts()");
+
+        // A separated stderr goes back to where `Command::spawn` put it.
+        process.stderr = self.termerr;
+
+        (self.handle, process, (self.termin, self.termout))
+    }
+
+    /// Kills the child and waits for it to exit.
+    pub async fn close(self) -> io::Result<()> {
+        self.close_with(CloseBehavior::Force).await?;
 
         Ok(())
     }
+
+    /// Kills the child and waits for it to exit, blocking the calling thread instead of needing an
+    /// async context, e.g. in `Drop`.
+    ///
+    /// Like [`into_parts`](Self::into_parts) it waits for calls on controllers that currently
+    /// use the child, which never finish if they run on the blocked thread.
+    ///
+    /// # Panics
+    ///
+    /// Panics if called on a runtime thread, e.g. from an async function. Use
+    /// `tokio::task::spawn_blocking` to call it from there.
+    pub fn close_blocking(self) -> io::Result<()> {
+        let _want = Want::new(&self.process);
+        let mut child = self.process.child.blocking_lock();
+        let child = child
+            .as_mut()
+            .expect("the child is only taken out by into_parts()");
+
+        // Killing fails once the child was reaped.
+        if child.try_wait()?.is_none() {
+            child.start_kill()?;
+        }
+        loop {
+            if let Some(status) = child.try_wait()? {
+                self.process.set_status(status);
+                return Ok(());
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    /// Shuts the child down as described by `behavior` and returns its exit status.
+    ///
+    /// See [`crate::Terminal::close_with`].
+    pub async fn close_with(self, behavior: CloseBehavior) -> io::Result<ExitStatus> {
+        // The child stays shared, so pending waits on controllers see its exit. The input is only
+        // dropped on return, its EOF would end children like `cat` on its own.
+        let Self {
+            handle: _handle,
+            process,
+            termin: _termin,
+            ..
+        } = self;
+
+        match behavior {
+            CloseBehavior::Force => process.start_kill().await?,
+            CloseBehavior::Graceful { signal, timeout } => {
+                #[cfg(unix)]
+                if let Some(pid) = process.lock().await?.id() {
+                    send_signal(pid, signal)?;
+                }
+                #[cfg(windows)]
+                let _ = signal;
+                #[cfg(windows)]
+                drop(_handle);
+
+                if let Ok(status) = tokio::time::timeout(timeout, process.wait()).await {
+                    return status;
+                }
+
+                process.start_kill().await?;
+            }
+            CloseBehavior::WaitOnly => {}
+        }
+
+        process.wait().await
+    }
+}
+
+/// A cloneable handle to a terminal and its child, independent of the terminal's I/O.
+///
+/// See [`crate::TerminalController`].
+#[derive(Clone)]
+pub struct TerminalController {
+    handle: TerminalHandle,
+    process: Arc<SharedChild>,
+}
+
+impl TerminalController {
+    /// Returns the OS-assigned process identifier of the child, `None` once it was reaped.
+    pub fn id(&self) -> Option<u32> {
+        self.process.id()
+    }
+
+    #[cfg(unix)]
+    pub fn get_term_size(&self) -> io::Result<TerminalSize> {
+        self.handle.get_term_size()
+    }
+
+    pub fn set_term_size(&self, new_size: TerminalSize) -> io::Result<()> {
+        self.handle.set_term_size(new_size).stage(PtyError::Resize)
+    }
+
+    /// Sends `signal` to the child, fails once the child was reaped.
+    #[cfg(unix)]
+    pub fn signal(&self, signal: i32) -> io::Result<()> {
+        send_signal(self.process.id().ok_or_else(reaped)?, signal)
+    }
+
+    /// Waits for the child to exit and returns its exit status.
+    ///
+    /// Other calls needing the child, on the terminal or any controller, go ahead in the meantime.
+    /// Fails once the child was taken out with [`Terminal::into_parts`].
+    pub async fn wait(&self) -> io::Result<ExitStatus> {
+        self.process.wait().await
+    }
+
+    /// Returns the exit status of the child if it has exited, without blocking.
+    pub async fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+        self.process.try_wait().await
+    }
+
+    /// Returns whether the child is still running, see [`Terminal::is_alive`].
+    pub fn is_alive(&self) -> bool {
+        self.process.is_alive()
+    }
+
+    /// Returns the exit status of the child once it was reaped, see [`Terminal::exit_status`].
+    pub fn exit_status(&self) -> Option<ExitStatus> {
+        self.process.exit_status()
+    }
 }
 
 pub trait CommandExt {
     fn spawn_terminal(self) -> io::Result<Terminal>;
+
+    /// Spawns the command in a new terminal configured by `options`.
+    fn spawn_terminal_with(self, options: &TerminalOptions) -> io::Result<Terminal>;
+
+    /// Spawns the command as a login shell of the current user.
+    ///
+    /// See [`crate::CommandExt::spawn_login_terminal`].
+    #[cfg(unix)]
+    fn spawn_login_terminal(self) -> io::Result<Terminal>;
 }
 
 impl CommandExt for StdCommand {
-    fn spawn_terminal(mut self) -> io::Result<Terminal> {
-        let (handle, (termin, termout)) = open_handle_and_io(&mut self)?;
+    fn spawn_terminal(self) -> io::Result<Terminal> {
+        self.spawn_terminal_with(&TerminalOptions::default())
+    }
+
+    fn spawn_terminal_with(mut self, options: &TerminalOptions) -> io::Result<Terminal> {
+        let (handle, (termin, termout)) = open_handle_and_io(options).stage(PtyError::Open)?;
+        // The spawn consumes the command, which can't be reused with anything left on it.
+        let _attachment = handle.attach(&mut self, options).stage(PtyError::Spawn)?;
+
+        handle.set_nonblocking().stage(PtyError::Open)?;
 
-        handle.set_nonblocking()?;
+        // Each direction is registered with the reactor on a descriptor of its own.
+        let termin = into_file(termin).and_then(async_master);
+        let termout = into_file(termout).and_then(async_master);
+        let (termin, termout) = (
+            termin.stage(PtyError::Open)?,
+            termout.stage(PtyError::Open)?,
+        );
 
-        Terminal::new(self, handle, (termin.into(), termout.into()))
+        Terminal::new(self, options, handle, (termin, termout))
+    }
+
+    #[cfg(unix)]
+    fn spawn_login_terminal(mut self) -> io::Result<Terminal> {
+        configure_login_shell(&mut self)?;
+
+        self.spawn_terminal()
     }
 }
 
-pub struct TerminalIn(File);
+/// The input of a terminal, dropping it and all its clones delivers EOF to the child while it
+/// reads lines, see [`crate::TerminalIn`].
+pub struct TerminalIn {
+    file: AsyncMaster,
+    // The number of inputs sharing the terminal, the last one dropped delivers EOF.
+    open: Arc<AtomicUsize>,
+    closed: bool,
+    #[cfg(feature = "dump")]
+    dump: Option<TrafficDump>,
+}
+
+impl TerminalIn {
+    fn new(file: AsyncMaster) -> Self {
+        Self {
+            file,
+            open: Arc::new(AtomicUsize::new(1)),
+            closed: false,
+            #[cfg(feature = "dump")]
+            dump: None,
+        }
+    }
+
+    /// Returns a new input writing to the same terminal through a duplicated descriptor.
+    ///
+    /// Dropping the input delivers EOF only once each of its clones is dropped as well.
+    pub async fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            file: self.file.try_clone().await?,
+            open: {
+                self.open.fetch_add(1, Ordering::Relaxed);
+                self.open.clone()
+            },
+            closed: false,
+            #[cfg(feature = "dump")]
+            dump: self.dump.clone(),
+        })
+    }
+
+    /// Flushes the input and delivers EOF to the child, see [`crate::TerminalIn::close`].
+    ///
+    /// Dropping the input sends the EOF without flushing, it may overtake writes still in flight.
+    /// Shutting the input down, e.g. at the end of [`tokio::io::copy`], closes it as well.
+    pub async fn close(mut self) -> io::Result<()> {
+        self.shutdown().await
+    }
+
+    /// Sends the terminal's EOF character, see [`crate::TerminalIn::send_eof`].
+    pub async fn send_eof(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Eof).await
+    }
+
+    /// Sends the terminal's interrupt character, see [`crate::TerminalIn::send_interrupt`].
+    pub async fn send_interrupt(&mut self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Interrupt).await
+    }
+
+    /// Writes `line` and a line terminator and flushes, see [`crate::TerminalIn::send_line`].
+    pub async fn send_line(&mut self, line: &str) -> io::Result<()> {
+        let mut data = line.as_bytes().to_vec();
+        data.extend_from_slice(line_terminator(&self.file)?);
+        self.write_all(&data).await?;
+
+        self.flush().await
+    }
+
+    async fn send_control_char(&mut self, char: ControlChar) -> io::Result<()> {
+        let bytes = control_char(&self.file, char)?;
+        self.write_all(&bytes).await?;
+
+        self.flush().await
+    }
+
+    /// Waits until the terminal may take more input, see [`tokio::net::TcpStream::writable`].
+    ///
+    /// The readiness can be a false positive, so [`try_write`](Self::try_write) may still fail
+    /// with `WouldBlock`, which waits for the next one.
+    #[cfg(unix)]
+    pub async fn writable(&self) -> io::Result<()> {
+        self.file.writable().await
+    }
+
+    /// Writes some of `buf` without waiting, failing with `WouldBlock` if the terminal is full.
+    #[cfg(unix)]
+    pub fn try_write(&self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.file.try_write(buf)?;
+        self.record(&buf[..written]);
+
+        Ok(written)
+    }
+
+    /// Turns the input into a sink of chunks to write, e.g. to forward websocket messages.
+    ///
+    /// Sending waits once 8 KiB are buffered, until the terminal took them. Closing the sink
+    /// delivers EOF, so does dropping it, but that discards what wasn't flushed.
+    #[cfg(feature = "stream")]
+    pub fn into_sink(self) -> impl Sink<Bytes, Error = io::Error> + Send + Unpin {
+        FramedWrite::with_capacity(self, BytesCodec::new(), CHUNK_SIZE)
+    }
+
+    #[cfg(feature = "dump")]
+    fn record(&self, data: &[u8]) {
+        if let Some(dump) = &self.dump {
+            dump.record(Direction::Input, data);
+        }
+    }
+
+    // Records the first `written` bytes of `bufs`.
+    fn record_vectored(&self, bufs: &[IoSlice<'_>], mut written: usize) {
+        for buf in bufs {
+            if written == 0 {
+                break;
+            }
+            let len = buf.len().min(written);
+            self.record(&buf[..len]);
+            written -= len;
+        }
+    }
+
+    #[cfg(not(feature = "dump"))]
+    fn record(&self, _data: &[u8]) {}
+}
+
+impl Drop for TerminalIn {
+    fn drop(&mut self) {
+        if self.closed || self.open.fetch_sub(1, Ordering::AcqRel) != 1 {
+            return;
+        }
+
+        send_eof_on_drop(&self.file);
+    }
+}
+
+fn shut_down() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::BrokenPipe,
+        "the terminal's input was shut down",
+    )
+}
 
 impl AsyncWrite for TerminalIn {
     fn poll_write(
@@ -60,25 +1055,193 @@ impl AsyncWrite for TerminalIn {
         cx: &mut std::task::Context<'_>,
         buf: &[u8],
     ) -> std::task::Poll<std::result::Result<usize, std::io::Error>> {
-        Pin::new(&mut self.0).poll_write(cx, buf)
+        if self.closed {
+            return Poll::Ready(Err(shut_down()));
+        }
+
+        let poll = Pin::new(&mut self.file).poll_write(cx, buf);
+        if let Poll::Ready(Ok(written)) = poll {
+            self.record(&buf[..written]);
+        }
+
+        poll
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        if self.closed {
+            return Poll::Ready(Err(shut_down()));
+        }
+
+        let poll = Pin::new(&mut self.file).poll_write_vectored(cx, bufs);
+        if let Poll::Ready(Ok(written)) = poll {
+            self.record_vectored(bufs, written);
+        }
+
+        poll
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.file.is_write_vectored()
     }
 
     fn poll_flush(
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<std::result::Result<(), std::io::Error>> {
-        Pin::new(&mut self.0).poll_flush(cx)
+        Pin::new(&mut self.file).poll_flush(cx)
     }
 
+    // Delivers EOF like `close()`, the terminal itself has no write side to shut down.
     fn poll_shutdown(
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<std::result::Result<(), std::io::Error>> {
-        Pin::new(&mut self.0).poll_shutdown(cx)
+        if self.closed {
+            return Poll::Ready(Ok(()));
+        }
+        std::task::ready!(Pin::new(&mut self.file).poll_flush(cx))?;
+
+        self.closed = true;
+        self.open.fetch_sub(1, Ordering::AcqRel);
+
+        Poll::Ready(send_control_char(&self.file, ControlChar::Eof))
     }
 }
 
-pub struct TerminalOut(File);
+pub struct TerminalOut {
+    file: AsyncMaster,
+    eio_as_eof: bool,
+    #[cfg(feature = "dump")]
+    dump: Option<TrafficDump>,
+    #[cfg(feature = "scrollback")]
+    scrollback: Option<Scrollback>,
+    #[cfg(feature = "title")]
+    title: Option<TitleWatcher>,
+}
+
+impl TerminalOut {
+    fn new(file: AsyncMaster) -> Self {
+        Self {
+            file,
+            eio_as_eof: true,
+            #[cfg(feature = "dump")]
+            dump: None,
+            #[cfg(feature = "scrollback")]
+            scrollback: None,
+            #[cfg(feature = "title")]
+            title: None,
+        }
+    }
+
+    /// Returns a new output reading from the same terminal through a duplicated descriptor.
+    ///
+    /// Both share a single stream, every byte is read by only one of them.
+    pub async fn try_clone(&self) -> io::Result<Self> {
+        Ok(Self {
+            file: self.file.try_clone().await?,
+            eio_as_eof: self.eio_as_eof,
+            #[cfg(feature = "dump")]
+            dump: self.dump.clone(),
+            #[cfg(feature = "scrollback")]
+            scrollback: self.scrollback.clone(),
+            #[cfg(feature = "title")]
+            title: self.title.clone(),
+        })
+    }
+
+    /// Controls whether the `EIO` Linux reports once the last slave is closed is turned into EOF.
+    ///
+    /// Enabled by default, see [`crate::TerminalOut::set_eio_as_eof`].
+    pub fn set_eio_as_eof(&mut self, enabled: bool) {
+        self.eio_as_eof = enabled;
+    }
+
+    /// Reads some output into `buf`, failing with [`io::ErrorKind::TimedOut`] if there is none
+    /// within `timeout`.
+    ///
+    /// The error wraps tokio's [`Elapsed`](tokio::time::error::Elapsed). Nothing is read when
+    /// it times out, so the read can simply be retried with the same `buf`.
+    pub async fn read_timeout(&mut self, buf: &mut [u8], timeout: Duration) -> io::Result<usize> {
+        tokio::time::timeout(timeout, self.read(buf))
+            .await
+            .map_err(|elapsed| io::Error::new(io::ErrorKind::TimedOut, elapsed))?
+    }
+
+    /// Waits until there is output to read, see [`tokio::net::TcpStream::readable`].
+    ///
+    /// The readiness can be a false positive, so [`try_read`](Self::try_read) may still fail with
+    /// `WouldBlock`, which waits for the next one.
+    #[cfg(unix)]
+    pub async fn readable(&self) -> io::Result<()> {
+        self.file.readable().await
+    }
+
+    /// Reads some output into `buf` without waiting, failing with `WouldBlock` if there is none.
+    ///
+    /// Returns 0 once the output ended.
+    #[cfg(unix)]
+    pub fn try_read(&self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = match self.file.try_read(buf) {
+            Err(err) if self.eio_as_eof && is_session_end(&err) => 0,
+            result => result?,
+        };
+        self.record(&buf[..read]);
+
+        Ok(read)
+    }
+
+    /// Turns the output into a stream of the chunks read from the terminal, e.g. to forward
+    /// them as websocket messages. The stream ends with the output.
+    #[cfg(feature = "stream")]
+    pub fn into_stream(self) -> impl Stream<Item = io::Result<Bytes>> + Send + Unpin {
+        ReaderStream::with_capacity(self, CHUNK_SIZE)
+    }
+
+    /// Turns the output into a stream of its lines, without their line endings.
+    ///
+    /// A `\r` right before the `\n` is dropped as well, see [`crate::TerminalOut::lines`].
+    #[cfg(feature = "stream")]
+    pub fn lines_stream(self) -> LinesStream {
+        self.lines_stream_with(LineEndings::default())
+    }
+
+    /// Turns the output into a stream of its lines, split as described by `endings`.
+    #[cfg(feature = "stream")]
+    pub fn lines_stream_with(self, endings: LineEndings) -> LinesStream {
+        LinesStream {
+            termout: self,
+            splitter: LineSplitter::new(endings),
+            buffer: vec![0; CHUNK_SIZE].into_boxed_slice(),
+            pos: 0,
+            len: 0,
+            trim_controls: false,
+            ended: false,
+        }
+    }
+
+    #[cfg(any(feature = "dump", feature = "scrollback", feature = "title"))]
+    fn record(&self, data: &[u8]) {
+        #[cfg(feature = "dump")]
+        if let Some(dump) = &self.dump {
+            dump.record(Direction::Output, data);
+        }
+        #[cfg(feature = "scrollback")]
+        if let Some(scrollback) = &self.scrollback {
+            scrollback.record(data);
+        }
+        #[cfg(feature = "title")]
+        if let Some(title) = &self.title {
+            title.feed(data);
+        }
+    }
+
+    #[cfg(not(any(feature = "dump", feature = "scrollback", feature = "title")))]
+    fn record(&self, _data: &[u8]) {}
+}
 
 impl AsyncRead for TerminalOut {
     fn poll_read(
@@ -86,6 +1249,341 @@ impl AsyncRead for TerminalOut {
         cx: &mut std::task::Context<'_>,
         dst: &mut tokio::io::ReadBuf<'_>,
     ) -> std::task::Poll<std::result::Result<(), std::io::Error>> {
-        Pin::new(&mut self.0).poll_read(cx, dst)
+        let start = dst.filled().len();
+        let poll = match Pin::new(&mut self.file).poll_read(cx, dst) {
+            Poll::Ready(Err(err)) if self.eio_as_eof && is_session_end(&err) => Poll::Ready(Ok(())),
+            poll => poll,
+        };
+        if let Poll::Ready(Ok(())) = poll {
+            self.record(&dst.filled()[start..]);
+        }
+
+        poll
+    }
+}
+
+/// A stream of the lines of a terminal's output, see [`TerminalOut::lines_stream`].
+///
+/// Lines are decoded as UTF-8, replacing invalid sequences. A line that was pending when a read
+/// failed is kept for the next poll.
+#[cfg(feature = "stream")]
+pub struct LinesStream {
+    termout: TerminalOut,
+    splitter: LineSplitter,
+    // Output read but not split yet, from `pos` up to `len`.
+    buffer: Box<[u8]>,
+    pos: usize,
+    len: usize,
+    trim_controls: bool,
+    ended: bool,
+}
+
+#[cfg(feature = "stream")]
+impl LinesStream {
+    /// Controls whether control characters at the end of each line are removed, e.g. the bell
+    /// or the `\r` of a progress bar's redraw.
+    ///
+    /// Disabled by default.
+    pub fn set_trim_controls(&mut self, enabled: bool) {
+        self.trim_controls = enabled;
+    }
+
+    fn finish_line(&self, mut line: String) -> String {
+        if self.trim_controls {
+            line.truncate(line.trim_end_matches(char::is_control).len());
+        }
+
+        line
+    }
+}
+
+#[cfg(feature = "stream")]
+impl Stream for LinesStream {
+    type Item = io::Result<String>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if this.pos < this.len {
+                let (consumed, line) = this.splitter.split(&this.buffer[this.pos..this.len]);
+                this.pos += consumed;
+
+                if let Some(line) = line {
+                    return Poll::Ready(Some(Ok(this.finish_line(line))));
+                }
+                continue;
+            }
+
+            if this.ended {
+                return Poll::Ready(
+                    this.splitter
+                        .finish()
+                        .map(|line| Ok(this.finish_line(line))),
+                );
+            }
+
+            let mut buf = tokio::io::ReadBuf::new(&mut this.buffer);
+            match std::task::ready!(Pin::new(&mut this.termout).poll_read(cx, &mut buf)) {
+                Ok(()) => {
+                    (this.pos, this.len) = (0, buf.filled().len());
+                    this.ended = this.len == 0;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return Poll::Ready(Some(Err(err))),
+            }
+        }
+    }
+}
+
+/// What happened on a terminal, see [`Terminal::events`].
+#[cfg(feature = "stream")]
+#[derive(Debug)]
+pub enum TerminalEvent {
+    /// A chunk read from the terminal's output.
+    Output(Bytes),
+    /// The child exited. It may come before the last of the output.
+    Exited(ExitStatus),
+    /// The output ended, usually because the last slave was closed. On Windows the output only
+    /// ends once the terminal is dropped.
+    Hangup,
+}
+
+/// A stream of a terminal's output and lifecycle, see [`Terminal::events`].
+#[cfg(feature = "stream")]
+pub struct TerminalEvents {
+    // Dropped once the output ended.
+    termout: Option<TerminalOut>,
+    buffer: BytesMut,
+    // Dropped once the child was reaped.
+    exit: Option<Pin<Box<dyn Future<Output = io::Result<ExitStatus>> + Send>>>,
+}
+
+#[cfg(feature = "stream")]
+impl Stream for TerminalEvents {
+    type Item = io::Result<TerminalEvent>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        // Pending output is yielded first.
+        while let Some(termout) = this.termout.as_mut() {
+            this.buffer.reserve(CHUNK_SIZE);
+
+            match poll_read_buf(Pin::new(termout), cx, &mut this.buffer) {
+                Poll::Ready(Ok(0)) => {
+                    this.termout = None;
+                    return Poll::Ready(Some(Ok(TerminalEvent::Hangup)));
+                }
+                Poll::Ready(Ok(_)) => {
+                    let chunk = this.buffer.split().freeze();
+                    return Poll::Ready(Some(Ok(TerminalEvent::Output(chunk))));
+                }
+                Poll::Ready(Err(err)) if err.kind() == io::ErrorKind::Interrupted => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                Poll::Pending => break,
+            }
+        }
+
+        if let Some(exit) = this.exit.as_mut() {
+            if let Poll::Ready(status) = exit.as_mut().poll(cx) {
+                this.exit = None;
+                return Poll::Ready(Some(status.map(TerminalEvent::Exited)));
+            }
+        }
+
+        if this.termout.is_none() && this.exit.is_none() {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+/// The output of a terminal fanned out to subscribers, see [`Terminal::broadcast_output`].
+///
+/// Each subscriber receives every chunk read after it subscribed. One that falls more than the
+/// channel's capacity behind misses the oldest chunks and gets [`RecvError::Lagged`] instead, the
+/// pump never waits for it. Once the output ended, or reading it failed, receivers get
+/// [`RecvError::Closed`].
+///
+/// [`RecvError::Lagged`]: broadcast::error::RecvError::Lagged
+/// [`RecvError::Closed`]: broadcast::error::RecvError::Closed
+#[cfg(feature = "broadcast")]
+pub struct OutputBroadcast {
+    // Only used to subscribe, the pump holds the sender so the channel closes when it returns.
+    receiver: broadcast::Receiver<Bytes>,
+    pump: JoinHandle<io::Result<()>>,
+}
+
+#[cfg(feature = "broadcast")]
+impl OutputBroadcast {
+    /// Returns a new receiver for the output from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<Bytes> {
+        self.receiver.resubscribe()
+    }
+
+    /// Waits until the output ended, returning the error reading it failed with, if any.
+    pub async fn finished(self) -> io::Result<()> {
+        self.pump.await.map_err(io::Error::other)?
+    }
+}
+
+/// Both halves of a terminal's I/O in one duplex stream, e.g. for
+/// [`tokio::io::copy_bidirectional`].
+pub struct TerminalStream {
+    termin: TerminalIn,
+    termout: TerminalOut,
+}
+
+impl TerminalStream {
+    pub fn new(termin: TerminalIn, termout: TerminalOut) -> Self {
+        Self { termin, termout }
+    }
+
+    /// Splits the stream back into its halves, e.g. for [`Terminal::unsplit`].
+    pub fn into_split(self) -> (TerminalIn, TerminalOut) {
+        (self.termin, self.termout)
+    }
+}
+
+impl AsyncRead for TerminalStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        dst: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.termout).poll_read(cx, dst)
+    }
+}
+
+impl AsyncWrite for TerminalStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.termin).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.termin).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.termin.is_write_vectored()
+    }
+
+    fn poll_flush(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.termin).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.termin).poll_shutdown(cx)
+    }
+}
+
+// The `futures-io` traits, for code written against those rather than tokio's.
+#[cfg(feature = "futures-io")]
+fn poll_read_slice(
+    reader: Pin<&mut impl AsyncRead>,
+    cx: &mut std::task::Context<'_>,
+    buf: &mut [u8],
+) -> Poll<io::Result<usize>> {
+    let mut buf = tokio::io::ReadBuf::new(buf);
+    std::task::ready!(reader.poll_read(cx, &mut buf))?;
+
+    Poll::Ready(Ok(buf.filled().len()))
+}
+
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncRead for TerminalOut {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_read_slice(self, cx, buf)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncRead for TerminalStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        poll_read_slice(self, cx, buf)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncWrite for TerminalIn {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write_vectored(self, cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_shutdown(self, cx)
+    }
+}
+
+#[cfg(feature = "futures-io")]
+impl futures_io::AsyncWrite for TerminalStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write(self, cx, buf)
+    }
+
+    fn poll_write_vectored(
+        self: Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        AsyncWrite::poll_write_vectored(self, cx, bufs)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_flush(self, cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut std::task::Context<'_>) -> Poll<io::Result<()>> {
+        AsyncWrite::poll_shutdown(self, cx)
     }
 }
@@ -0,0 +1,303 @@
+use std::ffi::OsString;
+#[cfg(unix)]
+use std::fmt;
+#[cfg(unix)]
+use std::os::fd::RawFd;
+use std::process::Command;
+
+use crate::{SizePolicy, TerminalSize};
+
+/// Options for spawning a command in a new terminal.
+///
+/// Mirrors [`std::fs::OpenOptions`]: configure the options and pass them to
+/// [`CommandExt::spawn_terminal_with`](crate::CommandExt::spawn_terminal_with).
+#[derive(Clone, Debug)]
+pub struct TerminalOptions {
+    pub(crate) size: Option<TerminalSize>,
+    pub(crate) size_policy: SizePolicy,
+    pub(crate) pixel_size: Option<(u16, u16)>,
+    pub(crate) term: Option<OsString>,
+    pub(crate) size_env: bool,
+    pub(crate) raw_mode: bool,
+    pub(crate) controlling_terminal: bool,
+    pub(crate) kill_on_drop: bool,
+    #[cfg(unix)]
+    pub(crate) pty_stdio: [bool; 3],
+    #[cfg(unix)]
+    pub(crate) separate_stderr: bool,
+    #[cfg(unix)]
+    pub(crate) slave_fds: Vec<RawFd>,
+    #[cfg(windows)]
+    pub(crate) pseudoconsole_flags: u32,
+    #[cfg(windows)]
+    pub(crate) console_code_page: Option<u32>,
+    #[cfg(windows)]
+    pub(crate) virtual_terminal_input: Option<bool>,
+    #[cfg(windows)]
+    pub(crate) virtual_terminal_processing: Option<bool>,
+}
+
+impl TerminalOptions {
+    pub fn new() -> Self {
+        Self {
+            size: None,
+            size_policy: SizePolicy::Reject,
+            pixel_size: None,
+            term: None,
+            size_env: false,
+            raw_mode: false,
+            controlling_terminal: true,
+            kill_on_drop: false,
+            #[cfg(unix)]
+            pty_stdio: [true; 3],
+            #[cfg(unix)]
+            separate_stderr: false,
+            #[cfg(unix)]
+            slave_fds: Vec::new(),
+            #[cfg(windows)]
+            pseudoconsole_flags: 0,
+            #[cfg(windows)]
+            console_code_page: None,
+            #[cfg(windows)]
+            virtual_terminal_input: None,
+            #[cfg(windows)]
+            virtual_terminal_processing: None,
+        }
+    }
+
+    /// Sets the size the terminal is created with.
+    pub fn size(&mut self, size: TerminalSize) -> &mut Self {
+        self.size = Some(size);
+        self
+    }
+
+    /// Sets how sizes outside of the supported range are treated, rejected by default.
+    ///
+    /// Applies to [`size`](Self::size) and every later `set_term_size()`.
+    pub fn size_policy(&mut self, size_policy: SizePolicy) -> &mut Self {
+        self.size_policy = size_policy;
+        self
+    }
+
+    /// Sets the size of the terminal in pixels, ignored on Windows.
+    pub fn pixel_size(&mut self, width: u16, height: u16) -> &mut Self {
+        self.pixel_size = Some((width, height));
+        self
+    }
+
+    /// Sets the `TERM` environment variable of the child.
+    pub fn term(&mut self, term: impl Into<OsString>) -> &mut Self {
+        self.term = Some(term.into());
+        self
+    }
+
+    /// Exports `LINES` and `COLUMNS` matching [`size`](Self::size) to the child.
+    ///
+    /// For programs that read the environment instead of asking the terminal. The variables don't
+    /// follow later resizes.
+    pub fn size_env(&mut self, size_env: bool) -> &mut Self {
+        self.size_env = size_env;
+        self
+    }
+
+    /// Puts the terminal into raw mode before the child starts, ignored on Windows.
+    pub fn raw_mode(&mut self, raw_mode: bool) -> &mut Self {
+        self.raw_mode = raw_mode;
+        self
+    }
+
+    /// Controls whether the terminal becomes the controlling terminal of the child.
+    ///
+    /// Enabled by default. When disabled, the child stays in the session of the parent and only
+    /// its standard streams are connected to the terminal. Ignored on Windows.
+    pub fn controlling_terminal(&mut self, controlling_terminal: bool) -> &mut Self {
+        self.controlling_terminal = controlling_terminal;
+        self
+    }
+
+    /// Kills the child when its `Terminal` is dropped.
+    ///
+    /// Disabled by default, mirroring tokio's `Command::kill_on_drop`. A child taken out with
+    /// `into_parts()` is never killed.
+    pub fn kill_on_drop(&mut self, kill_on_drop: bool) -> &mut Self {
+        self.kill_on_drop = kill_on_drop;
+        self
+    }
+
+    /// Controls whether the child's stdin is connected to the terminal.
+    ///
+    /// Enabled by default. When disabled, the stdin configured on the command is kept, e.g. a
+    /// file or a pipe feeding a program that only needs a terminal for its output.
+    #[cfg(unix)]
+    pub fn pty_stdin(&mut self, pty_stdin: bool) -> &mut Self {
+        self.pty_stdio[0] = pty_stdin;
+        self
+    }
+
+    /// Controls whether the child's stdout is connected to the terminal, see
+    /// [`pty_stdin`](Self::pty_stdin).
+    #[cfg(unix)]
+    pub fn pty_stdout(&mut self, pty_stdout: bool) -> &mut Self {
+        self.pty_stdio[1] = pty_stdout;
+        self
+    }
+
+    /// Controls whether the child's stderr is connected to the terminal, see
+    /// [`pty_stdin`](Self::pty_stdin).
+    #[cfg(unix)]
+    pub fn pty_stderr(&mut self, pty_stderr: bool) -> &mut Self {
+        self.pty_stdio[2] = pty_stderr;
+        self
+    }
+
+    /// Routes the child's stderr through a pipe instead of the terminal.
+    ///
+    /// The pipe is available as `termerr` on the spawned `Terminal`, overriding
+    /// [`pty_stderr`](Self::pty_stderr).
+    #[cfg(unix)]
+    pub fn separate_stderr(&mut self, separate_stderr: bool) -> &mut Self {
+        self.separate_stderr = separate_stderr;
+        self
+    }
+
+    /// Connects the descriptor `fd` of the child to the terminal, in addition to its stdio.
+    ///
+    /// Can be called repeatedly, e.g. for programs that write prompts to fd 3. Needs at least one
+    /// of the standard streams on the terminal.
+    #[cfg(unix)]
+    pub fn slave_fd(&mut self, fd: RawFd) -> &mut Self {
+        self.slave_fds.push(fd);
+        self
+    }
+
+    /// Sets the flags passed to `CreatePseudoConsole`, e.g. `PSEUDOCONSOLE_INHERIT_CURSOR`.
+    #[cfg(windows)]
+    pub fn pseudoconsole_flags(&mut self, flags: u32) -> &mut Self {
+        self.pseudoconsole_flags = flags;
+        self
+    }
+
+    /// Sets the input and output code page of the child's console, e.g. `65001` for UTF-8.
+    ///
+    /// Console settings are applied right after the child started. The calling process is
+    /// detached from its own console while doing so and reattached to its parent's console.
+    #[cfg(windows)]
+    pub fn console_code_page(&mut self, code_page: u32) -> &mut Self {
+        self.console_code_page = Some(code_page);
+        self
+    }
+
+    /// Sets or clears `ENABLE_VIRTUAL_TERMINAL_INPUT` on the child's console input.
+    #[cfg(windows)]
+    pub fn virtual_terminal_input(&mut self, enabled: bool) -> &mut Self {
+        self.virtual_terminal_input = Some(enabled);
+        self
+    }
+
+    /// Sets or clears `ENABLE_VIRTUAL_TERMINAL_PROCESSING` on the child's console output.
+    #[cfg(windows)]
+    pub fn virtual_terminal_processing(&mut self, enabled: bool) -> &mut Self {
+        self.virtual_terminal_processing = Some(enabled);
+        self
+    }
+
+    #[cfg(windows)]
+    pub(crate) fn configures_console(&self) -> bool {
+        self.console_code_page.is_some()
+            || self.virtual_terminal_input.is_some()
+            || self.virtual_terminal_processing.is_some()
+    }
+
+    pub(crate) fn configure(&self, cmd: &mut Command) {
+        if let Some(term) = &self.term {
+            cmd.env("TERM", term);
+        }
+        if let Some(size) = self.size.filter(|_| self.size_env) {
+            cmd.env("LINES", size.rows.to_string());
+            cmd.env("COLUMNS", size.columns.to_string());
+        }
+    }
+}
+
+impl Default for TerminalOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Options for handing the host terminal over to a child, see
+/// [`Terminal::interact_with`](crate::Terminal::interact_with).
+///
+/// Only available on Unix, like `interact_with()`. Windows has no console equivalent of these
+/// policies yet, e.g. routing `CTRL_C_EVENT` through `SetConsoleCtrlHandler`.
+#[cfg(unix)]
+#[derive(Debug, Default)]
+pub struct InteractOptions {
+    pub(crate) interrupt: InterruptPolicy,
+    pub(crate) suspend: SuspendPolicy,
+}
+
+#[cfg(unix)]
+impl InteractOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets what a Ctrl-C typed on the host terminal does, it's passed on to the child by default.
+    ///
+    /// Applies to the interrupt character the host terminal is configured with.
+    pub fn interrupt(&mut self, policy: InterruptPolicy) -> &mut Self {
+        self.interrupt = policy;
+        self
+    }
+
+    /// Sets what a Ctrl-Z typed on the host terminal does, it's passed on to the child by default.
+    ///
+    /// Applies to the suspend character the host terminal is configured with.
+    pub fn suspend(&mut self, policy: SuspendPolicy) -> &mut Self {
+        self.suspend = policy;
+        self
+    }
+}
+
+/// What a Ctrl-C typed on the host terminal does while a child has it, see
+/// [`InteractOptions::interrupt`].
+#[cfg(unix)]
+#[derive(Default)]
+pub enum InterruptPolicy {
+    /// Passes it on to the child, whose terminal turns it into `SIGINT`.
+    #[default]
+    Forward,
+    /// Ends `interact_with()` with an [`io::ErrorKind::Interrupted`](std::io::ErrorKind) error,
+    /// the child keeps running.
+    Local,
+    /// Calls the callback, which returns whether to pass it on to the child.
+    Callback(Box<dyn FnMut() -> bool + Send>),
+}
+
+/// What a Ctrl-Z typed on the host terminal does while a child has it, see
+/// [`InteractOptions::suspend`].
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SuspendPolicy {
+    /// Passes it on to the child, whose terminal turns it into `SIGTSTP` for the child's own job
+    /// control.
+    #[default]
+    Forward,
+    /// Stops the calling process with `SIGTSTP`, the child keeps running.
+    ///
+    /// The host terminal is restored first, so the shell that started the calling process gets
+    /// it back in a usable state. Raw mode is entered again once the process is continued.
+    Suspend,
+}
+
+#[cfg(unix)]
+impl fmt::Debug for InterruptPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InterruptPolicy::Forward => f.write_str("Forward"),
+            InterruptPolicy::Local => f.write_str("Local"),
+            InterruptPolicy::Callback(_) => f.write_str("Callback(..)"),
+        }
+    }
+}
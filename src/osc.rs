@@ -0,0 +1,79 @@
+// Operating system commands (OSC) embedded in a terminal's output, `ESC ]` up to `BEL` or
+// `ESC \`. 8-bit C1 introducers are ignored, their bytes are part of UTF-8 characters.
+
+use std::ops::Range;
+
+// Longer commands are skipped rather than buffered without bound.
+const MAX_PAYLOAD: usize = 4096;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum State {
+    #[default]
+    Ground,
+    Escape,
+    Osc,
+    // An `ESC` inside the command, which starts the `ESC \` terminator.
+    OscEscape,
+}
+
+/// Finds the commands in output fed to it in chunks of any size.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct OscScanner {
+    state: State,
+    payload: Vec<u8>,
+    overflowed: bool,
+}
+
+impl OscScanner {
+    /// Calls `found` with the payload of each command completed in `data`, along with where the
+    /// command is in `data`. It starts at 0 if the command began in an earlier chunk.
+    pub(crate) fn scan(&mut self, data: &[u8], mut found: impl FnMut(&[u8], Range<usize>)) {
+        let mut start = 0;
+
+        for (i, &byte) in data.iter().enumerate() {
+            self.state = match (self.state, byte) {
+                (State::Ground, 0x1b) => {
+                    start = i;
+                    State::Escape
+                }
+                (State::Ground, _) => State::Ground,
+                (State::Escape, b']') => {
+                    self.payload.clear();
+                    self.overflowed = false;
+                    State::Osc
+                }
+                (State::Escape, 0x1b) => {
+                    start = i;
+                    State::Escape
+                }
+                (State::Escape, _) => State::Ground,
+                (State::Osc, 0x07) | (State::OscEscape, b'\\') => {
+                    if !self.overflowed {
+                        found(&self.payload, start..i + 1);
+                    }
+                    State::Ground
+                }
+                (State::Osc, 0x1b) => State::OscEscape,
+                // Any other escape sequence cancels the command and starts anew.
+                (State::OscEscape, _) => {
+                    start = i.saturating_sub(1);
+                    if byte == b']' {
+                        self.payload.clear();
+                        self.overflowed = false;
+                        State::Osc
+                    } else {
+                        State::Ground
+                    }
+                }
+                (State::Osc, _) => {
+                    if self.payload.len() < MAX_PAYLOAD {
+                        self.payload.push(byte);
+                    } else {
+                        self.overflowed = true;
+                    }
+                    State::Osc
+                }
+            };
+        }
+    }
+}
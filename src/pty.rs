@@ -0,0 +1,180 @@
+#[cfg(unix)]
+use std::fs::File;
+use std::io;
+#[cfg(unix)]
+use std::io::{Read, Write};
+#[cfg(unix)]
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, OwnedFd, RawFd};
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::process::Command;
+#[cfg(unix)]
+use std::process::Stdio;
+
+use crate::error::Stage;
+use crate::sys::{open_handle_and_io, TerminalHandle, TerminalInput, TerminalOutput};
+use crate::{PtyError, Terminal, TerminalOptions, TerminalSize};
+
+/// A PTY opened ahead of any child.
+///
+/// Commands are spawned on it later with [`Pty::spawn`], each of them getting a [`Terminal`] of
+/// its own on the same PTY.
+pub struct Pty {
+    handle: TerminalHandle,
+    io: (TerminalInput, TerminalOutput),
+    options: TerminalOptions,
+}
+
+impl Pty {
+    /// Opens a PTY of the given size.
+    pub fn open(size: TerminalSize) -> io::Result<Self> {
+        Self::open_with(TerminalOptions::new().size(size))
+    }
+
+    /// Opens a PTY configured by `options`, which also apply to every command spawned on it.
+    pub fn open_with(options: &TerminalOptions) -> io::Result<Self> {
+        let (handle, io) = open_handle_and_io(options).stage(PtyError::Open)?;
+
+        Ok(Self {
+            handle,
+            io,
+            options: options.clone(),
+        })
+    }
+
+    /// Returns the handle used to query and resize the PTY.
+    pub fn handle(&self) -> &TerminalHandle {
+        &self.handle
+    }
+
+    /// Spawns `cmd` attached to the PTY.
+    pub fn spawn(&self, cmd: &mut Command) -> io::Result<Terminal> {
+        let io = (self.io.0.try_clone()?, self.io.1.try_clone()?);
+
+        Terminal::new(cmd, &self.options, self.handle.clone(), io)
+    }
+}
+
+/// Both ends of a PTY, for use without any of the `Command` integration.
+#[cfg(unix)]
+pub struct PtyPair {
+    pub master: PtyMaster,
+    pub slave: PtySlave,
+}
+
+#[cfg(unix)]
+impl PtyPair {
+    pub fn open(options: &TerminalOptions) -> io::Result<Self> {
+        let (handle, _) = open_handle_and_io(options).stage(PtyError::Open)?;
+        let slave = PtySlave(handle.open_slave().stage(PtyError::Open)?);
+
+        Ok(Self {
+            master: PtyMaster {
+                file: handle.master(),
+                handle,
+            },
+            slave,
+        })
+    }
+}
+
+/// The master end of a PTY, reading what the slave side writes and the other way around.
+#[cfg(unix)]
+pub struct PtyMaster {
+    handle: TerminalHandle,
+    file: TerminalInput,
+}
+
+#[cfg(unix)]
+impl PtyMaster {
+    pub fn get_term_size(&self) -> io::Result<TerminalSize> {
+        self.handle.get_term_size()
+    }
+
+    pub fn set_term_size(&self, new_size: TerminalSize) -> io::Result<()> {
+        self.handle.set_term_size(new_size).stage(PtyError::Resize)
+    }
+
+    /// Returns the path of the slave device.
+    pub fn slave_name(&self) -> io::Result<PathBuf> {
+        self.handle.slave_name()
+    }
+}
+
+#[cfg(unix)]
+impl Read for PtyMaster {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+#[cfg(unix)]
+impl Write for PtyMaster {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for PtyMaster {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.handle.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for PtyMaster {
+    fn as_raw_fd(&self) -> RawFd {
+        self.as_fd().as_raw_fd()
+    }
+}
+
+/// The slave end of a PTY, to be handed to a child as its standard streams.
+#[cfg(unix)]
+pub struct PtySlave(OwnedFd);
+
+#[cfg(unix)]
+impl PtySlave {
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(PtySlave(self.0.try_clone()?))
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for PtySlave {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for PtySlave {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+#[cfg(unix)]
+impl From<PtySlave> for OwnedFd {
+    fn from(slave: PtySlave) -> Self {
+        slave.0
+    }
+}
+
+#[cfg(unix)]
+impl From<PtySlave> for File {
+    fn from(slave: PtySlave) -> Self {
+        slave.0.into()
+    }
+}
+
+#[cfg(unix)]
+impl From<PtySlave> for Stdio {
+    fn from(slave: PtySlave) -> Self {
+        slave.0.into()
+    }
+}
@@ -0,0 +1,868 @@
+//! Emulation of the screen a terminal's output draws, see [`Parser`].
+//!
+//! The output is run through a VT parser that keeps a grid of cells along with their
+//! attributes, which is what a user would see in a terminal emulator. Common VT100 and xterm
+//! sequences are understood: cursor movement, erasing, scroll regions, SGR attributes including
+//! 256 and true colors, and the alternate screen.
+//!
+//! ```
+//! use pseudoterminal::screen::Parser;
+//! use pseudoterminal::TerminalSize;
+//!
+//! let mut parser = Parser::new(TerminalSize { rows: 24, columns: 80 });
+//! parser.process(b"\x1b[1mbold\x1b[m plain");
+//!
+//! let cell = &parser.screen().row(0).unwrap()[0];
+//! assert_eq!(cell.contents(), "b");
+//! assert!(cell.attributes().bold);
+//! ```
+//!
+//! Frontends that redraw the screen elsewhere, e.g. a multiplexer or a web terminal, can send
+//! only what changed: [`Parser::take_damage`] tells which rows were touched and [`Screen::diff`]
+//! which cells differ between two snapshots.
+//!
+//! Tests of terminal applications can check what is drawn with [`assert_screen_contains`],
+//! [`Screen::cell_at`] and [`Screen::contents`], which renders the screen as text.
+//!
+//! [`Terminal::enable_screen`](crate::Terminal::enable_screen) feeds everything read from a
+//! terminal to a parser of its own.
+
+use std::fmt;
+use std::mem;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use unicode_width::UnicodeWidthChar;
+use vte::{Params, Perform};
+
+use crate::TerminalSize;
+
+// Terminals start out at 0x0 on some platforms, the screen needs at least one cell.
+pub(crate) const DEFAULT_SIZE: TerminalSize = TerminalSize {
+    rows: 24,
+    columns: 80,
+};
+
+const TAB_WIDTH: u16 = 8;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Color {
+    /// The terminal's default foreground or background.
+    #[default]
+    Default,
+    /// One of the 256 palette colors, the first 16 are the named ones.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+/// How a cell is drawn, set through SGR sequences.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Attributes {
+    pub foreground: Color,
+    pub background: Color,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+    pub blink: bool,
+    pub inverse: bool,
+    pub hidden: bool,
+    pub strikethrough: bool,
+}
+
+/// A character cell of a [`Screen`].
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Cell {
+    contents: String,
+    attributes: Attributes,
+    wide: bool,
+    wide_continuation: bool,
+}
+
+impl Cell {
+    /// Returns the character in the cell along with its combining characters, empty if nothing
+    /// was drawn there.
+    pub fn contents(&self) -> &str {
+        &self.contents
+    }
+
+    pub fn attributes(&self) -> Attributes {
+        self.attributes
+    }
+
+    /// Whether the cell holds a character two columns wide, which also covers the next cell.
+    pub fn is_wide(&self) -> bool {
+        self.wide
+    }
+
+    /// Whether the cell is covered by the wide character left of it.
+    pub fn is_wide_continuation(&self) -> bool {
+        self.wide_continuation
+    }
+}
+
+// Each change to a row gives it a new generation, unique across all screens, so rows of the same
+// generation are known to be equal without comparing their cells.
+static GENERATION: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Clone, Debug)]
+struct Row {
+    cells: Vec<Cell>,
+    generation: u64,
+}
+
+impl Row {
+    fn new(cells: Vec<Cell>) -> Self {
+        Self {
+            cells,
+            generation: GENERATION.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    // Every change goes through here.
+    fn cells_mut(&mut self) -> &mut Vec<Cell> {
+        self.generation = GENERATION.fetch_add(1, Ordering::Relaxed);
+        &mut self.cells
+    }
+}
+
+/// A cell that changed between two snapshots, see [`Screen::diff`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CellChange {
+    pub row: u16,
+    pub column: u16,
+    /// The cell as it is now.
+    pub cell: Cell,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Cursor {
+    row: u16,
+    column: u16,
+}
+
+/// A snapshot of an emulated screen.
+#[derive(Clone, Debug)]
+pub struct Screen {
+    size: TerminalSize,
+    grid: Vec<Row>,
+    // The primary grid while the alternate one is shown.
+    primary: Option<Vec<Row>>,
+    cursor: Cursor,
+    saved_cursor: (Cursor, Attributes),
+    attributes: Attributes,
+    // The first and last row scrolled by line feeds, inclusive.
+    scroll_region: (u16, u16),
+    // Set after a character was printed in the last column, the next one wraps first.
+    wrap_pending: bool,
+    autowrap: bool,
+    cursor_visible: bool,
+    application_cursor: bool,
+}
+
+impl Screen {
+    fn new(size: TerminalSize) -> Self {
+        let size = size.clamp();
+
+        Self {
+            size,
+            grid: blank_grid(size, Cell::default()),
+            primary: None,
+            cursor: Cursor::default(),
+            saved_cursor: Default::default(),
+            attributes: Attributes::default(),
+            scroll_region: (0, size.rows - 1),
+            wrap_pending: false,
+            autowrap: true,
+            cursor_visible: true,
+            application_cursor: false,
+        }
+    }
+
+    pub fn size(&self) -> TerminalSize {
+        self.size
+    }
+
+    /// Returns the row and column of the cursor, both starting at 0.
+    pub fn cursor_position(&self) -> (u16, u16) {
+        (self.cursor.row, self.cursor.column)
+    }
+
+    pub fn cursor_visible(&self) -> bool {
+        self.cursor_visible
+    }
+
+    /// Whether the program asked for the cursor keys in application mode, see
+    /// `KeyEncoder::application_cursor` of the `keys` feature.
+    pub fn application_cursor(&self) -> bool {
+        self.application_cursor
+    }
+
+    /// Whether the alternate screen full screen applications draw on is shown.
+    pub fn alternate_screen(&self) -> bool {
+        self.primary.is_some()
+    }
+
+    /// Returns the cells of `row`, starting at 0 for the top.
+    pub fn row(&self, row: u16) -> Option<&[Cell]> {
+        self.grid
+            .get(usize::from(row))
+            .map(|row| row.cells.as_slice())
+    }
+
+    /// Returns the cell at `row` and `column`, both starting at 0.
+    pub fn cell_at(&self, row: u16, column: u16) -> Option<&Cell> {
+        self.row(row)?.get(usize::from(column))
+    }
+
+    /// Returns the text of `row` without the blanks at its end, see [`contents`](Self::contents).
+    pub fn row_contents(&self, row: u16) -> Option<String> {
+        self.row(row).map(row_text)
+    }
+
+    /// Renders the screen as text, one line per row without the blanks at its end.
+    ///
+    /// Cells nothing was drawn in become spaces, attributes are left out. Empty rows at the
+    /// bottom are dropped. Also available through `Display`.
+    pub fn contents(&self) -> String {
+        let mut lines: Vec<String> = self.grid.iter().map(|row| row_text(&row.cells)).collect();
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
+
+        lines.join("\n")
+    }
+
+    /// Whether `text` is drawn within a row of the screen.
+    pub fn contains(&self, text: &str) -> bool {
+        self.grid
+            .iter()
+            .any(|row| row_text(&row.cells).contains(text))
+    }
+
+    /// Returns the cells that differ from `previous`, row by row from the top.
+    ///
+    /// Every cell is reported if the size changed. Rows that weren't touched since `previous`
+    /// was taken are skipped without comparing them, so diffing snapshots of one screen is
+    /// cheap. The cursor is left out, see [`cursor_position`](Self::cursor_position).
+    pub fn diff(&self, previous: &Screen) -> Vec<CellChange> {
+        let mut changes = Vec::new();
+
+        for (row, (now, before)) in (0..).zip(self.grid.iter().zip(&previous.grid)) {
+            if self.size != previous.size || now.generation != before.generation {
+                for (column, (cell, old)) in (0..).zip(now.cells.iter().zip(&before.cells)) {
+                    if self.size != previous.size || cell != old {
+                        changes.push(CellChange {
+                            row,
+                            column,
+                            cell: cell.clone(),
+                        });
+                    }
+                }
+            }
+        }
+
+        changes
+    }
+
+    fn set_size(&mut self, size: TerminalSize) {
+        let size = size.clamp();
+
+        // Rows cut off at the bottom would take the cursor with them, the top ones go instead.
+        let excess = (self.cursor.row + 1).saturating_sub(size.rows);
+        resize_grid(&mut self.grid, size, usize::from(excess));
+        if let Some(primary) = &mut self.primary {
+            resize_grid(primary, size, usize::from(excess));
+        }
+
+        self.size = size;
+        self.cursor.row -= excess;
+        self.cursor.column = self.cursor.column.min(size.columns - 1);
+        self.scroll_region = (0, size.rows - 1);
+        self.wrap_pending = false;
+    }
+
+    fn print(&mut self, c: char) {
+        let width = c.width().unwrap_or(0) as u16;
+
+        if width == 0 {
+            self.combine(c);
+            return;
+        }
+
+        if self.wrap_pending && self.autowrap {
+            self.cursor.column = 0;
+            self.line_feed();
+        }
+        self.wrap_pending = false;
+
+        if width == 2 && self.cursor.column + 1 >= self.size.columns {
+            if self.size.columns < 2 {
+                return;
+            }
+            if self.autowrap {
+                self.erase_cell(self.cursor.row, self.cursor.column);
+                self.cursor.column = 0;
+                self.line_feed();
+            } else {
+                self.cursor.column = self.size.columns - 2;
+            }
+        }
+
+        let Cursor { row, column } = self.cursor;
+        self.erase_cell(row, column);
+        if width == 2 {
+            self.erase_cell(row, column + 1);
+        }
+
+        let attributes = self.attributes;
+        let cells = self.grid[usize::from(row)].cells_mut();
+        cells[usize::from(column)] = Cell {
+            contents: c.to_string(),
+            attributes,
+            wide: width == 2,
+            wide_continuation: false,
+        };
+        if width == 2 {
+            cells[usize::from(column) + 1] = Cell {
+                attributes,
+                wide_continuation: true,
+                ..Cell::default()
+            };
+        }
+
+        if column + width < self.size.columns {
+            self.cursor.column += width;
+        } else {
+            self.wrap_pending = true;
+        }
+    }
+
+    // Appends a zero width character to the one printed last.
+    fn combine(&mut self, c: char) {
+        let Cursor { row, mut column } = self.cursor;
+        if !self.wrap_pending {
+            if column == 0 {
+                return;
+            }
+            column -= 1;
+        }
+
+        let cells = self.grid[usize::from(row)].cells_mut();
+        if cells[usize::from(column)].wide_continuation && column > 0 {
+            column -= 1;
+        }
+        cells[usize::from(column)].contents.push(c);
+    }
+
+    // Blanks a cell along with the other half of a wide character it is part of.
+    fn erase_cell(&mut self, row: u16, column: u16) {
+        let blank = self.blank();
+        let cells = self.grid[usize::from(row)].cells_mut();
+        let column = usize::from(column);
+
+        if cells[column].wide_continuation && column > 0 {
+            cells[column - 1] = blank.clone();
+        }
+        if cells[column].wide && column + 1 < cells.len() {
+            cells[column + 1] = blank.clone();
+        }
+        cells[column] = blank;
+    }
+
+    // Erased cells keep the current background, like in xterm.
+    fn blank(&self) -> Cell {
+        Cell {
+            attributes: Attributes {
+                background: self.attributes.background,
+                ..Attributes::default()
+            },
+            ..Cell::default()
+        }
+    }
+
+    fn blank_row(&self) -> Row {
+        Row::new(vec![self.blank(); usize::from(self.size.columns)])
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+
+        if self.cursor.row == self.scroll_region.1 {
+            self.scroll_up(1);
+        } else if self.cursor.row + 1 < self.size.rows {
+            self.cursor.row += 1;
+        }
+    }
+
+    fn reverse_line_feed(&mut self) {
+        self.wrap_pending = false;
+
+        if self.cursor.row == self.scroll_region.0 {
+            self.scroll_down(1);
+        } else if self.cursor.row > 0 {
+            self.cursor.row -= 1;
+        }
+    }
+
+    fn scroll_up(&mut self, count: u16) {
+        let (top, bottom) = self.scroll_region;
+        self.delete_rows(top, bottom, count);
+    }
+
+    fn scroll_down(&mut self, count: u16) {
+        let (top, bottom) = self.scroll_region;
+        self.insert_rows(top, bottom, count);
+    }
+
+    // Removes `count` rows at `at`, moving the rest of the rows up to `bottom` up.
+    fn delete_rows(&mut self, at: u16, bottom: u16, count: u16) {
+        let (at, bottom) = (usize::from(at), usize::from(bottom));
+        let count = usize::from(count).min(bottom + 1 - at);
+
+        self.grid.drain(at..at + count);
+        for _ in 0..count {
+            self.grid.insert(bottom + 1 - count, self.blank_row());
+        }
+    }
+
+    // Inserts `count` blank rows at `at`, moving the rows below down and off at `bottom`.
+    fn insert_rows(&mut self, at: u16, bottom: u16, count: u16) {
+        let (at, bottom) = (usize::from(at), usize::from(bottom));
+        let count = usize::from(count).min(bottom + 1 - at);
+
+        self.grid.drain(bottom + 1 - count..=bottom);
+        for _ in 0..count {
+            self.grid.insert(at, self.blank_row());
+        }
+    }
+
+    fn erase_columns(&mut self, row: u16, columns: std::ops::Range<u16>) {
+        for column in columns {
+            self.erase_cell(row, column);
+        }
+    }
+
+    fn erase_rows(&mut self, rows: std::ops::Range<u16>) {
+        for row in rows {
+            self.grid[usize::from(row)] = self.blank_row();
+        }
+    }
+
+    fn move_to(&mut self, row: u16, column: u16) {
+        self.cursor = Cursor {
+            row: row.min(self.size.rows - 1),
+            column: column.min(self.size.columns - 1),
+        };
+        self.wrap_pending = false;
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = (self.cursor, self.attributes);
+    }
+
+    fn restore_cursor(&mut self) {
+        let (cursor, attributes) = self.saved_cursor;
+        self.move_to(cursor.row, cursor.column);
+        self.attributes = attributes;
+    }
+
+    fn enter_alternate_screen(&mut self) {
+        if self.primary.is_none() {
+            let blank = blank_grid(self.size, self.blank());
+            self.primary = Some(mem::replace(&mut self.grid, blank));
+        }
+    }
+
+    fn leave_alternate_screen(&mut self) {
+        if let Some(primary) = self.primary.take() {
+            self.grid = primary;
+        }
+    }
+
+    fn set_private_mode(&mut self, mode: u16, enabled: bool) {
+        match mode {
+            1 => self.application_cursor = enabled,
+            7 => self.autowrap = enabled,
+            25 => self.cursor_visible = enabled,
+            47 | 1047 if enabled => self.enter_alternate_screen(),
+            47 | 1047 => self.leave_alternate_screen(),
+            1049 if enabled => {
+                self.save_cursor();
+                self.enter_alternate_screen();
+            }
+            1049 => {
+                self.leave_alternate_screen();
+                self.restore_cursor();
+            }
+            _ => {}
+        }
+    }
+
+    fn set_graphic_rendition(&mut self, params: &Params) {
+        let params: Vec<&[u16]> = params.iter().collect();
+        if params.is_empty() {
+            self.attributes = Attributes::default();
+            return;
+        }
+
+        let mut i = 0;
+        while i < params.len() {
+            let param = params[i];
+            let attributes = &mut self.attributes;
+
+            match param[0] {
+                0 => *attributes = Attributes::default(),
+                1 => attributes.bold = true,
+                2 => attributes.dim = true,
+                3 => attributes.italic = true,
+                4 | 21 => attributes.underline = true,
+                5 | 6 => attributes.blink = true,
+                7 => attributes.inverse = true,
+                8 => attributes.hidden = true,
+                9 => attributes.strikethrough = true,
+                22 => (attributes.bold, attributes.dim) = (false, false),
+                23 => attributes.italic = false,
+                24 => attributes.underline = false,
+                25 => attributes.blink = false,
+                27 => attributes.inverse = false,
+                28 => attributes.hidden = false,
+                29 => attributes.strikethrough = false,
+                code @ 30..=37 => attributes.foreground = Color::Indexed((code - 30) as u8),
+                code @ 40..=47 => attributes.background = Color::Indexed((code - 40) as u8),
+                code @ 90..=97 => attributes.foreground = Color::Indexed((code - 90 + 8) as u8),
+                code @ 100..=107 => attributes.background = Color::Indexed((code - 100 + 8) as u8),
+                39 => attributes.foreground = Color::Default,
+                49 => attributes.background = Color::Default,
+                code @ (38 | 48) => {
+                    // `38:5:n` keeps the color in one parameter, `38;5;n` spreads it over several.
+                    let color = if param.len() > 1 {
+                        // The colon form may have a color space id before the components.
+                        let mut args = param[1..].to_vec();
+                        if args.len() == 5 && args[0] == 2 {
+                            args.remove(1);
+                        }
+                        extended_color(&args).map(|(color, _)| color)
+                    } else {
+                        let rest: Vec<u16> = params[i + 1..].iter().map(|param| param[0]).collect();
+                        extended_color(&rest).map(|(color, used)| {
+                            i += used;
+                            color
+                        })
+                    };
+
+                    match (code, color) {
+                        (38, Some(color)) => attributes.foreground = color,
+                        (_, Some(color)) => attributes.background = color,
+                        (_, None) => {}
+                    }
+                }
+                _ => {}
+            }
+
+            i += 1;
+        }
+    }
+}
+
+impl fmt::Display for Screen {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.contents())
+    }
+}
+
+/// Panics with the rendered screen unless `text` is drawn within one of its rows.
+///
+/// ```
+/// use pseudoterminal::screen::{assert_screen_contains, Parser};
+/// use pseudoterminal::TerminalSize;
+///
+/// let mut parser = Parser::new(TerminalSize { rows: 24, columns: 80 });
+/// parser.process(b"\x1b[5;10H\x1b[7m[ OK ]\x1b[m");
+///
+/// assert_screen_contains(parser.screen(), "[ OK ]");
+/// ```
+#[track_caller]
+pub fn assert_screen_contains(screen: &Screen, text: &str) {
+    assert!(
+        screen.contains(text),
+        "the screen doesn't contain {text:?}, it shows:\n{screen}"
+    );
+}
+
+fn row_text(row: &[Cell]) -> String {
+    let text: String = row
+        .iter()
+        .filter(|cell| !cell.wide_continuation)
+        .map(|cell| match cell.contents() {
+            "" => " ",
+            contents => contents,
+        })
+        .collect();
+
+    text.trim_end_matches(' ').to_owned()
+}
+
+/// Parses the rest of an extended color, returning it along with the parameters it took up.
+fn extended_color(params: &[u16]) -> Option<(Color, usize)> {
+    match *params {
+        [5, index, ..] => Some((Color::Indexed(index as u8), 2)),
+        [2, r, g, b, ..] => Some((Color::Rgb(r as u8, g as u8, b as u8), 4)),
+        _ => None,
+    }
+}
+
+fn blank_grid(size: TerminalSize, blank: Cell) -> Vec<Row> {
+    (0..size.rows)
+        .map(|_| Row::new(vec![blank.clone(); usize::from(size.columns)]))
+        .collect()
+}
+
+fn resize_grid(grid: &mut Vec<Row>, size: TerminalSize, scrolled: usize) {
+    grid.drain(..scrolled.min(grid.len()));
+    grid.resize_with(usize::from(size.rows), || Row::new(Vec::new()));
+
+    for row in grid {
+        let cells = row.cells_mut();
+        cells.resize_with(usize::from(size.columns), Cell::default);
+        // A wide character cut in half can't be drawn anymore.
+        if cells.last().is_some_and(|cell| cell.wide) {
+            *cells.last_mut().unwrap() = Cell::default();
+        }
+    }
+}
+
+// The first parameter, or `default` if it is missing or 0.
+fn param(params: &Params, default: u16) -> u16 {
+    nth_param(params, 0, default)
+}
+
+fn nth_param(params: &Params, n: usize, default: u16) -> u16 {
+    match params.iter().nth(n).map(|param| param[0]) {
+        None | Some(0) => default,
+        Some(value) => value,
+    }
+}
+
+/// A VT parser keeping a [`Screen`] up to date with the output fed to it.
+pub struct Parser {
+    vte: vte::Parser,
+    screen: Screen,
+    // The generation of each row when the damage was last taken.
+    undamaged: Vec<u64>,
+}
+
+impl Parser {
+    /// Creates a parser for a blank screen of `size`.
+    pub fn new(size: TerminalSize) -> Self {
+        Self {
+            vte: vte::Parser::new(),
+            screen: Screen::new(size),
+            undamaged: Vec::new(),
+        }
+    }
+
+    /// Applies a chunk of output to the screen.
+    ///
+    /// Sequences and UTF-8 characters split across chunks are put together.
+    pub fn process(&mut self, data: &[u8]) {
+        self.vte.advance(&mut Performer(&mut self.screen), data);
+    }
+
+    pub fn screen(&self) -> &Screen {
+        &self.screen
+    }
+
+    /// Resizes the screen, cutting off what doesn't fit anymore.
+    pub fn set_size(&mut self, size: TerminalSize) {
+        self.screen.set_size(size);
+    }
+
+    /// Returns the rows changed since the last call, or all of them on the first call.
+    ///
+    /// Rows count as changed when they were drawn on or moved, even if their cells look the
+    /// same as before, e.g. after erasing an empty row.
+    pub fn take_damage(&mut self) -> Vec<u16> {
+        let generations: Vec<u64> = self.screen.grid.iter().map(|row| row.generation).collect();
+        let damaged = (0..)
+            .zip(&generations)
+            .filter(|&(row, generation)| self.undamaged.get(usize::from(row)) != Some(generation))
+            .map(|(row, _)| row)
+            .collect();
+        self.undamaged = generations;
+
+        damaged
+    }
+}
+
+// Keeps `vte` out of the public interface of `Screen`.
+struct Performer<'a>(&'a mut Screen);
+
+impl Perform for Performer<'_> {
+    fn print(&mut self, c: char) {
+        self.0.print(c);
+    }
+
+    fn execute(&mut self, byte: u8) {
+        let screen = &mut *self.0;
+
+        match byte {
+            0x08 => {
+                let Cursor { row, column } = screen.cursor;
+                screen.move_to(row, column.saturating_sub(1));
+            }
+            b'\t' => {
+                let Cursor { row, column } = screen.cursor;
+                screen.move_to(row, (column / TAB_WIDTH + 1) * TAB_WIDTH);
+            }
+            b'\n' | 0x0b | 0x0c => screen.line_feed(),
+            b'\r' => {
+                screen.cursor.column = 0;
+                screen.wrap_pending = false;
+            }
+            _ => {}
+        }
+    }
+
+    fn csi_dispatch(&mut self, params: &Params, intermediates: &[u8], ignore: bool, action: char) {
+        let screen = &mut *self.0;
+        if ignore {
+            return;
+        }
+
+        if intermediates == b"?" {
+            if let 'h' | 'l' = action {
+                for mode in params.iter() {
+                    screen.set_private_mode(mode[0], action == 'h');
+                }
+            }
+            return;
+        }
+        if !intermediates.is_empty() {
+            return;
+        }
+
+        let Cursor { row, column } = screen.cursor;
+        let TerminalSize { rows, columns } = screen.size;
+        let count = param(params, 1);
+
+        match action {
+            'A' => screen.move_to(row.saturating_sub(count), column),
+            'B' | 'e' => screen.move_to(row.saturating_add(count), column),
+            'C' | 'a' => screen.move_to(row, column.saturating_add(count)),
+            'D' => screen.move_to(row, column.saturating_sub(count)),
+            'E' => screen.move_to(row.saturating_add(count), 0),
+            'F' => screen.move_to(row.saturating_sub(count), 0),
+            'G' | '`' => screen.move_to(row, count - 1),
+            'd' => screen.move_to(count - 1, column),
+            'H' | 'f' => screen.move_to(count - 1, nth_param(params, 1, 1) - 1),
+            'J' => match param(params, 0) {
+                0 => {
+                    screen.erase_columns(row, column..columns);
+                    screen.erase_rows(row + 1..rows);
+                }
+                1 => {
+                    screen.erase_rows(0..row);
+                    screen.erase_columns(row, 0..column + 1);
+                }
+                2 => screen.erase_rows(0..rows),
+                _ => {}
+            },
+            'K' => match param(params, 0) {
+                0 => screen.erase_columns(row, column..columns),
+                1 => screen.erase_columns(row, 0..column + 1),
+                2 => screen.erase_columns(row, 0..columns),
+                _ => {}
+            },
+            'L' | 'M' => {
+                let (top, bottom) = screen.scroll_region;
+                if (top..=bottom).contains(&row) {
+                    if action == 'L' {
+                        screen.insert_rows(row, bottom, count);
+                    } else {
+                        screen.delete_rows(row, bottom, count);
+                    }
+                    screen.move_to(row, 0);
+                }
+            }
+            '@' | 'P' => {
+                // Split wide characters at both ends of the moved cells.
+                screen.erase_cell(row, column);
+                let count = usize::from(count.min(columns - column));
+                let blank = screen.blank();
+                let cells = &mut screen.grid[usize::from(row)].cells_mut()[usize::from(column)..];
+                if action == '@' {
+                    cells.rotate_right(count);
+                    cells[..count].fill(blank);
+                } else {
+                    cells.rotate_left(count);
+                    let len = cells.len();
+                    cells[len - count..].fill(blank);
+                }
+                screen.wrap_pending = false;
+            }
+            'X' => screen.erase_columns(row, column..column.saturating_add(count).min(columns)),
+            'S' => screen.scroll_up(count),
+            'T' => screen.scroll_down(count),
+            'r' => {
+                let top = param(params, 1) - 1;
+                let bottom = nth_param(params, 1, rows).min(rows) - 1;
+                if top < bottom {
+                    screen.scroll_region = (top, bottom);
+                    screen.move_to(0, 0);
+                }
+            }
+            'm' => screen.set_graphic_rendition(params),
+            's' => screen.save_cursor(),
+            'u' => screen.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    fn esc_dispatch(&mut self, intermediates: &[u8], ignore: bool, byte: u8) {
+        let screen = &mut *self.0;
+        if ignore || !intermediates.is_empty() {
+            return;
+        }
+
+        match byte {
+            b'7' => screen.save_cursor(),
+            b'8' => screen.restore_cursor(),
+            b'D' => screen.line_feed(),
+            b'E' => {
+                screen.cursor.column = 0;
+                screen.line_feed();
+            }
+            b'M' => screen.reverse_line_feed(),
+            b'c' => *screen = Screen::new(screen.size),
+            _ => {}
+        }
+    }
+}
+
+// A parser shared by a terminal and its output, which feeds it.
+#[derive(Clone)]
+pub(crate) struct SharedScreen(Arc<Mutex<Parser>>);
+
+impl SharedScreen {
+    pub(crate) fn new(size: TerminalSize) -> Self {
+        Self(Arc::new(Mutex::new(Parser::new(size))))
+    }
+
+    pub(crate) fn process(&self, data: &[u8]) {
+        self.lock().process(data);
+    }
+
+    pub(crate) fn snapshot(&self) -> Screen {
+        self.lock().screen().clone()
+    }
+
+    pub(crate) fn set_size(&self, size: TerminalSize) {
+        self.lock().set_size(size);
+    }
+
+    // A panic while parsing leaves the screen consistent enough to keep going.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Parser> {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
@@ -0,0 +1,49 @@
+// The history of a terminal's output kept for late readers, e.g. clients connecting to a web
+// terminal that replay it before following the live output.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// The last output read from a terminal, shared by the terminal and its output which records it.
+#[derive(Clone)]
+pub(crate) struct Scrollback(Arc<Mutex<Ring>>);
+
+struct Ring {
+    data: VecDeque<u8>,
+    capacity: usize,
+}
+
+impl Scrollback {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self(Arc::new(Mutex::new(Ring {
+            data: VecDeque::with_capacity(capacity),
+            capacity,
+        })))
+    }
+
+    // Drops the oldest output a line at a time once it outgrows the capacity, so the history
+    // doesn't start in the middle of a line or an escape sequence at its beginning.
+    pub(crate) fn record(&self, data: &[u8]) {
+        let mut ring = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        ring.data.extend(data);
+
+        let excess = ring.data.len().saturating_sub(ring.capacity);
+        if excess == 0 {
+            return;
+        }
+
+        let at_line_start = ring.data[excess - 1] == b'\n';
+        ring.data.drain(..excess);
+        if !at_line_start {
+            if let Some(newline) = ring.data.iter().position(|&byte| byte == b'\n') {
+                ring.data.drain(..=newline);
+            }
+        }
+    }
+
+    pub(crate) fn contents(&self) -> Vec<u8> {
+        let ring = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        ring.data.iter().copied().collect()
+    }
+}
@@ -0,0 +1,76 @@
+//! Shell integration through the FinalTerm sequences, `OSC 133`, marking where prompts, commands
+//! and their output start, see [`ShellIntegration`].
+//!
+//! Shells send them once configured to, e.g. by embedding them into `PS1`. They delimit commands
+//! reliably, unlike matching prompts against regular expressions.
+//!
+//! ```
+//! use pseudoterminal::shell_integration::{ShellEvent, ShellIntegration};
+//!
+//! let mut shell = ShellIntegration::new();
+//! let events = shell.feed(b"out\x1b]133;D;1\x07\x1b]133;A\x07$ ");
+//!
+//! assert_eq!(
+//!     events,
+//!     [ShellEvent::CommandFinished { exit_code: Some(1) }, ShellEvent::PromptStart],
+//! );
+//! ```
+
+use crate::osc::OscScanner;
+
+/// A mark sent by the shell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ShellEvent {
+    /// A prompt is printed next, `OSC 133 ; A`.
+    PromptStart,
+    /// The prompt was printed, what follows is the command typed, `OSC 133 ; B`.
+    CommandStart,
+    /// The command was entered and runs, what follows is its output, `OSC 133 ; C`.
+    CommandExecuted,
+    /// The command finished, `OSC 133 ; D`, with its exit code if the shell reported one.
+    CommandFinished { exit_code: Option<i32> },
+}
+
+impl ShellEvent {
+    /// Parses the payload of an OSC, between `ESC ]` and the terminator.
+    pub(crate) fn from_osc(payload: &[u8]) -> Option<Self> {
+        let mut params = payload.strip_prefix(b"133;")?.split(|&byte| byte == b';');
+
+        Some(match params.next()? {
+            b"A" => Self::PromptStart,
+            b"B" => Self::CommandStart,
+            b"C" => Self::CommandExecuted,
+            b"D" => Self::CommandFinished {
+                exit_code: params
+                    .next()
+                    .and_then(|code| std::str::from_utf8(code).ok())
+                    .and_then(|code| code.parse().ok()),
+            },
+            _ => return None,
+        })
+    }
+}
+
+/// Picks the [`ShellEvent`]s out of a terminal's output.
+///
+/// The output can be fed in chunks of any size, marks split across chunks are still found.
+#[derive(Clone, Debug, Default)]
+pub struct ShellIntegration {
+    scanner: OscScanner,
+}
+
+impl ShellIntegration {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the marks completed by `data`, in the order they were sent.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<ShellEvent> {
+        let mut events = Vec::new();
+        self.scanner.scan(data, |payload, _| {
+            events.extend(ShellEvent::from_osc(payload));
+        });
+
+        events
+    }
+}
@@ -0,0 +1,134 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// The size of a terminal in character cells.
+///
+/// Displays and parses as `<columns>x<rows>`, e.g. `120x40`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TerminalSize {
+    pub rows: u16,
+    pub columns: u16,
+}
+
+impl TerminalSize {
+    /// The smallest size a terminal can be given.
+    pub const MIN: TerminalSize = TerminalSize {
+        rows: 1,
+        columns: 1,
+    };
+
+    /// The largest size a terminal can be given, limited by the signed coordinates of ConPTY.
+    pub const MAX: TerminalSize = TerminalSize {
+        rows: i16::MAX as u16,
+        columns: i16::MAX as u16,
+    };
+
+    /// Creates a size, failing if it lies outside of [`MIN`](Self::MIN) and [`MAX`](Self::MAX).
+    pub fn new(rows: u16, columns: u16) -> Result<Self, SizeError> {
+        let size = TerminalSize { rows, columns };
+
+        if size.clamp() != size {
+            return Err(SizeError { size });
+        }
+
+        Ok(size)
+    }
+
+    /// Returns the size with both dimensions moved into [`MIN`](Self::MIN) and [`MAX`](Self::MAX).
+    pub fn clamp(self) -> Self {
+        TerminalSize {
+            rows: self.rows.clamp(Self::MIN.rows, Self::MAX.rows),
+            columns: self.columns.clamp(Self::MIN.columns, Self::MAX.columns),
+        }
+    }
+
+    // Checks a size before it's handed to the OS.
+    pub(crate) fn apply(self, policy: SizePolicy) -> io::Result<Self> {
+        match policy {
+            SizePolicy::Reject => TerminalSize::new(self.rows, self.columns)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err)),
+            SizePolicy::Clamp => Ok(self.clamp()),
+        }
+    }
+
+    /// Returns the size of the terminal the calling process runs in.
+    ///
+    /// Useful for spawning a child at the size of the host terminal. On Unix the standard streams
+    /// are asked in order, on Windows the console of stdout.
+    pub fn from_current_terminal() -> io::Result<Self> {
+        crate::sys::current_terminal_size()
+    }
+}
+
+impl fmt::Display for TerminalSize {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.columns, self.rows)
+    }
+}
+
+impl FromStr for TerminalSize {
+    type Err = ParseSizeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (columns, rows) = s.split_once(['x', 'X']).ok_or(ParseSizeError(()))?;
+
+        Ok(TerminalSize {
+            rows: rows.trim().parse().map_err(|_| ParseSizeError(()))?,
+            columns: columns.trim().parse().map_err(|_| ParseSizeError(()))?,
+        })
+    }
+}
+
+/// The error returned when parsing a [`TerminalSize`] fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseSizeError(());
+
+impl fmt::Display for ParseSizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("expected a terminal size like `120x40`")
+    }
+}
+
+impl std::error::Error for ParseSizeError {}
+
+/// How a terminal treats sizes outside of [`TerminalSize::MIN`] and [`TerminalSize::MAX`].
+///
+/// Applies to the initial size and every later resize, see
+/// [`TerminalOptions::size_policy`](crate::TerminalOptions::size_policy).
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SizePolicy {
+    /// Fails with [`io::ErrorKind::InvalidInput`] wrapping a [`SizeError`].
+    #[default]
+    Reject,
+    /// Clamps the size with [`TerminalSize::clamp`].
+    Clamp,
+}
+
+/// The error returned for a [`TerminalSize`] outside of the supported range.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SizeError {
+    size: TerminalSize,
+}
+
+impl SizeError {
+    /// Returns the rejected size.
+    pub fn size(&self) -> TerminalSize {
+        self.size
+    }
+}
+
+impl fmt::Display for SizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "terminal size {} is outside of {} to {}",
+            self.size,
+            TerminalSize::MIN,
+            TerminalSize::MAX
+        )
+    }
+}
+
+impl std::error::Error for SizeError {}
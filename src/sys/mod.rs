@@ -2,6 +2,7 @@ cfg_if::cfg_if! {
     if #[cfg(unix)] {
         mod unix;
         pub(crate) use unix::*;
+        pub use unix::{ResizeFollower, TerminalHandle};
     } else if #[cfg(windows)] {
         mod windows;
         pub use windows::*;
@@ -0,0 +1,639 @@
+//! The terminal of the calling process, the one `interact()` hands over to the child.
+
+use std::cell::UnsafeCell;
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Write};
+use std::mem::MaybeUninit;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd};
+use std::panic;
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+use super::{
+    backend, current_terminal_size, is_session_end, poll, wait_readable, ControlChar, PollFd,
+    ReadInterrupt, Readiness, TerminalHandle,
+};
+use crate::{InteractOptions, InterruptPolicy, ReadInterrupted, SuspendPolicy};
+
+/// Raw mode on the terminal of the calling process, the previous mode is restored on drop.
+///
+/// Being a guard, the mode is also restored while unwinding from a panic. `SIGTERM` and `SIGHUP`
+/// restore it as well before they terminate the process, unless the process handles them itself.
+pub(crate) struct RawMode {
+    // Dropped first, the handlers must not restore the mode through a closed descriptor.
+    _cleanup: SignalCleanup,
+    fd: OwnedFd,
+    previous: backend::Termios,
+}
+
+impl RawMode {
+    /// Switches stdin to raw mode, `None` if stdin is not a terminal.
+    pub fn enable() -> io::Result<Option<Self>> {
+        let stdin = io::stdin();
+        if !stdin.is_terminal() {
+            return Ok(None);
+        }
+
+        let fd = stdin.as_fd().try_clone_to_owned()?;
+        let cleanup = SignalCleanup::install(fd.as_fd())?;
+        let previous = backend::make_raw(fd.as_fd())?;
+
+        Ok(Some(RawMode {
+            _cleanup: cleanup,
+            fd,
+            previous,
+        }))
+    }
+
+    // Raw mode leaves the special characters configured, they are only no longer acted upon.
+    fn control_char(&self, char: ControlChar) -> Option<u8> {
+        backend::control_char(self.fd.as_fd(), char)
+            .ok()
+            .filter(|&byte| byte != libc::_POSIX_VDISABLE)
+    }
+
+    // Stops the calling process in the previous mode and enters raw mode again once it's
+    // continued.
+    fn suspend(&self) -> io::Result<()> {
+        backend::restore_mode(self.fd.as_fd(), &self.previous)?;
+
+        // Raised on the calling thread, the signal is acted upon before `raise()` returns, which
+        // is only after the process was continued. A handled or ignored SIGTSTP returns right away.
+        if unsafe { libc::raise(libc::SIGTSTP) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        backend::make_raw(self.fd.as_fd())?;
+
+        Ok(())
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = backend::restore_mode(self.fd.as_fd(), &self.previous);
+    }
+}
+
+// The signals that terminate a process by default and are commonly sent to ask it to exit.
+const TERMINATING_SIGNALS: [libc::c_int; 2] = [libc::SIGTERM, libc::SIGHUP];
+
+// The mode the terminal had before the first of the active raw modes, for the handlers.
+struct SavedMode(UnsafeCell<MaybeUninit<libc::termios>>);
+
+// Only written while `RESTORE_FD` is -1, the handlers don't read it then.
+unsafe impl Sync for SavedMode {}
+
+static SAVED_MODE: SavedMode = SavedMode(UnsafeCell::new(MaybeUninit::uninit()));
+// A descriptor of the terminal to restore, -1 while no raw mode is active.
+static RESTORE_FD: AtomicI32 = AtomicI32::new(-1);
+// The number of active raw modes, only the first one installs the handlers.
+static RAW_MODES: Mutex<usize> = Mutex::new(0);
+
+// Only calls async-signal-safe functions.
+extern "C" fn restore_and_terminate(signal: libc::c_int) {
+    let fd = RESTORE_FD.load(Ordering::Acquire);
+    if fd != -1 {
+        unsafe { libc::tcsetattr(fd, libc::TCSANOW, (*SAVED_MODE.0.get()).as_ptr()) };
+    }
+
+    // The signal is blocked until the handler returns, it then terminates the process like it
+    // would have without the handler.
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = libc::SIG_DFL;
+    unsafe {
+        libc::sigaction(signal, &action, std::ptr::null_mut());
+        libc::raise(signal);
+    }
+}
+
+fn sigaction(signal: libc::c_int, action: Option<&libc::sigaction>) -> io::Result<usize> {
+    let mut old: libc::sigaction = unsafe { std::mem::zeroed() };
+    let action = action.map_or(std::ptr::null(), |action| action as *const _);
+    if unsafe { libc::sigaction(signal, action, &mut old) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(old.sa_sigaction)
+}
+
+// Restores the terminal from the terminating signals while raw mode is active, a guard like
+// `RawMode` is never dropped when they end the process.
+struct SignalCleanup;
+
+impl SignalCleanup {
+    fn install(fd: BorrowedFd<'_>) -> io::Result<Self> {
+        let mut raw_modes = RAW_MODES.lock().unwrap_or_else(PoisonError::into_inner);
+        if *raw_modes == 0 {
+            let mut saved = MaybeUninit::uninit();
+            if unsafe { libc::tcgetattr(fd.as_raw_fd(), saved.as_mut_ptr()) } == -1 {
+                return Err(io::Error::last_os_error());
+            }
+            unsafe { *SAVED_MODE.0.get() = saved };
+            let fd = fd.try_clone_to_owned()?;
+            RESTORE_FD.store(fd.into_raw_fd(), Ordering::Release);
+
+            let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+            action.sa_sigaction = restore_and_terminate as *const () as usize;
+            unsafe { libc::sigemptyset(&mut action.sa_mask) };
+            for signal in TERMINATING_SIGNALS {
+                // A process handling or ignoring the signal decides itself what happens.
+                if sigaction(signal, None)? == libc::SIG_DFL {
+                    sigaction(signal, Some(&action))?;
+                }
+            }
+        }
+        *raw_modes += 1;
+
+        Ok(SignalCleanup)
+    }
+}
+
+impl Drop for SignalCleanup {
+    fn drop(&mut self) {
+        let mut raw_modes = RAW_MODES.lock().unwrap_or_else(PoisonError::into_inner);
+        *raw_modes -= 1;
+        if *raw_modes > 0 {
+            return;
+        }
+
+        let handler = restore_and_terminate as *const () as usize;
+        let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+        action.sa_sigaction = libc::SIG_DFL;
+        for signal in TERMINATING_SIGNALS {
+            // Handlers installed in the meantime stay.
+            if sigaction(signal, None).is_ok_and(|current| current == handler) {
+                let _ = sigaction(signal, Some(&action));
+            }
+        }
+
+        let fd = RESTORE_FD.swap(-1, Ordering::AcqRel);
+        drop(unsafe { OwnedFd::from_raw_fd(fd) });
+    }
+}
+
+// The write end of the pipe the SIGWINCH handler wakes the relay thread through, -1 until
+// the handler is installed.
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+static INSTALLED: Mutex<bool> = Mutex::new(false);
+static PREVIOUS_ACTION: AtomicUsize = AtomicUsize::new(libc::SIG_DFL);
+static PREVIOUS_FLAGS: AtomicI32 = AtomicI32::new(0);
+
+static WATCHES: Mutex<Vec<(usize, File)>> = Mutex::new(Vec::new());
+static NEXT_WATCH: AtomicUsize = AtomicUsize::new(0);
+
+fn errno() -> *mut libc::c_int {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "emscripten", target_os = "redox"))] {
+            unsafe { libc::__errno_location() }
+        } else if #[cfg(any(target_os = "android", target_os = "netbsd", target_os = "openbsd"))] {
+            unsafe { libc::__errno() }
+        } else {
+            unsafe { libc::__error() }
+        }
+    }
+}
+
+extern "C" fn on_resize(
+    signal: libc::c_int,
+    info: *mut libc::siginfo_t,
+    context: *mut libc::c_void,
+) {
+    // A failed write would clobber the errno of the code the signal interrupted.
+    let saved = unsafe { *errno() };
+
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd != -1 {
+        // A full pipe already has a wake-up pending.
+        unsafe { libc::write(fd, [0u8].as_ptr().cast(), 1) };
+    }
+
+    // Handlers installed before ours keep working.
+    let action = PREVIOUS_ACTION.load(Ordering::Relaxed);
+    if action != libc::SIG_DFL && action != libc::SIG_IGN {
+        if PREVIOUS_FLAGS.load(Ordering::Relaxed) & libc::SA_SIGINFO != 0 {
+            let action: extern "C" fn(libc::c_int, *mut libc::siginfo_t, *mut libc::c_void) =
+                unsafe { std::mem::transmute(action) };
+            action(signal, info, context);
+        } else {
+            let action: extern "C" fn(libc::c_int) = unsafe { std::mem::transmute(action) };
+            action(signal);
+        }
+    }
+
+    unsafe { *errno() = saved };
+}
+
+// Installs the SIGWINCH handler and starts the thread relaying it to every watch, once.
+fn install_handler() -> io::Result<()> {
+    let mut installed = INSTALLED.lock().unwrap_or_else(PoisonError::into_inner);
+    if *installed {
+        return Ok(());
+    }
+
+    let (read, write) = backend::pipe()?;
+    backend::set_nonblocking(write.as_fd())?;
+    let read = File::from(read);
+
+    thread::Builder::new()
+        .name("pseudoterminal-sigwinch".into())
+        .spawn(move || relay_resizes(read))?;
+
+    // The handler may run at any time from now on, so the write end is never closed.
+    SIGNAL_PIPE.store(write.into_raw_fd(), Ordering::Relaxed);
+
+    let mut action: libc::sigaction = unsafe { std::mem::zeroed() };
+    action.sa_sigaction = on_resize as *const () as usize;
+    action.sa_flags = libc::SA_SIGINFO | libc::SA_RESTART;
+    unsafe { libc::sigemptyset(&mut action.sa_mask) };
+
+    let mut old: libc::sigaction = unsafe { std::mem::zeroed() };
+    if unsafe { libc::sigaction(libc::SIGWINCH, &action, &mut old) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    PREVIOUS_FLAGS.store(old.sa_flags, Ordering::Relaxed);
+    PREVIOUS_ACTION.store(old.sa_sigaction, Ordering::Relaxed);
+    *installed = true;
+
+    Ok(())
+}
+
+// Runs outside of the signal handler, so taking a lock to reach the watches is fine.
+fn relay_resizes(mut signals: File) {
+    let mut buf = [0; 64];
+
+    loop {
+        match signals.read(&mut buf) {
+            Ok(0) => return,
+            Ok(_) => {}
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(_) => return,
+        }
+
+        let watches = WATCHES.lock().unwrap_or_else(PoisonError::into_inner);
+        for (_, pipe) in watches.iter() {
+            // A full pipe already has a resize pending.
+            let _ = (&*pipe).write(&[0]);
+        }
+    }
+}
+
+/// Becomes readable whenever the terminal of the calling process was resized.
+pub(crate) struct ResizeWatch {
+    id: usize,
+    read: File,
+}
+
+impl ResizeWatch {
+    pub fn new() -> io::Result<Self> {
+        install_handler()?;
+
+        let (read, write) = backend::pipe()?;
+        backend::set_nonblocking(read.as_fd())?;
+        backend::set_nonblocking(write.as_fd())?;
+
+        let id = NEXT_WATCH.fetch_add(1, Ordering::Relaxed);
+        WATCHES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((id, write.into()));
+
+        Ok(ResizeWatch {
+            id,
+            read: read.into(),
+        })
+    }
+
+    /// Consumes the pending notifications, returns whether there were any.
+    pub fn clear(&self) -> bool {
+        let mut buf = [0; 64];
+        let mut resized = false;
+
+        while let Ok(1..) = (&self.read).read(&mut buf) {
+            resized = true;
+        }
+
+        resized
+    }
+}
+
+impl AsFd for ResizeWatch {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.read.as_fd()
+    }
+}
+
+impl Drop for ResizeWatch {
+    fn drop(&mut self) {
+        WATCHES
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|(id, _)| *id != self.id);
+    }
+}
+
+// Best effort, when the calling process has no terminal the PTY keeps its size.
+fn follow_host_size(handle: &TerminalHandle) {
+    if let Ok(size) = current_terminal_size() {
+        let _ = handle.set_term_size(size);
+    }
+}
+
+/// Resizes a terminal along with the host terminal, see
+/// [`Terminal::follow_host_resize`](crate::Terminal::follow_host_resize).
+///
+/// Following stops once this is dropped.
+pub struct ResizeFollower {
+    stop: ReadInterrupt,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ResizeFollower {
+    pub(crate) fn start(handle: TerminalHandle) -> io::Result<Self> {
+        let resize = ResizeWatch::new()?;
+        let stop = ReadInterrupt::new()?;
+        follow_host_size(&handle);
+
+        let thread = thread::Builder::new()
+            .name("pseudoterminal-resize".into())
+            .spawn({
+                let stop = stop.clone();
+                move || follow_resizes(&handle, &resize, &stop)
+            })?;
+
+        Ok(ResizeFollower {
+            stop,
+            thread: Some(thread),
+        })
+    }
+}
+
+fn follow_resizes(handle: &TerminalHandle, resize: &ResizeWatch, stop: &ReadInterrupt) {
+    loop {
+        let mut fds = [
+            PollFd::new(stop.as_fd(), libc::POLLIN),
+            PollFd::new(resize.as_fd(), libc::POLLIN),
+        ];
+        if poll(&mut fds, None).is_err() || fds[0].revents != 0 {
+            return;
+        }
+
+        if resize.clear() {
+            follow_host_size(handle);
+        }
+    }
+}
+
+impl Drop for ResizeFollower {
+    fn drop(&mut self) {
+        let _ = self.stop.interrupt();
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Connects the terminal of the calling process to `input` and `output` until the output ends.
+///
+/// Both halves may be non-blocking, they are then polled through `handle`. `output_stop` has to
+/// cancel a blocking read of `output`, it ends the forwarding when `options` call for it.
+pub(crate) fn interact(
+    handle: &TerminalHandle,
+    input: &mut (impl Write + Send),
+    output: &mut impl Read,
+    output_stop: &ReadInterrupt,
+    options: &mut InteractOptions,
+) -> io::Result<()> {
+    let stdin = File::from(io::stdin().as_fd().try_clone_to_owned()?);
+    let resize = ResizeWatch::new()?;
+    let stop = ReadInterrupt::new()?;
+
+    let raw_mode = RawMode::enable()?;
+    follow_host_size(handle);
+
+    let control_char = |char| {
+        raw_mode
+            .as_ref()
+            .and_then(|raw_mode| raw_mode.control_char(char))
+    };
+    let mut keys = HostKeys {
+        interrupt: match options.interrupt {
+            InterruptPolicy::Forward => None,
+            _ => control_char(ControlChar::Interrupt),
+        },
+        suspend: match options.suspend {
+            SuspendPolicy::Forward => None,
+            SuspendPolicy::Suspend => control_char(ControlChar::Suspend),
+        },
+        raw_mode,
+        options,
+        output_stop,
+    };
+
+    thread::scope(|scope| {
+        let forwarder = scope.spawn(|| {
+            let forwarded = panic::catch_unwind(panic::AssertUnwindSafe(|| {
+                forward_input(handle, &stdin, input, &mut keys, &resize, &stop)
+            }));
+            // The output would be forwarded until the child exits, the panic is raised again below.
+            if forwarded.is_err() {
+                let _ = output_stop.interrupt();
+            }
+            forwarded
+        });
+
+        // Stops the input forwarding however the output ends, even if processing it panics.
+        let stop_input = StopOnDrop(&stop);
+        let result = forward_output(handle, output, output_stop);
+        drop(stop_input);
+
+        let forwarded = match forwarder.join() {
+            Ok(Ok(forwarded)) => forwarded,
+            Ok(Err(panic)) | Err(panic) => panic::resume_unwind(panic),
+        };
+
+        result.and(forwarded)
+    })
+}
+
+struct StopOnDrop<'a>(&'a ReadInterrupt);
+
+impl Drop for StopOnDrop<'_> {
+    fn drop(&mut self) {
+        let _ = self.0.interrupt();
+    }
+}
+
+// The control keys typed on the host terminal that the options apply a policy to, each one
+// `None` if it's passed on as it is.
+struct HostKeys<'a> {
+    interrupt: Option<u8>,
+    suspend: Option<u8>,
+    // Kept here for the thread forwarding the input to suspend it, restored once dropped.
+    raw_mode: Option<RawMode>,
+    options: &'a mut InteractOptions,
+    output_stop: &'a ReadInterrupt,
+}
+
+impl HostKeys<'_> {
+    fn find(&self, data: &[u8]) -> Option<usize> {
+        data.iter()
+            .position(|&byte| Some(byte) == self.interrupt || Some(byte) == self.suspend)
+    }
+}
+
+fn forward_input(
+    handle: &TerminalHandle,
+    stdin: &File,
+    input: &mut impl Write,
+    keys: &mut HostKeys<'_>,
+    resize: &ResizeWatch,
+    stop: &ReadInterrupt,
+) -> io::Result<()> {
+    let mut buf = [0; 4096];
+    let mut stdin_open = true;
+
+    loop {
+        let mut fds = vec![
+            PollFd::new(stop.as_fd(), libc::POLLIN),
+            PollFd::new(resize.as_fd(), libc::POLLIN),
+        ];
+        if stdin_open {
+            fds.push(PollFd::new(stdin.as_fd(), libc::POLLIN));
+        }
+        poll(&mut fds, None)?;
+
+        if fds[0].revents != 0 {
+            return Ok(());
+        }
+        if fds[1].revents != 0 && resize.clear() {
+            follow_host_size(handle);
+        }
+        if fds.get(2).is_some_and(|fd| fd.revents != 0) {
+            match (&*stdin).read(&mut buf) {
+                Ok(0) => stdin_open = false,
+                Ok(read) => {
+                    if !route_input(handle, input, &buf[..read], keys, stop)? {
+                        return Ok(());
+                    }
+                }
+                Err(err)
+                    if matches!(
+                        err.kind(),
+                        io::ErrorKind::Interrupted | io::ErrorKind::WouldBlock
+                    ) => {}
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+// Writes `data` like `write_input()`, applying the policies to the control keys in it.
+fn route_input(
+    handle: &TerminalHandle,
+    input: &mut impl Write,
+    mut data: &[u8],
+    keys: &mut HostKeys<'_>,
+    stop: &ReadInterrupt,
+) -> io::Result<bool> {
+    while let Some(at) = keys.find(data) {
+        if !write_input(handle, input, &data[..at], stop)? {
+            return Ok(false);
+        }
+
+        let forward = if Some(data[at]) == keys.interrupt {
+            match &mut keys.options.interrupt {
+                InterruptPolicy::Forward => true,
+                InterruptPolicy::Local => {
+                    keys.output_stop.interrupt()?;
+                    return Err(io::Error::new(
+                        io::ErrorKind::Interrupted,
+                        "interrupted from the host terminal",
+                    ));
+                }
+                InterruptPolicy::Callback(callback) => callback(),
+            }
+        } else {
+            if let Some(raw_mode) = &keys.raw_mode {
+                raw_mode.suspend()?;
+                // The host terminal may have been resized in the meantime.
+                follow_host_size(handle);
+            }
+            false
+        };
+        if forward && !write_input(handle, input, &data[at..=at], stop)? {
+            return Ok(false);
+        }
+        data = &data[at + 1..];
+    }
+
+    write_input(handle, input, data, stop)
+}
+
+// Returns `false` if `stop` was interrupted while waiting for the terminal to take the input.
+fn write_input(
+    handle: &TerminalHandle,
+    input: &mut impl Write,
+    mut data: &[u8],
+    stop: &ReadInterrupt,
+) -> io::Result<bool> {
+    while !data.is_empty() {
+        match input.write(data) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => data = &data[written..],
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                let mut fds = [
+                    PollFd::new(handle.as_fd(), libc::POLLOUT),
+                    PollFd::new(stop.as_fd(), libc::POLLIN),
+                ];
+                poll(&mut fds, None)?;
+
+                if fds[1].revents != 0 {
+                    return Ok(false);
+                }
+            }
+            // Input typed after the session ended goes nowhere.
+            Err(err) if is_session_end(&err) => return Ok(true),
+            Err(err) => return Err(err),
+        }
+    }
+
+    input.flush()?;
+
+    Ok(true)
+}
+
+// Returns once the output ended or `stop` was interrupted.
+fn forward_output(
+    handle: &TerminalHandle,
+    output: &mut impl Read,
+    stop: &ReadInterrupt,
+) -> io::Result<()> {
+    let mut buf = [0; 4096];
+
+    loop {
+        match output.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(read) => {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(&buf[..read])?;
+                stdout.flush()?;
+            }
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                if wait_readable(handle.as_fd(), Some(stop), None)? == Readiness::Interrupted {
+                    return Ok(());
+                }
+            }
+            Err(err) if err.get_ref().is_some_and(|err| err.is::<ReadInterrupted>()) => {
+                return Ok(())
+            }
+            Err(err) if is_session_end(&err) => return Ok(()),
+            Err(err) => return Err(err),
+        }
+    }
+}
@@ -0,0 +1,881 @@
+use std::ffi::{CStr, OsStr, OsString};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use libc::pid_t;
+
+use crate::error::Context;
+use crate::{SizePolicy, TerminalOptions};
+
+cfg_if::cfg_if! {
+    if #[cfg(feature = "rustix")] {
+        #[path = "rustix.rs"]
+        mod backend;
+    } else if #[cfg(feature = "nix")] {
+        #[path = "nix.rs"]
+        mod backend;
+    } else {
+        compile_error!("either the `nix` or the `rustix` feature has to be enabled on Unix");
+    }
+}
+
+#[cfg(feature = "non-blocking")]
+mod async_master;
+mod host;
+
+#[cfg(feature = "non-blocking")]
+pub(crate) use async_master::{async_master, AsyncMaster};
+pub(crate) use host::interact;
+pub use host::ResizeFollower;
+
+pub(crate) fn open_handle_and_io(
+    options: &TerminalOptions,
+) -> io::Result<(TerminalHandle, (TerminalInput, TerminalOutput))> {
+    let terminal_handle = TerminalHandle::open(options.size_policy)?;
+
+    if options.size.is_some() || options.pixel_size.is_some() {
+        let mut size = backend::get_window_size(terminal_handle.0.as_fd())
+            .context("getting the window size")?;
+        if let Some(new_size) = options.size {
+            let new_size = new_size.apply(options.size_policy)?;
+            size.rows = new_size.rows;
+            size.columns = new_size.columns;
+        }
+        if let Some((width, height)) = options.pixel_size {
+            size.x_pixels = width;
+            size.y_pixels = height;
+        }
+        backend::set_window_size(terminal_handle.0.as_fd(), size).context(format_args!(
+            "setting the window size to {}x{}",
+            size.columns, size.rows
+        ))?;
+    }
+
+    if options.raw_mode {
+        // The line discipline belongs to the PTY, it outlives this slave descriptor.
+        backend::make_raw(terminal_handle.open_slave()?.as_fd()).context("enabling raw mode")?;
+    }
+
+    // Both directions go through the one master descriptor owned by the handle.
+    let io = (terminal_handle.0.clone(), terminal_handle.0.clone());
+
+    Ok((terminal_handle, io))
+}
+
+// Linux reports EIO on the master once the last slave has been closed.
+pub(crate) fn is_session_end(err: &io::Error) -> bool {
+    err.raw_os_error() == Some(libc::EIO)
+}
+
+// What `TerminalHandle::attach()` left on a command, released once it was spawned.
+pub(crate) struct Attachment {
+    // The `pre_exec` hooks can't be removed from the command, later spawns of it find them unarmed.
+    armed: Arc<AtomicBool>,
+    // The standard streams `attach()` replaced, the others keep what the caller configured.
+    on_pty: [bool; 3],
+}
+
+impl Attachment {
+    // `Command` keeps the slave descriptors alive after spawning, which would keep the session
+    // open after the child exits and leak the slave if spawning failed.
+    pub(crate) fn release(self, cmd: &mut Command) {
+        self.armed.store(false, Ordering::Relaxed);
+
+        let [stdin, stdout, stderr] = self.on_pty;
+        if stdin {
+            cmd.stdin(Stdio::inherit());
+        }
+        if stdout {
+            cmd.stdout(Stdio::inherit());
+        }
+        if stderr {
+            cmd.stderr(Stdio::inherit());
+        }
+    }
+}
+
+// The first of the standard streams connected to a terminal answers.
+pub(crate) fn current_terminal_size() -> io::Result<crate::TerminalSize> {
+    let winsize = backend::get_window_size(io::stdin().as_fd())
+        .or_else(|_| backend::get_window_size(io::stdout().as_fd()))
+        .or_else(|_| backend::get_window_size(io::stderr().as_fd()))?;
+
+    Ok(crate::TerminalSize {
+        columns: winsize.columns,
+        rows: winsize.rows,
+    })
+}
+
+// Unix terminals have no console settings beyond what the options already configured.
+pub(crate) fn configure_console(_pid: u32, _options: &TerminalOptions) -> io::Result<()> {
+    Ok(())
+}
+
+struct User {
+    name: OsString,
+    dir: PathBuf,
+    shell: PathBuf,
+}
+
+impl User {
+    fn current() -> io::Result<Self> {
+        let mut buf = vec![0; 1024];
+        let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+        let mut result = std::ptr::null_mut();
+
+        loop {
+            let err = unsafe {
+                libc::getpwuid_r(
+                    libc::getuid(),
+                    &mut passwd,
+                    buf.as_mut_ptr(),
+                    buf.len(),
+                    &mut result,
+                )
+            };
+            match err {
+                0 => break,
+                libc::ERANGE => buf.resize(buf.len() * 2, 0),
+                err => return Err(io::Error::from_raw_os_error(err)),
+            }
+        }
+
+        if result.is_null() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "no passwd entry for the current user",
+            ));
+        }
+
+        let field = |ptr: *const libc::c_char| {
+            let bytes = unsafe { CStr::from_ptr(ptr) }.to_bytes();
+            OsStr::from_bytes(bytes).to_owned()
+        };
+
+        Ok(User {
+            name: field(passwd.pw_name),
+            dir: field(passwd.pw_dir).into(),
+            shell: field(passwd.pw_shell).into(),
+        })
+    }
+}
+
+pub(crate) fn configure_login_shell(cmd: &mut Command) -> io::Result<()> {
+    let user = User::current()?;
+
+    let program = Path::new(cmd.get_program()).to_owned();
+    let name = program.file_name().unwrap_or(program.as_os_str());
+
+    let mut arg0 = OsString::from("-");
+    arg0.push(name);
+    cmd.arg0(arg0);
+
+    let configured: Vec<OsString> = cmd.get_envs().map(|(key, _)| key.to_owned()).collect();
+    let defaults = [
+        ("HOME", user.dir.as_os_str()),
+        ("SHELL", user.shell.as_os_str()),
+        ("USER", user.name.as_os_str()),
+        ("LOGNAME", user.name.as_os_str()),
+        ("TERM", "xterm-256color".as_ref()),
+    ];
+    for (key, value) in defaults {
+        if !configured.iter().any(|configured| configured == key) {
+            cmd.env(key, value);
+        }
+    }
+
+    if cmd.get_current_dir().is_none() {
+        cmd.current_dir(&user.dir);
+    }
+
+    Ok(())
+}
+
+pub(crate) fn send_signal(pid: u32, signal: i32) -> io::Result<()> {
+    if unsafe { libc::kill(pid as pid_t, signal) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/// A special character of the line discipline, see termios(3).
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ControlChar {
+    Eof,
+    Interrupt,
+    Suspend,
+}
+
+// The character the terminal is currently configured with, so a changed `stty eof` still works.
+pub(crate) fn control_char(input: &impl AsFd, char: ControlChar) -> io::Result<Vec<u8>> {
+    let byte = backend::control_char(input.as_fd(), char)?;
+    if byte == libc::_POSIX_VDISABLE {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{char:?} is disabled on the terminal"),
+        ));
+    }
+
+    Ok(vec![byte])
+}
+
+// Enter sends a carriage return, which the terminal turns into the newline that ends a line
+// unless `ICRNL` was turned off. Line-based input then needs the newline itself.
+pub(crate) fn line_terminator(input: &impl AsFd) -> io::Result<&'static [u8]> {
+    let modes = backend::line_modes(input.as_fd())?;
+
+    Ok(if modes.canonical && !modes.cr_to_nl {
+        b"\n"
+    } else {
+        b"\r"
+    })
+}
+
+// The parts of a terminal's mode that decide how input lines are read.
+pub(crate) struct LineModes {
+    canonical: bool,
+    cr_to_nl: bool,
+}
+
+// Writes the character straight to the descriptor, for inputs that can't be written otherwise,
+// e.g. async ones being dropped.
+pub(crate) fn send_control_char(input: &impl AsFd, char: ControlChar) -> io::Result<()> {
+    let bytes = control_char(input, char)?;
+
+    // The descriptor stays owned by `input`.
+    let file = std::mem::ManuallyDrop::new(unsafe { File::from_raw_fd(input.as_fd().as_raw_fd()) });
+    (&*file).write_all(&bytes)
+}
+
+// Delivers EOF for an input being dropped. Only a terminal reading lines turns the EOF character
+// into an end of input, one in raw mode would hand it to the child as a literal byte.
+pub(crate) fn send_eof_on_drop(input: &impl AsFd) {
+    if matches!(backend::line_modes(input.as_fd()), Ok(modes) if modes.canonical) {
+        // The child may already be gone, in which case nobody is left waiting for the EOF.
+        let _ = send_control_char(input, ControlChar::Eof);
+    }
+}
+
+pub(crate) fn process_group_id(pid: u32) -> io::Result<u32> {
+    match unsafe { libc::getpgid(pid as pid_t) } {
+        -1 => Err(io::Error::last_os_error()),
+        pgid => Ok(pgid as u32),
+    }
+}
+
+pub(crate) fn session_id(pid: u32) -> io::Result<u32> {
+    match unsafe { libc::getsid(pid as pid_t) } {
+        -1 => Err(io::Error::last_os_error()),
+        sid => Ok(sid as u32),
+    }
+}
+
+// Waits for the child on a thread of its own, so it does not linger as a zombie once it exits.
+pub(crate) fn reap_in_background(pid: u32) {
+    thread::spawn(move || loop {
+        let mut status = 0;
+        if unsafe { libc::waitpid(pid as pid_t, &mut status, 0) } != -1
+            || io::Error::last_os_error().kind() != io::ErrorKind::Interrupted
+        {
+            break;
+        }
+    });
+}
+
+/// A child forked without exec'ing a command, which a [`std::process::Child`] can't represent.
+pub(crate) struct ForkedChild {
+    pid: pid_t,
+    status: Option<ExitStatus>,
+}
+
+impl ForkedChild {
+    pub(crate) fn id(&self) -> u32 {
+        self.pid as u32
+    }
+
+    pub(crate) fn wait(&mut self) -> io::Result<ExitStatus> {
+        match self.status {
+            Some(status) => Ok(status),
+            None => Ok(self
+                .waitpid(0)?
+                .expect("a blocking waitpid() always returns a status")),
+        }
+    }
+
+    pub(crate) fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        match self.status {
+            Some(status) => Ok(Some(status)),
+            None => self.waitpid(libc::WNOHANG),
+        }
+    }
+
+    pub(crate) fn kill(&mut self) -> io::Result<()> {
+        // The pid might already belong to another process once the child was reaped.
+        if self.status.is_none() {
+            send_signal(self.id(), libc::SIGKILL)?;
+        }
+
+        Ok(())
+    }
+
+    fn waitpid(&mut self, flags: libc::c_int) -> io::Result<Option<ExitStatus>> {
+        let mut status = 0;
+
+        loop {
+            match unsafe { libc::waitpid(self.pid, &mut status, flags) } {
+                0 => return Ok(None),
+                -1 if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted => {}
+                -1 => return Err(io::Error::last_os_error()),
+                _ => break,
+            }
+        }
+
+        self.status = Some(ExitStatus::from_raw(status));
+
+        Ok(self.status)
+    }
+}
+
+pub(crate) struct PollFd<'fd> {
+    pub fd: BorrowedFd<'fd>,
+    pub events: libc::c_short,
+    pub revents: libc::c_short,
+}
+
+impl<'fd> PollFd<'fd> {
+    pub fn new(fd: BorrowedFd<'fd>, events: libc::c_short) -> Self {
+        PollFd {
+            fd,
+            events,
+            revents: 0,
+        }
+    }
+}
+
+// Like poll(2), but retries when interrupted by a signal.
+fn poll(fds: &mut [PollFd<'_>], timeout: Option<Duration>) -> io::Result<usize> {
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        let timeout = deadline.map(|deadline| deadline.saturating_duration_since(Instant::now()));
+
+        match backend::poll(fds, timeout) {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            result => return result,
+        }
+    }
+}
+
+/// A self-pipe waking up a thread blocked reading the master.
+///
+/// Every interrupt writes a byte and every interrupted read consumes one, so an interrupt with
+/// no read pending cancels the next one.
+#[derive(Clone)]
+pub(crate) struct ReadInterrupt(Arc<(File, File)>);
+
+impl ReadInterrupt {
+    pub fn new() -> io::Result<Self> {
+        let (read, write) = backend::pipe()?;
+
+        Ok(ReadInterrupt(Arc::new((read.into(), write.into()))))
+    }
+
+    pub fn interrupt(&self) -> io::Result<()> {
+        (&self.0 .1).write_all(&[0])
+    }
+}
+
+/// What ended a wait for a descriptor to become readable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Readiness {
+    Readable,
+    Interrupted,
+    TimedOut,
+}
+
+/// Waits until `fd` is readable, `interrupt` cancels the wait or `timeout` elapses.
+pub(crate) fn wait_readable(
+    fd: BorrowedFd<'_>,
+    interrupt: Option<&ReadInterrupt>,
+    timeout: Option<Duration>,
+) -> io::Result<Readiness> {
+    let mut fds = [
+        PollFd::new(fd, libc::POLLIN),
+        PollFd::new(
+            interrupt.map_or(fd, |interrupt| interrupt.as_fd()),
+            libc::POLLIN,
+        ),
+    ];
+    let fds = &mut fds[..1 + interrupt.is_some() as usize];
+
+    if poll(fds, timeout)? == 0 {
+        return Ok(Readiness::TimedOut);
+    }
+
+    if let (Some(interrupt), Some(fd)) = (interrupt, fds.get(1)) {
+        if fd.revents & libc::POLLIN != 0 {
+            (&interrupt.0 .0).read_exact(&mut [0])?;
+            return Ok(Readiness::Interrupted);
+        }
+    }
+
+    Ok(Readiness::Readable)
+}
+
+impl AsFd for ReadInterrupt {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0 .0.as_fd()
+    }
+}
+
+// Makes writes to the input fail with `WouldBlock` instead of waiting for the child to read. The
+// descriptor is shared with the output, so its reads have to wait for readiness from then on.
+pub(crate) fn set_input_nonblocking(input: &TerminalInput) -> io::Result<()> {
+    backend::set_nonblocking(input.as_fd())
+}
+
+/// Waits until `input` takes more data, returns `false` if `timeout` elapsed instead.
+pub(crate) fn wait_writable(input: &TerminalInput, timeout: Option<Duration>) -> io::Result<bool> {
+    Ok(poll(&mut [PollFd::new(input.as_fd(), libc::POLLOUT)], timeout)? > 0)
+}
+
+// Non-blocking writes to the master already fail with `WouldBlock` once the PTY is full.
+pub(crate) fn would_block(written: io::Result<usize>, _len: usize) -> io::Result<usize> {
+    written
+}
+
+// Plain `read(2)` and `write(2)`, for descriptors async-io polls for readiness.
+#[cfg(feature = "async-io")]
+pub(crate) fn read_fd(fd: BorrowedFd<'_>, buf: &mut [u8]) -> io::Result<usize> {
+    backend::read(fd, buf)
+}
+
+#[cfg(feature = "async-io")]
+pub(crate) fn write_fd(fd: BorrowedFd<'_>, buf: &[u8]) -> io::Result<usize> {
+    backend::write(fd, buf)
+}
+
+// Like `write_all()`, waiting for room instead of failing if `output` is non-blocking.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(crate) fn write_all_when_writable(output: &mut File, mut data: &[u8]) -> io::Result<()> {
+    while !data.is_empty() {
+        match output.write(data) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(written) => data = &data[written..],
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                poll(&mut [PollFd::new(output.as_fd(), libc::POLLOUT)], None)?;
+            }
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(())
+}
+
+/// The master side of the PTY, readable and writable through a single descriptor.
+#[derive(Clone)]
+pub(crate) struct Master(Arc<File>);
+
+impl AsFd for TerminalHandle {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+pub(crate) type TerminalInput = Master;
+pub(crate) type TerminalOutput = Master;
+
+// Hands out the descriptor as an owned `File`, duplicating it if it is still shared.
+#[cfg(any(
+    feature = "non-blocking",
+    feature = "async-std",
+    feature = "async-io",
+    all(target_os = "linux", feature = "io-uring")
+))]
+pub(crate) fn into_file(master: Master) -> io::Result<File> {
+    Arc::try_unwrap(master.0).or_else(|shared| shared.try_clone())
+}
+
+impl Master {
+    // A new descriptor for the same master, like `File::try_clone`.
+    pub fn try_clone(&self) -> io::Result<Self> {
+        Ok(Master(Arc::new(self.0.try_clone()?)))
+    }
+}
+
+impl AsFd for Master {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl Read for Master {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self.0).read(buf)
+    }
+
+    fn read_vectored(&mut self, bufs: &mut [io::IoSliceMut<'_>]) -> io::Result<usize> {
+        (&*self.0).read_vectored(bufs)
+    }
+}
+
+impl Write for Master {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self.0).write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[io::IoSlice<'_>]) -> io::Result<usize> {
+        (&*self.0).write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self.0).flush()
+    }
+}
+
+/// The PTY master of a terminal, used to query and resize it.
+#[derive(Clone)]
+pub struct TerminalHandle(Master, SizePolicy);
+
+impl TerminalHandle {
+    fn open(size_policy: SizePolicy) -> io::Result<Self> {
+        let master = File::from(backend::open_master()?);
+
+        Ok(TerminalHandle(Master(Arc::new(master)), size_policy))
+    }
+
+    // Connects the standard streams of `cmd` to a new slave descriptor.
+    pub(crate) fn attach(
+        &self,
+        cmd: &mut Command,
+        options: &TerminalOptions,
+    ) -> io::Result<Attachment> {
+        let [stdin, stdout, stderr] = options.pty_stdio;
+        let stderr = stderr && !options.separate_stderr;
+
+        // Any standard stream connected to the terminal can make it the controlling one, or be
+        // duplicated onto extra descriptors.
+        let pty_stream = [stdin, stdout, stderr]
+            .iter()
+            .position(|&on_pty| on_pty)
+            .map(|fd| fd as RawFd);
+        if pty_stream.is_none() && !options.slave_fds.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "extra slave descriptors need a standard stream on the terminal",
+            ));
+        }
+
+        options.configure(cmd);
+
+        // All clones are made before `cmd` gets any of them, a failed attach leaves it without one.
+        let slave = self.open_slave()?;
+        let clone_slave = |on_pty: bool| {
+            on_pty
+                .then(|| slave.try_clone())
+                .transpose()
+                .context("duplicating the slave descriptor")
+        };
+        let (stdin_slave, stdout_slave) = (clone_slave(stdin)?, clone_slave(stdout)?);
+
+        if let Some(slave) = stdin_slave {
+            cmd.stdin(slave);
+        }
+        if let Some(slave) = stdout_slave {
+            cmd.stdout(slave);
+        }
+        if options.separate_stderr {
+            cmd.stderr(Stdio::piped());
+        } else if stderr {
+            cmd.stderr(slave);
+        }
+
+        let attachment = Attachment {
+            armed: Arc::new(AtomicBool::new(true)),
+            on_pty: [stdin, stdout, stderr || options.separate_stderr],
+        };
+
+        // The master is opened with CLOEXEC, so the child only has to take over the slave.
+        if let Some(fd) = pty_stream.filter(|_| options.controlling_terminal) {
+            let armed = attachment.armed.clone();
+
+            unsafe {
+                cmd.pre_exec(move || {
+                    if !armed.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    backend::make_controlling_terminal(fd)
+                })
+            };
+        }
+
+        // A slave kept around for the extra descriptors would keep the terminal from hanging up
+        // after the child exited.
+        if let Some(pty_stream) = pty_stream.filter(|_| !options.slave_fds.is_empty()) {
+            let armed = attachment.armed.clone();
+            let slave_fds = options.slave_fds.clone();
+
+            unsafe {
+                cmd.pre_exec(move || {
+                    if !armed.load(Ordering::Relaxed) {
+                        return Ok(());
+                    }
+                    for &fd in &slave_fds {
+                        if libc::dup2(pty_stream, fd) == -1 {
+                            return Err(io::Error::last_os_error());
+                        }
+                    }
+                    Ok(())
+                })
+            };
+        }
+
+        Ok(attachment)
+    }
+
+    // Safety: see `Terminal::spawn_fn`.
+    pub(crate) unsafe fn fork(&self, f: impl FnOnce() -> i32) -> io::Result<ForkedChild> {
+        let slave = self.open_slave()?;
+
+        match libc::fork() {
+            -1 => Err(io::Error::last_os_error()).context("fork()"),
+            0 => {
+                for fd in 0..3 {
+                    if libc::dup2(slave.as_raw_fd(), fd) == -1 {
+                        libc::_exit(127);
+                    }
+                }
+                drop(slave);
+
+                if backend::make_controlling_terminal(0).is_err() {
+                    libc::_exit(127);
+                }
+
+                let code = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(101);
+                let _ = io::stdout().flush();
+
+                libc::_exit(code)
+            }
+            pid => Ok(ForkedChild { pid, status: None }),
+        }
+    }
+
+    // Safety: see `Terminal::from_raw_master`.
+    pub(crate) unsafe fn from_raw_master(
+        fd: RawFd,
+    ) -> io::Result<(Self, (TerminalInput, TerminalOutput))> {
+        let master = Master(Arc::new(File::from_raw_fd(fd)));
+
+        // Fails unless the descriptor is a PTY master.
+        backend::slave_name(master.as_fd()).context(format_args!("fd {fd} is not a PTY master"))?;
+
+        let handle = TerminalHandle(master.clone(), SizePolicy::default());
+
+        Ok((handle, (master.clone(), master)))
+    }
+
+    pub(crate) fn open_slave(&self) -> io::Result<OwnedFd> {
+        let name = self.slave_name()?;
+        let pts = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .custom_flags(libc::O_NOCTTY)
+            .open(&name)
+            .context(format_args!("opening the slave {}", name.display()))?;
+
+        Ok(pts.into())
+    }
+
+    /// Opens another descriptor for the PTY's slave, to attach more processes to the terminal.
+    ///
+    /// Processes attached this way share the terminal without becoming its controlling process,
+    /// e.g. the members of a pipeline whose first command was spawned on the terminal.
+    pub fn slave_stdio(&self) -> io::Result<Stdio> {
+        Ok(self.open_slave()?.into())
+    }
+
+    /// Returns the path of the PTY's slave device.
+    pub fn slave_name(&self) -> io::Result<PathBuf> {
+        backend::slave_name(self.0.as_fd()).context(format_args!(
+            "ptsname() of the master fd {}",
+            self.0.as_fd().as_raw_fd()
+        ))
+    }
+
+    pub(crate) fn master(&self) -> Master {
+        self.0.clone()
+    }
+
+    #[cfg(feature = "non-blocking")]
+    pub(crate) fn set_nonblocking(&self) -> io::Result<()> {
+        backend::set_nonblocking(self.0.as_fd())
+    }
+
+    pub fn wait_for_hangup(&self) -> io::Result<()> {
+        // POLLHUP is always reported, no events have to be requested for it.
+        let mut fds = [PollFd::new(self.0.as_fd(), 0)];
+
+        loop {
+            poll(&mut fds, None)?;
+
+            // Polling again would report POLLERR right away, a read of the master tells what it is.
+            if fds[0].revents & (libc::POLLHUP | libc::POLLERR) != 0 {
+                return Ok(());
+            }
+            if fds[0].revents & libc::POLLNVAL != 0 {
+                return Err(io::Error::from_raw_os_error(libc::EBADF));
+            }
+        }
+    }
+
+    pub fn get_term_size(&self) -> io::Result<crate::TerminalSize> {
+        let winsize = backend::get_window_size(self.0.as_fd())?;
+
+        Ok(crate::TerminalSize {
+            columns: winsize.columns,
+            rows: winsize.rows,
+        })
+    }
+
+    pub fn foreground_cwd(&self) -> io::Result<PathBuf> {
+        let pgid = backend::foreground_process_group(self.0.as_fd())?;
+
+        process_cwd(pgid)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn splice_to(&self, fd: BorrowedFd<'_>) -> io::Result<u64> {
+        const CHUNK_SIZE: usize = 64 * 1024;
+
+        // splice(2) needs a pipe on one end, so the output is staged through an intermediate one.
+        let (pipe_read, pipe_write) = backend::pipe()?;
+
+        let mut total = 0;
+        loop {
+            let mut pending = match backend::splice(self.0.as_fd(), pipe_write.as_fd(), CHUNK_SIZE)
+            {
+                Ok(0) => return Ok(total),
+                Ok(spliced) => spliced,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    poll(&mut [PollFd::new(self.0.as_fd(), libc::POLLIN)], None)?;
+                    continue;
+                }
+                Err(err) if is_session_end(&err) => return Ok(total),
+                // Older kernels can't splice from a tty.
+                Err(err) if err.raw_os_error() == Some(libc::EINVAL) && total == 0 => {
+                    return self.copy_to(fd)
+                }
+                Err(err) => return Err(err),
+            };
+
+            while pending > 0 {
+                match backend::splice(pipe_read.as_fd(), fd, pending) {
+                    Ok(spliced) => {
+                        pending -= spliced;
+                        total += spliced as u64;
+                    }
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    // The output stays in the pipe until `fd` has room for it.
+                    Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                        poll(&mut [PollFd::new(fd, libc::POLLOUT)], None)?;
+                    }
+                    Err(err) => return Err(err),
+                }
+            }
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn copy_to(&self, fd: BorrowedFd<'_>) -> io::Result<u64> {
+        let mut master = self.0.clone();
+        let mut output = File::from(fd.try_clone_to_owned()?);
+
+        let mut total = 0;
+        let mut buf = [0; 8192];
+        loop {
+            let read = match master.read(&mut buf) {
+                Ok(0) => return Ok(total),
+                Ok(read) => read,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    poll(&mut [PollFd::new(master.as_fd(), libc::POLLIN)], None)?;
+                    continue;
+                }
+                Err(err) if is_session_end(&err) => return Ok(total),
+                Err(err) => return Err(err),
+            };
+            write_all_when_writable(&mut output, &buf[..read])?;
+            total += read as u64;
+        }
+    }
+
+    pub fn set_term_size(&self, new_size: crate::TerminalSize) -> io::Result<()> {
+        let new_size = new_size.apply(self.1)?;
+
+        // The pixel size isn't part of `TerminalSize`, it's kept as set by the options.
+        let mut size = backend::get_window_size(self.0.as_fd())?;
+        size.rows = new_size.rows;
+        size.columns = new_size.columns;
+        backend::set_window_size(self.0.as_fd(), size)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn process_cwd(pid: pid_t) -> io::Result<PathBuf> {
+    std::fs::read_link(format!("/proc/{pid}/cwd"))
+}
+
+#[cfg(target_os = "macos")]
+fn process_cwd(pid: pid_t) -> io::Result<PathBuf> {
+    use libc::{proc_pidinfo, proc_vnodepathinfo, PROC_PIDVNODEPATHINFO};
+
+    let mut info: proc_vnodepathinfo = unsafe { std::mem::zeroed() };
+    let size = std::mem::size_of::<proc_vnodepathinfo>() as _;
+
+    let written = unsafe {
+        proc_pidinfo(
+            pid,
+            PROC_PIDVNODEPATHINFO,
+            0,
+            &mut info as *mut _ as *mut _,
+            size,
+        )
+    };
+    if written != size {
+        return Err(io::Error::last_os_error());
+    }
+
+    let path = unsafe { CStr::from_ptr(info.pvi_cdir.vip_path.as_ptr() as *const _) };
+
+    Ok(PathBuf::from(OsStr::from_bytes(path.to_bytes())))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+fn process_cwd(_pid: pid_t) -> io::Result<PathBuf> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "querying the working directory of a process is not supported on this platform",
+    ))
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct WindowSize {
+    pub rows: u16,
+    pub columns: u16,
+    pub x_pixels: u16,
+    pub y_pixels: u16,
+}
@@ -0,0 +1,239 @@
+use std::ffi::{CStr, OsStr};
+use std::io;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use nix::fcntl::{fcntl, FcntlArg, FdFlag, OFlag};
+use nix::libc::{self, pid_t};
+use nix::pty::{grantpt, posix_openpt, unlockpt, Winsize};
+use nix::unistd::{setsid, tcgetpgrp};
+
+use super::{ControlChar, LineModes, PollFd, WindowSize};
+use crate::error::Context;
+
+pub(super) fn open_master() -> io::Result<OwnedFd> {
+    let master = posix_openpt(OFlag::O_RDWR | OFlag::O_NOCTTY).context("posix_openpt()")?;
+    grantpt(&master).context("grantpt()")?;
+    unlockpt(&master).context("unlockpt()")?;
+
+    let master = unsafe { OwnedFd::from_raw_fd(master.into_raw_fd()) };
+    set_cloexec(master.as_fd()).context("setting close-on-exec on the master")?;
+
+    Ok(master)
+}
+
+fn set_cloexec(fd: BorrowedFd<'_>) -> io::Result<()> {
+    let raw_flags = fcntl(fd.as_raw_fd(), FcntlArg::F_GETFD)?;
+    let mut flags = FdFlag::from_bits_retain(raw_flags);
+    flags |= FdFlag::FD_CLOEXEC;
+
+    fcntl(fd.as_raw_fd(), FcntlArg::F_SETFD(flags))?;
+
+    Ok(())
+}
+
+pub(super) fn slave_name(master: BorrowedFd<'_>) -> io::Result<PathBuf> {
+    // nix only offers ptsname() on a PtyMaster it owns.
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let mut buf = [0; 128];
+        let err = unsafe { libc::ptsname_r(master.as_raw_fd(), buf.as_mut_ptr(), buf.len()) };
+        if err != 0 {
+            return Err(io::Error::from_raw_os_error(err));
+        }
+
+        let name = unsafe { CStr::from_ptr(buf.as_ptr()) };
+
+        Ok(OsStr::from_bytes(name.to_bytes()).into())
+    }
+
+    // ptsname() returns a static buffer, only one thread at a time may use and copy it.
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        static PTSNAME: Mutex<()> = Mutex::new(());
+        let _guard = PTSNAME.lock().unwrap_or_else(PoisonError::into_inner);
+
+        let name = unsafe { libc::ptsname(master.as_raw_fd()) };
+        if name.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        let name = unsafe { CStr::from_ptr(name) };
+
+        Ok(OsStr::from_bytes(name.to_bytes()).into())
+    }
+}
+
+pub(super) fn set_nonblocking(fd: BorrowedFd<'_>) -> io::Result<()> {
+    let raw_flags = fcntl(fd.as_raw_fd(), FcntlArg::F_GETFL)?;
+    let mut flags = OFlag::from_bits_retain(raw_flags);
+    flags |= OFlag::O_NONBLOCK;
+
+    fcntl(fd.as_raw_fd(), FcntlArg::F_SETFL(flags))?;
+
+    Ok(())
+}
+
+pub(super) fn get_window_size(fd: BorrowedFd<'_>) -> io::Result<WindowSize> {
+    let mut winsize: Winsize = unsafe { std::mem::zeroed() };
+
+    if unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCGWINSZ, &mut winsize as *mut _) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(WindowSize {
+        rows: winsize.ws_row,
+        columns: winsize.ws_col,
+        x_pixels: winsize.ws_xpixel,
+        y_pixels: winsize.ws_ypixel,
+    })
+}
+
+pub(super) fn set_window_size(fd: BorrowedFd<'_>, size: WindowSize) -> io::Result<()> {
+    let winsize = Winsize {
+        ws_row: size.rows,
+        ws_col: size.columns,
+        ws_xpixel: size.x_pixels,
+        ws_ypixel: size.y_pixels,
+    };
+
+    if unsafe { libc::ioctl(fd.as_raw_fd(), libc::TIOCSWINSZ, &winsize) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+pub(super) type Termios = nix::sys::termios::Termios;
+
+// Returns the previous mode for `restore_mode()`.
+pub(super) fn make_raw(fd: BorrowedFd<'_>) -> io::Result<Termios> {
+    use nix::sys::termios::{cfmakeraw, tcgetattr, tcsetattr, SetArg};
+
+    let previous = tcgetattr(fd)?;
+    let mut termios = previous.clone();
+    cfmakeraw(&mut termios);
+    tcsetattr(fd, SetArg::TCSANOW, &termios)?;
+
+    Ok(previous)
+}
+
+pub(super) fn restore_mode(fd: BorrowedFd<'_>, termios: &Termios) -> io::Result<()> {
+    use nix::sys::termios::{tcsetattr, SetArg};
+
+    Ok(tcsetattr(fd, SetArg::TCSANOW, termios)?)
+}
+
+pub(super) fn control_char(fd: BorrowedFd<'_>, char: ControlChar) -> io::Result<u8> {
+    use nix::sys::termios::{tcgetattr, SpecialCharacterIndices};
+
+    let index = match char {
+        ControlChar::Eof => SpecialCharacterIndices::VEOF,
+        ControlChar::Interrupt => SpecialCharacterIndices::VINTR,
+        ControlChar::Suspend => SpecialCharacterIndices::VSUSP,
+    };
+
+    Ok(tcgetattr(fd)?.control_chars[index as usize])
+}
+
+pub(super) fn line_modes(fd: BorrowedFd<'_>) -> io::Result<LineModes> {
+    use nix::sys::termios::{tcgetattr, InputFlags, LocalFlags};
+
+    let termios = tcgetattr(fd)?;
+
+    Ok(LineModes {
+        canonical: termios.local_flags.contains(LocalFlags::ICANON),
+        cr_to_nl: termios.input_flags.contains(InputFlags::ICRNL),
+    })
+}
+
+pub(super) fn foreground_process_group(fd: BorrowedFd<'_>) -> io::Result<pid_t> {
+    Ok(tcgetpgrp(fd.as_raw_fd())?.as_raw())
+}
+
+// Runs between fork and exec, so this must stay async-signal-safe.
+pub(super) fn make_controlling_terminal(fd: RawFd) -> io::Result<()> {
+    setsid()?;
+
+    if unsafe { libc::ioctl(fd, libc::TIOCSCTTY as _, 1) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+pub(super) fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            let (read, write) = nix::unistd::pipe2(OFlag::O_CLOEXEC)?;
+            let (read, write) = unsafe { (OwnedFd::from_raw_fd(read), OwnedFd::from_raw_fd(write)) };
+        } else {
+            let (read, write) = nix::unistd::pipe()?;
+            let (read, write) = unsafe { (OwnedFd::from_raw_fd(read), OwnedFd::from_raw_fd(write)) };
+            set_cloexec(read.as_fd())?;
+            set_cloexec(write.as_fd())?;
+        }
+    }
+
+    Ok((read, write))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn splice(from: BorrowedFd<'_>, to: BorrowedFd<'_>, len: usize) -> io::Result<usize> {
+    use nix::fcntl::SpliceFFlags;
+
+    Ok(nix::fcntl::splice(
+        from.as_raw_fd(),
+        None,
+        to.as_raw_fd(),
+        None,
+        len,
+        SpliceFFlags::SPLICE_F_MOVE,
+    )?)
+}
+
+#[cfg(feature = "async-io")]
+pub(super) fn read(fd: BorrowedFd<'_>, buf: &mut [u8]) -> io::Result<usize> {
+    Ok(nix::unistd::read(fd.as_raw_fd(), buf)?)
+}
+
+#[cfg(feature = "async-io")]
+pub(super) fn write(fd: BorrowedFd<'_>, buf: &[u8]) -> io::Result<usize> {
+    Ok(nix::unistd::write(fd.as_raw_fd(), buf)?)
+}
+
+pub(super) fn poll(fds: &mut [PollFd<'_>], timeout: Option<Duration>) -> io::Result<usize> {
+    use nix::poll::PollFlags;
+
+    let timeout = match timeout {
+        Some(timeout) => timeout
+            .as_nanos()
+            .div_ceil(1_000_000)
+            .try_into()
+            .unwrap_or(libc::c_int::MAX),
+        None => -1,
+    };
+
+    let mut poll_fds: Vec<_> = fds
+        .iter()
+        .map(|fd| nix::poll::PollFd::new(&fd.fd, PollFlags::from_bits_truncate(fd.events)))
+        .collect();
+
+    let ready = nix::poll::poll(&mut poll_fds, timeout)?;
+
+    let revents: Vec<_> = poll_fds
+        .iter()
+        .map(|poll_fd| poll_fd.revents().map_or(0, |revents| revents.bits()))
+        .collect();
+    drop(poll_fds);
+
+    for (fd, revents) in fds.iter_mut().zip(revents) {
+        fd.revents = revents;
+    }
+
+    Ok(ready as usize)
+}
@@ -0,0 +1,187 @@
+use std::ffi::OsStr;
+use std::io;
+use std::os::fd::{BorrowedFd, OwnedFd, RawFd};
+use std::os::unix::ffi::OsStrExt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use libc::pid_t;
+use rustix::io::{fcntl_getfd, fcntl_setfd, FdFlags};
+use rustix::pty::{grantpt, openpt, ptsname, unlockpt, OpenptFlags};
+use rustix::termios::{tcgetpgrp, tcgetwinsize, tcsetwinsize, Winsize};
+
+use super::{ControlChar, LineModes, PollFd, WindowSize};
+use crate::error::Context;
+
+pub(super) fn open_master() -> io::Result<OwnedFd> {
+    let master = openpt(OpenptFlags::RDWR | OpenptFlags::NOCTTY).context("posix_openpt()")?;
+    grantpt(&master).context("grantpt()")?;
+    unlockpt(&master).context("unlockpt()")?;
+
+    fcntl_getfd(&master)
+        .and_then(|flags| fcntl_setfd(&master, flags | FdFlags::CLOEXEC))
+        .context("setting close-on-exec on the master")?;
+
+    Ok(master)
+}
+
+pub(super) fn slave_name(master: BorrowedFd<'_>) -> io::Result<PathBuf> {
+    let name = ptsname(master, Vec::new())?;
+
+    Ok(OsStr::from_bytes(name.as_bytes()).into())
+}
+
+pub(super) fn set_nonblocking(fd: BorrowedFd<'_>) -> io::Result<()> {
+    use rustix::fs::{fcntl_getfl, fcntl_setfl, OFlags};
+
+    let flags = fcntl_getfl(fd)?;
+    fcntl_setfl(fd, flags | OFlags::NONBLOCK)?;
+
+    Ok(())
+}
+
+pub(super) fn get_window_size(fd: BorrowedFd<'_>) -> io::Result<WindowSize> {
+    let winsize = tcgetwinsize(fd)?;
+
+    Ok(WindowSize {
+        rows: winsize.ws_row,
+        columns: winsize.ws_col,
+        x_pixels: winsize.ws_xpixel,
+        y_pixels: winsize.ws_ypixel,
+    })
+}
+
+pub(super) fn set_window_size(fd: BorrowedFd<'_>, size: WindowSize) -> io::Result<()> {
+    let winsize = Winsize {
+        ws_row: size.rows,
+        ws_col: size.columns,
+        ws_xpixel: size.x_pixels,
+        ws_ypixel: size.y_pixels,
+    };
+
+    Ok(tcsetwinsize(fd, winsize)?)
+}
+
+pub(super) type Termios = rustix::termios::Termios;
+
+// Returns the previous mode for `restore_mode()`.
+pub(super) fn make_raw(fd: BorrowedFd<'_>) -> io::Result<Termios> {
+    use rustix::termios::{tcgetattr, tcsetattr, OptionalActions};
+
+    let previous = tcgetattr(fd)?;
+    let mut termios = previous.clone();
+    termios.make_raw();
+    tcsetattr(fd, OptionalActions::Now, &termios)?;
+
+    Ok(previous)
+}
+
+pub(super) fn restore_mode(fd: BorrowedFd<'_>, termios: &Termios) -> io::Result<()> {
+    use rustix::termios::{tcsetattr, OptionalActions};
+
+    Ok(tcsetattr(fd, OptionalActions::Now, termios)?)
+}
+
+pub(super) fn control_char(fd: BorrowedFd<'_>, char: ControlChar) -> io::Result<u8> {
+    use rustix::termios::{tcgetattr, SpecialCodeIndex};
+
+    let index = match char {
+        ControlChar::Eof => SpecialCodeIndex::VEOF,
+        ControlChar::Interrupt => SpecialCodeIndex::VINTR,
+        ControlChar::Suspend => SpecialCodeIndex::VSUSP,
+    };
+
+    Ok(tcgetattr(fd)?.special_codes[index])
+}
+
+pub(super) fn line_modes(fd: BorrowedFd<'_>) -> io::Result<LineModes> {
+    use rustix::termios::{tcgetattr, InputModes, LocalModes};
+
+    let termios = tcgetattr(fd)?;
+
+    Ok(LineModes {
+        canonical: termios.local_modes.contains(LocalModes::ICANON),
+        cr_to_nl: termios.input_modes.contains(InputModes::ICRNL),
+    })
+}
+
+pub(super) fn foreground_process_group(fd: BorrowedFd<'_>) -> io::Result<pid_t> {
+    Ok(tcgetpgrp(fd)?.as_raw_nonzero().get())
+}
+
+// Runs between fork and exec, so this must stay async-signal-safe.
+pub(super) fn make_controlling_terminal(fd: RawFd) -> io::Result<()> {
+    rustix::process::setsid()?;
+
+    let slave = unsafe { BorrowedFd::borrow_raw(fd) };
+    rustix::process::ioctl_tiocsctty(slave)?;
+
+    Ok(())
+}
+
+pub(super) fn pipe() -> io::Result<(OwnedFd, OwnedFd)> {
+    cfg_if::cfg_if! {
+        if #[cfg(any(target_os = "linux", target_os = "android"))] {
+            use rustix::pipe::{pipe_with, PipeFlags};
+
+            Ok(pipe_with(PipeFlags::CLOEXEC)?)
+        } else {
+            let (read, write) = rustix::pipe::pipe()?;
+            fcntl_setfd(&read, fcntl_getfd(&read)? | FdFlags::CLOEXEC)?;
+            fcntl_setfd(&write, fcntl_getfd(&write)? | FdFlags::CLOEXEC)?;
+
+            Ok((read, write))
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub(super) fn splice(from: BorrowedFd<'_>, to: BorrowedFd<'_>, len: usize) -> io::Result<usize> {
+    use rustix::pipe::SpliceFlags;
+
+    Ok(rustix::pipe::splice(
+        from,
+        None,
+        to,
+        None,
+        len,
+        SpliceFlags::MOVE,
+    )?)
+}
+
+#[cfg(feature = "async-io")]
+pub(super) fn read(fd: BorrowedFd<'_>, buf: &mut [u8]) -> io::Result<usize> {
+    Ok(rustix::io::read(fd, buf)?)
+}
+
+#[cfg(feature = "async-io")]
+pub(super) fn write(fd: BorrowedFd<'_>, buf: &[u8]) -> io::Result<usize> {
+    Ok(rustix::io::write(fd, buf)?)
+}
+
+pub(super) fn poll(fds: &mut [PollFd<'_>], timeout: Option<Duration>) -> io::Result<usize> {
+    use rustix::event::{PollFlags, Timespec};
+
+    let timeout = timeout
+        .map(|timeout| Timespec::try_from(timeout).map_err(|_| io::ErrorKind::InvalidInput))
+        .transpose()?;
+
+    let mut poll_fds: Vec<_> = fds
+        .iter()
+        .map(|fd| rustix::event::PollFd::new(&fd.fd, PollFlags::from_bits_truncate(fd.events as _)))
+        .collect();
+
+    let ready = rustix::event::poll(&mut poll_fds, timeout.as_ref())?;
+
+    let revents: Vec<_> = poll_fds
+        .iter()
+        .map(|poll_fd| poll_fd.revents().bits() as _)
+        .collect();
+    drop(poll_fds);
+
+    for (fd, revents) in fds.iter_mut().zip(revents) {
+        fd.revents = revents;
+    }
+
+    Ok(ready)
+}
@@ -1,19 +1,55 @@
 use std::fs::File;
-use std::io;
+use std::io::{self, Write};
 use std::mem::zeroed;
-use std::os::windows::io::FromRawHandle;
+use std::os::windows::io::{AsHandle, AsRawHandle, FromRawHandle};
 use std::os::windows::process::CommandExt;
 use std::process::Command;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-use windows::Win32::Foundation::CloseHandle;
+use windows::core::w;
 use windows::Win32::Foundation::HANDLE;
+use windows::Win32::Foundation::{CloseHandle, ERROR_BROKEN_PIPE};
+use windows::Win32::Foundation::{GENERIC_READ, GENERIC_WRITE};
+use windows::Win32::Storage::FileSystem::{
+    CreateFileW, FILE_FLAGS_AND_ATTRIBUTES, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+};
 use windows::Win32::System::Console::{
-    ClosePseudoConsole, CreatePseudoConsole, ResizePseudoConsole, COORD, HPCON,
+    AttachConsole, ClosePseudoConsole, CreatePseudoConsole, FreeConsole, GetConsoleMode,
+    GetConsoleScreenBufferInfo, GetStdHandle, ResizePseudoConsole, SetConsoleCP, SetConsoleMode,
+    SetConsoleOutputCP, ATTACH_PARENT_PROCESS, CONSOLE_MODE, CONSOLE_SCREEN_BUFFER_INFO, COORD,
+    ENABLE_VIRTUAL_TERMINAL_INPUT, ENABLE_VIRTUAL_TERMINAL_PROCESSING, HPCON, STD_OUTPUT_HANDLE,
+};
+use windows::Win32::System::Pipes::{
+    CreatePipe, PeekNamedPipe, SetNamedPipeHandleState, PIPE_NOWAIT,
 };
-use windows::Win32::System::Pipes::CreatePipe;
 use windows::Win32::System::Threading::PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE;
 
-pub(crate) fn open_handle_and_io(cmd: &mut Command) -> io::Result<(TerminalHandle, (File, File))> {
+use crate::error::Context;
+use crate::{SizePolicy, TerminalOptions};
+
+pub(crate) type TerminalInput = File;
+pub(crate) type TerminalOutput = File;
+
+#[cfg(any(feature = "non-blocking", feature = "async-std"))]
+pub(crate) fn into_file(file: File) -> io::Result<File> {
+    Ok(file)
+}
+
+// tokio runs the blocking I/O of anonymous pipes on its thread pool.
+#[cfg(feature = "non-blocking")]
+pub(crate) type AsyncMaster = tokio::fs::File;
+
+#[cfg(feature = "non-blocking")]
+pub(crate) fn async_master(file: File) -> io::Result<AsyncMaster> {
+    Ok(file.into())
+}
+
+pub(crate) fn open_handle_and_io(
+    options: &TerminalOptions,
+) -> io::Result<(TerminalHandle, (TerminalInput, TerminalOutput))> {
     // - Close these after CreateProcess of child application with pseudoconsole object.
     let (mut input_read_side, mut output_write_side) = unsafe { (zeroed(), zeroed()) };
 
@@ -21,18 +57,13 @@ pub(crate) fn open_handle_and_io(cmd: &mut Command) -> io::Result<(TerminalHandl
     let (mut output_read_side, mut input_write_side) = unsafe { (zeroed(), zeroed()) };
 
     unsafe {
-        CreatePipe(&mut input_read_side, &mut input_write_side, None, 0)?;
-        CreatePipe(&mut output_read_side, &mut output_write_side, None, 0)?;
+        CreatePipe(&mut input_read_side, &mut input_write_side, None, 0)
+            .context("CreatePipe() for the input")?;
+        CreatePipe(&mut output_read_side, &mut output_write_side, None, 0)
+            .context("CreatePipe() for the output")?;
     }
 
-    let terminal_handle = TerminalHandle::open(input_read_side, output_write_side)?;
-
-    unsafe {
-        cmd.raw_attribute(
-            PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE as usize,
-            terminal_handle.0,
-        )
-    };
+    let terminal_handle = TerminalHandle::open(input_read_side, output_write_side, options)?;
 
     let io = unsafe {
         (
@@ -44,36 +75,328 @@ pub(crate) fn open_handle_and_io(cmd: &mut Command) -> io::Result<(TerminalHandl
     Ok((terminal_handle, io))
 }
 
-pub struct TerminalHandle(HPCON);
+/// Applies the console settings of `options` to the console of the process `pid`.
+///
+/// Console settings can only be changed from a process attached to that console, so this
+/// briefly trades the console of the calling process for the child's one.
+pub(crate) fn configure_console(pid: u32, options: &TerminalOptions) -> io::Result<()> {
+    if !options.configures_console() {
+        return Ok(());
+    }
+
+    unsafe {
+        let _ = FreeConsole();
+        AttachConsole(pid).context(format_args!("attaching to the console of process {pid}"))?;
+    }
+
+    let result = unsafe { configure_attached_console(options) }
+        .context(format_args!("configuring the console of process {pid}"));
+
+    unsafe {
+        let _ = FreeConsole();
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+
+    result
+}
+
+unsafe fn configure_attached_console(options: &TerminalOptions) -> io::Result<()> {
+    if let Some(code_page) = options.console_code_page {
+        SetConsoleCP(code_page).context(format_args!("SetConsoleCP({code_page})"))?;
+        SetConsoleOutputCP(code_page).context(format_args!("SetConsoleOutputCP({code_page})"))?;
+    }
+
+    if let Some(enabled) = options.virtual_terminal_input {
+        update_console_mode(w!("CONIN$"), ENABLE_VIRTUAL_TERMINAL_INPUT, enabled)?;
+    }
+    if let Some(enabled) = options.virtual_terminal_processing {
+        update_console_mode(w!("CONOUT$"), ENABLE_VIRTUAL_TERMINAL_PROCESSING, enabled)?;
+    }
+
+    Ok(())
+}
+
+unsafe fn update_console_mode(
+    name: windows::core::PCWSTR,
+    flag: CONSOLE_MODE,
+    enabled: bool,
+) -> io::Result<()> {
+    let handle = CreateFileW(
+        name,
+        (GENERIC_READ | GENERIC_WRITE).0,
+        FILE_SHARE_READ | FILE_SHARE_WRITE,
+        None,
+        OPEN_EXISTING,
+        FILE_FLAGS_AND_ATTRIBUTES(0),
+        HANDLE::default(),
+    )
+    .context(format_args!("opening {}", name.display()))?;
+
+    let mut mode = CONSOLE_MODE::default();
+    let result = GetConsoleMode(handle, &mut mode).and_then(|_| {
+        let mode = if enabled { mode | flag } else { mode & !flag };
+        SetConsoleMode(handle, mode)
+    });
+    let _ = CloseHandle(handle);
+
+    result.context(format_args!("changing the mode of {}", name.display()))
+}
+
+pub(crate) fn current_terminal_size() -> io::Result<crate::TerminalSize> {
+    let mut info = CONSOLE_SCREEN_BUFFER_INFO::default();
+    unsafe { GetConsoleScreenBufferInfo(GetStdHandle(STD_OUTPUT_HANDLE)?, &mut info)? };
+
+    // The visible window, the buffer behind it is usually much taller.
+    let window = info.srWindow;
+    Ok(crate::TerminalSize {
+        columns: (window.Right - window.Left + 1) as u16,
+        rows: (window.Bottom - window.Top + 1) as u16,
+    })
+}
+
+/// Resizes a terminal along with the host terminal, see
+/// [`Terminal::follow_host_resize`](crate::Terminal::follow_host_resize).
+///
+/// Following stops once this is dropped.
+pub struct ResizeFollower {
+    stop: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl ResizeFollower {
+    const INTERVAL: Duration = Duration::from_millis(100);
+
+    // Waiting for `WINDOW_BUFFER_SIZE_EVENT`s would consume the console input meant for the rest
+    // of the process, so the console size is polled instead.
+    pub(crate) fn start(handle: TerminalHandle) -> io::Result<Self> {
+        let (stop, stopped) = mpsc::channel();
+
+        let thread = thread::Builder::new()
+            .name("pseudoterminal-resize".into())
+            .spawn(move || {
+                let mut last = None;
+
+                loop {
+                    // Best effort, without a console the pseudoconsole keeps its size.
+                    if let Ok(size) = current_terminal_size() {
+                        if last != Some(size) && handle.set_term_size(size).is_ok() {
+                            last = Some(size);
+                        }
+                    }
+
+                    if stopped.recv_timeout(Self::INTERVAL) != Err(RecvTimeoutError::Timeout) {
+                        return;
+                    }
+                }
+            })?;
+
+        Ok(ResizeFollower {
+            stop: Some(stop),
+            thread: Some(thread),
+        })
+    }
+}
+
+impl Drop for ResizeFollower {
+    fn drop(&mut self) {
+        drop(self.stop.take());
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// What ended a wait for a pipe to become readable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Readiness {
+    Readable,
+    TimedOut,
+}
+
+// Anonymous pipes don't support overlapped I/O, so this checks for pending output until the
+// timeout elapses.
+pub(crate) fn wait_readable(
+    pipe: &impl AsHandle,
+    timeout: Option<Duration>,
+) -> io::Result<Readiness> {
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    let pipe = HANDLE(pipe.as_handle().as_raw_handle() as _);
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        let mut available = 0;
+        match unsafe { PeekNamedPipe(pipe, None, 0, None, Some(&mut available), None) } {
+            Ok(()) if available > 0 => return Ok(Readiness::Readable),
+            Ok(()) => {}
+            // A closed pipe is read as EOF right away.
+            Err(err) if err.code() == ERROR_BROKEN_PIPE.to_hresult() => {
+                return Ok(Readiness::Readable)
+            }
+            Err(err) => return Err(err.into()),
+        }
+
+        let Some(deadline) = deadline else {
+            return Ok(Readiness::Readable);
+        };
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Ok(Readiness::TimedOut);
+        }
+
+        thread::sleep(remaining.min(INTERVAL));
+    }
+}
+
+// Anonymous pipes are byte-mode named pipes, without waiting their writes return once the
+// buffer is full.
+pub(crate) fn set_input_nonblocking(input: &TerminalInput) -> io::Result<()> {
+    let pipe = HANDLE(input.as_raw_handle() as _);
+    unsafe { SetNamedPipeHandleState(pipe, Some(&PIPE_NOWAIT), None, None)? };
+
+    Ok(())
+}
+
+// The write end of a pipe reports no readiness, so this only paces the retries.
+pub(crate) fn wait_writable(_input: &TerminalInput, timeout: Option<Duration>) -> io::Result<bool> {
+    const INTERVAL: Duration = Duration::from_millis(10);
+
+    match timeout {
+        Some(timeout) if timeout.is_zero() => Ok(false),
+        timeout => {
+            thread::sleep(timeout.map_or(INTERVAL, |timeout| timeout.min(INTERVAL)));
+            Ok(true)
+        }
+    }
+}
+
+// A full pipe that doesn't wait accepts nothing instead of failing.
+pub(crate) fn would_block(written: io::Result<usize>, len: usize) -> io::Result<usize> {
+    match written {
+        Ok(0) if len > 0 => Err(io::ErrorKind::WouldBlock.into()),
+        written => written,
+    }
+}
+
+pub(crate) fn is_session_end(_err: &io::Error) -> bool {
+    false
+}
+
+/// A console key with a special meaning to the processes reading the console.
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum ControlChar {
+    Eof,
+    Interrupt,
+}
+
+// The pseudoconsole turns Ctrl-C into a `CTRL_C_EVENT`, console programs read Ctrl-Z at the start
+// of a line as the end of their input.
+pub(crate) fn control_char(_input: &impl AsHandle, char: ControlChar) -> io::Result<Vec<u8>> {
+    Ok(match char {
+        ControlChar::Eof => b"\x1a\r".to_vec(),
+        ControlChar::Interrupt => b"\x03".to_vec(),
+    })
+}
+
+// Enter reaches console programs as a carriage return.
+pub(crate) fn line_terminator(_input: &impl AsHandle) -> io::Result<&'static [u8]> {
+    Ok(b"\r")
+}
+
+// Writes the key straight to the handle, for inputs that can't be written otherwise, e.g. async
+// ones being dropped.
+pub(crate) fn send_control_char(input: &impl AsHandle, char: ControlChar) -> io::Result<()> {
+    let bytes = control_char(input, char)?;
+
+    // The handle stays owned by `input`.
+    let file = std::mem::ManuallyDrop::new(unsafe {
+        File::from_raw_handle(input.as_handle().as_raw_handle())
+    });
+    (&*file).write_all(&bytes)
+}
+
+// Delivers EOF for an input being dropped. The console mode of the child can't be queried from
+// here, so unlike on Unix this doesn't spare children reading raw input.
+pub(crate) fn send_eof_on_drop(input: &impl AsHandle) {
+    // The child may already be gone, in which case nobody is left waiting for the EOF.
+    let _ = send_control_char(input, ControlChar::Eof);
+}
+
+// The pseudoconsole is passed as a process attribute, the command doesn't hold on to any handles.
+pub(crate) struct Attachment;
+
+impl Attachment {
+    pub(crate) fn release(self, _cmd: &mut Command) {}
+}
+
+// Exited processes don't linger on Windows, closing the process handle is enough.
+pub(crate) fn reap_in_background(_pid: u32) {}
+
+/// The pseudoconsole of a terminal, used to resize it.
+#[derive(Clone)]
+pub struct TerminalHandle(Arc<PseudoConsole>, SizePolicy);
+
+struct PseudoConsole(HPCON);
 
 impl TerminalHandle {
-    fn open(input: HANDLE, output: HANDLE) -> io::Result<Self> {
-        let size = COORD { X: 60, Y: 40 };
+    fn open(input: HANDLE, output: HANDLE, options: &TerminalOptions) -> io::Result<Self> {
+        let size = match options.size {
+            Some(size) => size.apply(options.size_policy)?.into(),
+            None => COORD { X: 60, Y: 40 },
+        };
+
+        let h_pc = unsafe { CreatePseudoConsole(size, input, output, options.pseudoconsole_flags) }
+            .context(format_args!(
+                "CreatePseudoConsole() of size {}x{}",
+                size.X, size.Y
+            ))?;
+
+        unsafe { CloseHandle(input) }.context("closing the pseudoconsole's input pipe")?;
+        unsafe { CloseHandle(output) }.context("closing the pseudoconsole's output pipe")?;
 
-        let h_pc = unsafe { CreatePseudoConsole(size, input, output, 0)? };
+        Ok(TerminalHandle(
+            Arc::new(PseudoConsole(h_pc)),
+            options.size_policy,
+        ))
+    }
+
+    // Starts `cmd` attached to the pseudoconsole.
+    pub(crate) fn attach(
+        &self,
+        cmd: &mut Command,
+        options: &TerminalOptions,
+    ) -> io::Result<Attachment> {
+        options.configure(cmd);
 
-        unsafe { CloseHandle(input)? };
-        unsafe { CloseHandle(output)? };
+        unsafe { cmd.raw_attribute(PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE as usize, self.0 .0) };
 
-        Ok(TerminalHandle(h_pc))
+        Ok(Attachment)
     }
 
+    // The pipes stay blocking, see `AsyncMaster`.
     #[cfg(feature = "non-blocking")]
-    pub fn set_nonblocking(&self) -> io::Result<()> {
-        todo!()
+    pub(crate) fn set_nonblocking(&self) -> io::Result<()> {
+        Ok(())
     }
 
-    pub fn set_term_size(&mut self, new_size: crate::TerminalSize) -> io::Result<()> {
-        let coord_size = COORD {
-            X: new_size.rows as i16,
-            Y: new_size.columns as i16,
-        };
+    pub fn set_term_size(&self, new_size: crate::TerminalSize) -> io::Result<()> {
+        let new_size = new_size.apply(self.1)?;
+
+        unsafe { Ok(ResizePseudoConsole(self.0 .0, new_size.into())?) }
+    }
+}
 
-        unsafe { Ok(ResizePseudoConsole(self.0, coord_size)?) }
+impl From<crate::TerminalSize> for COORD {
+    fn from(value: crate::TerminalSize) -> Self {
+        COORD {
+            X: value.columns as i16,
+            Y: value.rows as i16,
+        }
     }
 }
 
-impl Drop for TerminalHandle {
+impl Drop for PseudoConsole {
     fn drop(&mut self) {
         unsafe { ClosePseudoConsole(self.0) }
     }
@@ -0,0 +1,218 @@
+//! A harness running terminal user interfaces headlessly in tests, see [`TuiTest`].
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! use pseudoterminal::keys::{Key, Modifiers};
+//! use pseudoterminal::screen::assert_screen_contains;
+//! use pseudoterminal::testing::TuiTest;
+//! use pseudoterminal::TerminalSize;
+//!
+//! let size = TerminalSize { rows: 24, columns: 80 };
+//! let mut tui = TuiTest::spawn(&mut Command::new("htop"), size)?;
+//!
+//! assert_screen_contains(&tui.wait_stable()?, "Load average");
+//! tui.send_key(Key::F(10), Modifiers::NONE)?;
+//! assert!(tui.wait()?.success());
+//! # std::io::Result::Ok(())
+//! ```
+
+use std::io::{self, Read, Write};
+use std::process::{Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+use crate::keys::{Key, Modifiers};
+use crate::screen::Screen;
+use crate::{CommandExt, Terminal, TerminalIn, TerminalOptions, TerminalOut, TerminalSize};
+
+/// A program running on an emulated screen, driven by keys and resizes.
+///
+/// The output is only read while waiting, the screen shows what was drawn up to the last wait.
+/// Waits give up after the [timeout](Self::set_timeout) with [`io::ErrorKind::TimedOut`], the
+/// error message shows the screen at that point.
+pub struct TuiTest {
+    terminal: Terminal,
+    eof: bool,
+    settle_time: Duration,
+    timeout: Duration,
+}
+
+impl TuiTest {
+    /// Spawns `cmd` in a new terminal of `size` with an emulated screen.
+    pub fn spawn(cmd: &mut Command, size: TerminalSize) -> io::Result<Self> {
+        let mut options = TerminalOptions::new();
+        options.size(size);
+
+        Self::new(cmd.spawn_terminal_with(&options)?)
+    }
+
+    /// Takes over a terminal, enabling its screen if it has none yet.
+    pub fn new(mut terminal: Terminal) -> io::Result<Self> {
+        if terminal.screen().is_none() {
+            terminal.enable_screen()?;
+        }
+
+        Ok(Self {
+            terminal,
+            eof: false,
+            settle_time: Duration::from_millis(100),
+            timeout: Duration::from_secs(10),
+        })
+    }
+
+    /// Sets how long the output has to pause for the screen to count as stable.
+    ///
+    /// 100 milliseconds by default.
+    pub fn set_settle_time(&mut self, settle_time: Duration) {
+        self.settle_time = settle_time;
+    }
+
+    /// Sets how long a wait takes at most.
+    ///
+    /// 10 seconds by default.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// Returns the screen as of the last wait.
+    pub fn screen(&self) -> Screen {
+        self.terminal
+            .screen()
+            .expect("the screen was enabled on creation")
+    }
+
+    /// Waits until the program stops drawing, i.e. there was no output for the settle time or
+    /// the output ended, and returns the screen.
+    pub fn wait_stable(&mut self) -> io::Result<Screen> {
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let wait = self.settle_time.min(remaining);
+
+            if self.eof || !self.termout()?.wait_readable(Some(wait))? {
+                if self.eof || wait == self.settle_time {
+                    return Ok(self.screen());
+                }
+                return Err(self.timed_out("the screen didn't settle"));
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Waits until `text` is drawn within a row of the screen and returns the screen.
+    ///
+    /// Fails with [`io::ErrorKind::UnexpectedEof`] if the output ends first.
+    pub fn wait_for_text(&mut self, text: &str) -> io::Result<Screen> {
+        let deadline = Instant::now() + self.timeout;
+
+        loop {
+            let screen = self.screen();
+            if screen.contains(text) {
+                return Ok(screen);
+            }
+
+            if self.eof {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!(
+                        "the output ended before {text:?} showed up, the screen shows:\n{screen}"
+                    ),
+                ));
+            }
+
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || !self.termout()?.wait_readable(Some(remaining))? {
+                return Err(self.timed_out(&format!("{text:?} didn't show up")));
+            }
+            self.fill()?;
+        }
+    }
+
+    /// Presses `key`, encoded for the cursor key mode the program switched to.
+    pub fn send_key(&mut self, key: Key, modifiers: Modifiers) -> io::Result<()> {
+        let mut keys = self.terminal.key_encoder();
+        keys.application_cursor(self.screen().application_cursor());
+
+        keys.write_key(self.termin()?, key, modifiers)
+    }
+
+    /// Types `text` as is, e.g. `"ls\r"`.
+    pub fn type_text(&mut self, text: &str) -> io::Result<()> {
+        let termin = self.termin()?;
+        termin.write_all(text.as_bytes())?;
+
+        termin.flush()
+    }
+
+    /// Resizes the terminal along with the screen, the program is notified like by a user
+    /// resizing their window.
+    pub fn resize(&mut self, size: TerminalSize) -> io::Result<()> {
+        self.terminal.set_term_size(size)
+    }
+
+    /// Waits until the output ended and the program exited.
+    pub fn wait(&mut self) -> io::Result<ExitStatus> {
+        let deadline = Instant::now() + self.timeout;
+
+        while !self.eof {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() || !self.termout()?.wait_readable(Some(remaining))? {
+                return Err(self.timed_out("the program didn't exit"));
+            }
+            self.fill()?;
+        }
+
+        self.terminal.wait()
+    }
+
+    pub fn get_ref(&self) -> &Terminal {
+        &self.terminal
+    }
+
+    pub fn get_mut(&mut self) -> &mut Terminal {
+        &mut self.terminal
+    }
+
+    pub fn into_inner(self) -> Terminal {
+        self.terminal
+    }
+
+    // Reads the next chunk of output, which updates the screen.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut chunk = [0; 4096];
+        let read = loop {
+            match self.termout()?.read(&mut chunk) {
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                result => break result?,
+            }
+        };
+        self.eof = read == 0;
+
+        Ok(())
+    }
+
+    fn timed_out(&self, what: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::TimedOut,
+            format!(
+                "{what} within {:?}, the screen shows:\n{}",
+                self.timeout,
+                self.screen()
+            ),
+        )
+    }
+
+    fn termin(&mut self) -> io::Result<&mut TerminalIn> {
+        self.terminal
+            .termin
+            .as_mut()
+            .ok_or_else(|| io::Error::other("the terminal's input was taken"))
+    }
+
+    fn termout(&mut self) -> io::Result<&mut TerminalOut> {
+        self.terminal
+            .termout
+            .as_mut()
+            .ok_or_else(|| io::Error::other("the terminal's output was taken"))
+    }
+}
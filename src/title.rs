@@ -0,0 +1,91 @@
+//! Window titles set by a terminal's programs through `OSC 0` and `OSC 2`, see [`TitleTracker`].
+//!
+//! Shells and editors set them to show the current directory or file, which tabs of a terminal
+//! application or the header of a web terminal can display.
+//!
+//! ```
+//! use pseudoterminal::title::TitleTracker;
+//!
+//! let mut titles = TitleTracker::new();
+//!
+//! assert_eq!(titles.feed(b"\x1b]0;vim notes.txt\x07text"), ["vim notes.txt"]);
+//! assert_eq!(titles.title(), Some("vim notes.txt"));
+//! ```
+//!
+//! [`Terminal::on_title_change`](crate::Terminal::on_title_change) calls back on every change
+//! instead.
+
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::osc::OscScanner;
+
+// Parses the payload of an OSC, between `ESC ]` and the terminator. `OSC 1` only sets the icon
+// name, which terminal emulators hardly show anymore.
+fn title_from_osc(payload: &[u8]) -> Option<String> {
+    let title = payload
+        .strip_prefix(b"0;")
+        .or_else(|| payload.strip_prefix(b"2;"))?;
+
+    Some(String::from_utf8_lossy(title).into_owned())
+}
+
+/// Picks the title changes out of a terminal's output.
+///
+/// The output can be fed in chunks of any size, sequences split across chunks are still found.
+/// Titles are decoded as UTF-8, replacing invalid sequences.
+#[derive(Clone, Debug, Default)]
+pub struct TitleTracker {
+    scanner: OscScanner,
+    title: Option<String>,
+}
+
+impl TitleTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the titles set by `data`, in the order they were set.
+    ///
+    /// An empty title resets it to the terminal's default.
+    pub fn feed(&mut self, data: &[u8]) -> Vec<String> {
+        let mut titles = Vec::new();
+        self.scanner.scan(data, |payload, _| {
+            titles.extend(title_from_osc(payload));
+        });
+
+        if let Some(title) = titles.last() {
+            self.title = Some(title.clone());
+        }
+
+        titles
+    }
+
+    /// Returns the title set last, `None` if none was set yet.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+}
+
+type Callback = Box<dyn FnMut(&str) + Send>;
+
+// A tracker shared by a terminal's outputs, calling back on each change they read.
+#[derive(Clone)]
+pub(crate) struct TitleWatcher(Arc<Mutex<(TitleTracker, Callback)>>);
+
+impl TitleWatcher {
+    pub(crate) fn new(callback: impl FnMut(&str) + Send + 'static) -> Self {
+        Self(Arc::new(Mutex::new((
+            TitleTracker::new(),
+            Box::new(callback),
+        ))))
+    }
+
+    pub(crate) fn feed(&self, data: &[u8]) {
+        let mut watcher = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let (tracker, callback) = &mut *watcher;
+
+        for title in tracker.feed(data) {
+            callback(&title);
+        }
+    }
+}
@@ -0,0 +1,236 @@
+//! An io_uring based async terminal for tokio-uring, see [`CommandExt`].
+//!
+//! Reads and writes are submitted to the ring instead of waiting for readiness first, which
+//! saves a syscall per operation when serving many terminals. Like tokio-uring itself, the
+//! halves take ownership of the buffers while an operation is in flight and have to be used
+//! within [`tokio_uring::start`].
+//!
+//! ```no_run
+//! # use std::process::Command;
+//! use pseudoterminal::uring::CommandExt;
+//!
+//! tokio_uring::start(async {
+//!     let terminal = Command::new("ls").spawn_terminal()?;
+//!
+//!     let (read, buf) = terminal.termout.as_ref().unwrap().read(vec![0; 4096]).await;
+//!     println!("{}", String::from_utf8_lossy(&buf[..read?]));
+//!     std::io::Result::Ok(())
+//! })?;
+//! # std::io::Result::Ok(())
+//! ```
+
+use std::io;
+use std::os::fd::{AsRawFd, BorrowedFd, OwnedFd};
+use std::process::{Command, ExitStatus};
+
+use tokio::task::spawn_blocking;
+use tokio_uring::buf::{BoundedBuf, BoundedBufMut, IoBuf};
+use tokio_uring::fs::File;
+use tokio_uring::BufResult;
+
+use crate::sys::{
+    control_char, is_session_end, line_terminator, send_control_char, send_eof_on_drop, ControlChar,
+};
+use crate::{TerminalController, TerminalOptions, TerminalSize};
+
+pub struct Terminal {
+    inner: crate::Terminal,
+    pub termin: Option<TerminalIn>,
+    pub termout: Option<TerminalOut>,
+    /// The child's stderr if `TerminalOptions::separate_stderr` routed it through a pipe.
+    pub termerr: Option<File>,
+}
+
+impl Terminal {
+    /// Moves the I/O of a blocking terminal to io_uring, e.g. one the child was attached to.
+    ///
+    /// Halves taken out of `terminal` before stay missing.
+    pub fn new(mut terminal: crate::Terminal) -> io::Result<Self> {
+        let termin = terminal.termin.take().map(TerminalIn::new).transpose()?;
+        let termout = terminal.termout.take().map(TerminalOut::new).transpose()?;
+        let termerr = terminal
+            .termerr
+            .take()
+            .map(|stderr| File::from_std(OwnedFd::from(stderr).into()));
+
+        Ok(Self {
+            inner: terminal,
+            termin,
+            termout,
+            termerr,
+        })
+    }
+
+    /// Returns the OS-assigned process identifier of the child.
+    pub fn id(&self) -> Option<u32> {
+        self.inner.id()
+    }
+
+    /// Returns a handle for resizing, signalling and waiting that can be moved to another task.
+    pub fn controller(&self) -> TerminalController {
+        self.inner.controller()
+    }
+
+    pub fn set_term_size(&mut self, new_size: TerminalSize) -> io::Result<()> {
+        self.inner.set_term_size(new_size)
+    }
+
+    /// Waits for the child to exit, see [`crate::Terminal::wait`].
+    pub async fn wait(&mut self) -> io::Result<ExitStatus> {
+        let controller = self.inner.controller();
+
+        spawn_blocking(move || controller.wait()).await?
+    }
+
+    /// Delivers EOF to the child, then kills it and waits until it exited.
+    pub async fn close(self) -> io::Result<()> {
+        let Self {
+            inner,
+            termin,
+            termout,
+            termerr,
+        } = self;
+        drop((termin, termout, termerr));
+
+        spawn_blocking(move || inner.close()).await?
+    }
+}
+
+pub trait CommandExt {
+    fn spawn_terminal(&mut self) -> io::Result<Terminal>;
+
+    /// Spawns the command in a new terminal configured by `options`.
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal>;
+}
+
+impl CommandExt for Command {
+    fn spawn_terminal(&mut self) -> io::Result<Terminal> {
+        self.spawn_terminal_with(&TerminalOptions::default())
+    }
+
+    fn spawn_terminal_with(&mut self, options: &TerminalOptions) -> io::Result<Terminal> {
+        Terminal::new(crate::CommandExt::spawn_terminal_with(self, options)?)
+    }
+}
+
+// tokio-uring files only expose their raw descriptor.
+fn borrow_fd(file: &File) -> BorrowedFd<'_> {
+    unsafe { BorrowedFd::borrow_raw(file.as_raw_fd()) }
+}
+
+/// The input of a terminal, dropping it delivers EOF to the child while it reads lines, see
+/// [`crate::TerminalIn`].
+pub struct TerminalIn {
+    file: File,
+    closed: bool,
+}
+
+impl TerminalIn {
+    /// Takes over `termin`, delivering EOF from now on regardless of its clones.
+    pub fn new(termin: crate::TerminalIn) -> io::Result<Self> {
+        Ok(Self {
+            file: File::from_std(termin.into_file()?),
+            closed: false,
+        })
+    }
+
+    /// Writes some of `buf`, returns the number of bytes the terminal took along with `buf`.
+    pub async fn write<T: BoundedBuf>(&self, buf: T) -> BufResult<usize, T> {
+        // A terminal is a stream, the offset is ignored.
+        self.file.write_at(buf, 0).submit().await
+    }
+
+    /// Writes all of `buf`, returning it once the terminal took everything.
+    pub async fn write_all<T: IoBuf>(&self, mut buf: T) -> BufResult<(), T> {
+        let len = buf.bytes_init();
+        let mut total = 0;
+
+        while total < len {
+            let (result, slice) = self.file.write_at(buf.slice(total..len), 0).submit().await;
+            buf = slice.into_inner();
+
+            match result {
+                Ok(0) => return (Err(io::ErrorKind::WriteZero.into()), buf),
+                Ok(written) => total += written,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => {}
+                Err(err) => return (Err(err), buf),
+            }
+        }
+
+        (Ok(()), buf)
+    }
+
+    /// Delivers EOF to the child, see [`crate::TerminalIn::close`].
+    pub fn close(mut self) -> io::Result<()> {
+        self.closed = true;
+
+        send_control_char(&borrow_fd(&self.file), ControlChar::Eof)
+    }
+
+    /// Sends the terminal's EOF character, see [`crate::TerminalIn::send_eof`].
+    pub async fn send_eof(&self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Eof).await
+    }
+
+    /// Sends the terminal's interrupt character, see [`crate::TerminalIn::send_interrupt`].
+    pub async fn send_interrupt(&self) -> io::Result<()> {
+        self.send_control_char(ControlChar::Interrupt).await
+    }
+
+    /// Writes `line` and a line terminator, see [`crate::TerminalIn::send_line`].
+    pub async fn send_line(&self, line: &str) -> io::Result<()> {
+        let mut data = line.as_bytes().to_vec();
+        data.extend_from_slice(line_terminator(&borrow_fd(&self.file))?);
+
+        self.write_all(data).await.0
+    }
+
+    async fn send_control_char(&self, char: ControlChar) -> io::Result<()> {
+        let bytes = control_char(&borrow_fd(&self.file), char)?;
+
+        self.write_all(bytes).await.0
+    }
+}
+
+impl Drop for TerminalIn {
+    fn drop(&mut self) {
+        if self.closed {
+            return;
+        }
+
+        send_eof_on_drop(&borrow_fd(&self.file));
+    }
+}
+
+/// The output of a terminal.
+pub struct TerminalOut {
+    file: File,
+    eio_as_eof: bool,
+}
+
+impl TerminalOut {
+    /// Takes over `termout`, output it buffered is lost.
+    pub fn new(termout: crate::TerminalOut) -> io::Result<Self> {
+        Ok(Self {
+            file: File::from_std(termout.into_file()?),
+            eio_as_eof: true,
+        })
+    }
+
+    /// Reads some output into `buf`, returns the number of bytes read along with `buf`.
+    ///
+    /// Returns 0 once the output ended.
+    pub async fn read<T: BoundedBufMut>(&self, buf: T) -> BufResult<usize, T> {
+        match self.file.read_at(buf, 0).await {
+            (Err(err), buf) if self.eio_as_eof && is_session_end(&err) => (Ok(0), buf),
+            result => result,
+        }
+    }
+
+    /// Controls whether the `EIO` Linux reports once the last slave is closed is turned into EOF.
+    ///
+    /// Enabled by default, see [`crate::TerminalOut::set_eio_as_eof`].
+    pub fn set_eio_as_eof(&mut self, enabled: bool) {
+        self.eio_as_eof = enabled;
+    }
+}
@@ -0,0 +1,126 @@
+use std::io;
+use std::str;
+
+const REPLACEMENT: char = '\u{FFFD}';
+
+/// Decodes output read in chunks as UTF-8, keeping characters split across chunks together.
+///
+/// A character cut off at the end of a chunk is held back until the next one completes it. Invalid
+/// sequences are replaced with U+FFFD unless lossy decoding is disabled, see
+/// [`set_lossy`](Self::set_lossy).
+///
+/// ```
+/// use pseudoterminal::Utf8Decoder;
+///
+/// let mut decoder = Utf8Decoder::new();
+///
+/// assert_eq!(decoder.decode(b"gr\xc3")?, "gr");
+/// assert_eq!(decoder.decode(b"\xbc\xc3\x9f")?, "üß");
+/// # std::io::Result::Ok(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Utf8Decoder {
+    // Bytes of a character that isn't complete yet, or that follow an error not reported yet.
+    pending: Vec<u8>,
+    lossy: bool,
+}
+
+impl Default for Utf8Decoder {
+    fn default() -> Self {
+        Self {
+            pending: Vec::new(),
+            lossy: true,
+        }
+    }
+}
+
+impl Utf8Decoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Controls whether invalid sequences are replaced with U+FFFD or fail with
+    /// [`io::ErrorKind::InvalidData`].
+    ///
+    /// Enabled by default. Without it the text before an invalid sequence is returned first, the
+    /// next call fails and skips the sequence, so decoding can go on after an error.
+    pub fn set_lossy(&mut self, enabled: bool) {
+        self.lossy = enabled;
+    }
+
+    /// Decodes `data` along with the start of a character held back from the last chunk.
+    ///
+    /// Returns an empty string if `data` only continues a character that is still incomplete.
+    pub fn decode(&mut self, data: &[u8]) -> io::Result<String> {
+        self.pending.extend_from_slice(data);
+
+        let mut text = String::new();
+        let mut pos = 0;
+
+        let result = loop {
+            let rest = &self.pending[pos..];
+            let err = match str::from_utf8(rest) {
+                Ok(valid) => {
+                    text.push_str(valid);
+                    pos = self.pending.len();
+                    break Ok(());
+                }
+                Err(err) => err,
+            };
+
+            let valid_up_to = err.valid_up_to();
+            text.push_str(str::from_utf8(&rest[..valid_up_to]).expect("checked to be valid"));
+            pos += valid_up_to;
+
+            match err.error_len() {
+                // The rest is the start of a character cut off at the end of the chunk.
+                None => break Ok(()),
+                Some(len) if self.lossy => {
+                    text.push(REPLACEMENT);
+                    pos += len;
+                }
+                Some(_) if !text.is_empty() => break Ok(()),
+                Some(len) => {
+                    pos += len;
+                    break Err(invalid_data());
+                }
+            }
+        };
+
+        self.pending.drain(..pos);
+        result.map(|()| text)
+    }
+
+    /// Decodes what was held back once the output ended.
+    ///
+    /// A character that never got completed is replaced with U+FFFD, or fails with
+    /// [`io::ErrorKind::InvalidData`] unless lossy. Like [`decode`](Self::decode), the text before
+    /// an error is returned first and the next call fails.
+    pub fn finish(&mut self) -> io::Result<String> {
+        let mut text = String::new();
+
+        while !self.pending.is_empty() {
+            let decoded = self.decode(&[])?;
+            if decoded.is_empty() {
+                // Only an incomplete character is left.
+                self.pending.clear();
+                if !self.lossy {
+                    return Err(invalid_data());
+                }
+                text.push(REPLACEMENT);
+            }
+            text.push_str(&decoded);
+
+            // Without lossy decoding, only an error can follow.
+            if !self.lossy && !self.pending.is_empty() {
+                break;
+            }
+        }
+
+        Ok(text)
+    }
+}
+
+fn invalid_data() -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, "the output is not valid UTF-8")
+}
@@ -0,0 +1,69 @@
+#![cfg(all(unix, feature = "async-io"))]
+
+use std::process::Command;
+
+use futures::executor::block_on;
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+use pseudoterminal::async_io::{CommandExt, Terminal};
+
+#[test]
+fn read_to_end() {
+    let mut terminal = Command::new("echo")
+        .arg("Hello, World!")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    block_on(async {
+        let mut output = String::new();
+        let termout = terminal.termout.as_mut().unwrap();
+        termout.read_to_string(&mut output).await.unwrap();
+        assert_eq!(output, "Hello, World!\r\n");
+
+        assert!(terminal.wait().await.unwrap().success());
+    });
+}
+
+#[test]
+fn write_and_read() {
+    let terminal = pseudoterminal::CommandExt::spawn_terminal(&mut Command::new("cat"))
+        .expect("should be spawnable");
+    let mut terminal = Terminal::new(terminal).unwrap();
+
+    block_on(async {
+        let mut termin = terminal.termin.take().unwrap();
+        termin.write_all(b"ping\n").await.unwrap();
+        termin.flush().await.unwrap();
+
+        // The terminal's echo, followed by cat's copy.
+        let termout = terminal.termout.as_mut().unwrap();
+        let mut output = Vec::new();
+        let mut buf = [0; 64];
+        while !output.ends_with(b"ping\r\nping\r\n") {
+            let read = termout.read(&mut buf).await.unwrap();
+            assert_ne!(read, 0, "the output ended early");
+            output.extend_from_slice(&buf[..read]);
+        }
+
+        // Dropping the input delivers EOF, so cat exits.
+        drop(termin);
+        assert!(terminal.wait().await.unwrap().success());
+    });
+}
+
+#[test]
+fn close_delivers_eof() {
+    let terminal = pseudoterminal::CommandExt::spawn_terminal(&mut Command::new("cat"))
+        .expect("should be spawnable");
+    let mut terminal = Terminal::new(terminal).unwrap();
+
+    block_on(async {
+        let mut termin = terminal.termin.take().unwrap();
+        termin.write_all(b"ping\n").await.unwrap();
+        AsyncWriteExt::close(&mut termin).await.unwrap();
+        AsyncWriteExt::close(&mut termin).await.unwrap();
+
+        // cat exits on the EOF while the input is still open.
+        assert!(terminal.wait().await.unwrap().success());
+        drop(termin);
+    });
+}
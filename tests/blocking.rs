@@ -3,7 +3,7 @@ use std::{
     process::Command,
 };
 
-use pseudoterminal::{CommandExt, TerminalSize};
+use pseudoterminal::{CommandExt, TerminalOptions, TerminalSize};
 
 #[test]
 fn read_from_term() {
@@ -98,3 +98,1934 @@ fn set_term_size() {
 
     terminal.close().expect("");
 }
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+#[test]
+fn foreground_cwd() {
+    let dir = std::env::temp_dir().canonicalize().unwrap();
+
+    let mut cmd = Command::new("sleep");
+    cmd.arg("5").current_dir(&dir);
+
+    let terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    assert_eq!(dir, terminal.foreground_cwd().unwrap());
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn spawn_login_terminal() {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg("echo \"[$0]\"");
+
+    let mut terminal = cmd.spawn_login_terminal().expect("should be spawnable");
+
+    let mut output = Vec::new();
+    let mut buf = [0; 64];
+    while !String::from_utf8_lossy(&output).contains("]\r\n") {
+        let n = terminal
+            .termout
+            .as_mut()
+            .unwrap()
+            .read(&mut buf)
+            .expect("terminal output was not readable");
+        output.extend_from_slice(&buf[..n]);
+    }
+
+    assert!(String::from_utf8_lossy(&output).contains("[-sh]"));
+
+    terminal.close().expect("");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn splice_to() {
+    use std::os::unix::net::UnixStream;
+
+    const TEST_STRING: &str = "Hello, World!";
+
+    let mut terminal = Command::new("echo")
+        .arg(TEST_STRING)
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let (sender, mut receiver) = UnixStream::pair().unwrap();
+
+    let transferred = terminal.splice_to(&sender).expect("should be spliceable");
+    drop(sender);
+
+    let mut output = String::new();
+    receiver.read_to_string(&mut output).unwrap();
+
+    assert_eq!(transferred, output.len() as u64);
+    assert_eq!(output, format!("{TEST_STRING}\r\n"));
+
+    terminal.close().expect("");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn splice_to_nonblocking() {
+    use std::os::unix::net::UnixStream;
+
+    let mut terminal = Command::new("head")
+        .args(["-c", "1000000", "/dev/zero"])
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let (sender, mut receiver) = UnixStream::pair().unwrap();
+    sender.set_nonblocking(true).unwrap();
+
+    // The reader falls behind, so the socket fills up while the output is spliced.
+    let reader = std::thread::spawn(move || {
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        let mut output = Vec::new();
+        receiver.read_to_end(&mut output).unwrap();
+        output.len()
+    });
+
+    let transferred = terminal.splice_to(&sender).expect("should be spliceable");
+    drop(sender);
+
+    assert_eq!(transferred, 1_000_000);
+    assert_eq!(reader.join().unwrap() as u64, transferred);
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn read_to_end_after_exit() {
+    const TEST_STRING: &str = "Hello, World!";
+
+    let mut terminal = Command::new("echo")
+        .arg(TEST_STRING)
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .expect("the end of the session should read as EOF");
+
+    assert_eq!(output, format!("{TEST_STRING}\r\n"));
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn wait_for_hangup() {
+    let terminal = Command::new("true")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    terminal
+        .wait_for_hangup()
+        .expect("the session should hang up once the child exits");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn spawn_terminal_with_options() {
+    let size = TerminalSize {
+        rows: 24,
+        columns: 100,
+    };
+
+    let mut terminal = Command::new("sh")
+        .arg("-c")
+        .arg("echo $TERM")
+        .spawn_terminal_with(
+            TerminalOptions::new()
+                .size(size)
+                .term("vt100")
+                .raw_mode(true),
+        )
+        .expect("should be spawnable");
+
+    assert_eq!(terminal.get_term_size().unwrap(), size);
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+
+    // Raw mode disables the translation of `\n` into `\r\n`.
+    assert_eq!(output, "vt100\n");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn wait() {
+    let mut terminal = Command::new("sh")
+        .arg("-c")
+        .arg("exit 3")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let status = terminal.wait().expect("the child should be waitable");
+
+    assert_eq!(status.code(), Some(3));
+}
+
+#[cfg(unix)]
+#[test]
+fn try_wait() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    assert!(terminal.try_wait().unwrap().is_none());
+
+    // `cat` exits on EOF, which is `^D` at the start of a line.
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"\x04")
+        .unwrap();
+    terminal.wait().unwrap();
+
+    assert!(terminal.try_wait().unwrap().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn is_alive() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let controller = terminal.controller();
+
+    assert!(terminal.is_alive());
+    assert!(controller.is_alive());
+
+    // A controller waiting for the child doesn't hold up the check.
+    let waiter = std::thread::spawn({
+        let controller = controller.clone();
+        move || controller.wait()
+    });
+    std::thread::sleep(std::time::Duration::from_millis(100));
+    let start = std::time::Instant::now();
+    assert!(terminal.is_alive());
+    assert!(start.elapsed() < std::time::Duration::from_millis(50));
+
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"\x04")
+        .unwrap();
+    waiter.join().unwrap().unwrap();
+
+    assert!(!terminal.is_alive());
+    assert!(!controller.is_alive());
+}
+
+#[cfg(unix)]
+#[test]
+fn exit_status() {
+    let terminal = Command::new("sh")
+        .args(["-c", "exit 4"])
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let controller = terminal.controller();
+    assert_eq!(terminal.exit_status(), None);
+
+    let status = controller.wait().unwrap();
+    assert_eq!(status.code(), Some(4));
+    assert_eq!(terminal.exit_status(), Some(status));
+    assert_eq!(controller.exit_status(), Some(status));
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupt_read() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let mut termout = terminal.termout.take().unwrap();
+    let interrupter = termout.read_interrupter().unwrap();
+
+    let reader = std::thread::spawn(move || termout.read(&mut [0; 16]));
+
+    std::thread::sleep(std::time::Duration::from_millis(50));
+    interrupter.interrupt_read().unwrap();
+
+    let err = reader
+        .join()
+        .unwrap()
+        .expect_err("the read should be interrupted");
+    assert!(err
+        .get_ref()
+        .is_some_and(|err| err.is::<pseudoterminal::ReadInterrupted>()));
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn read_timeout() {
+    use std::io;
+    use std::time::{Duration, Instant};
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let termout = terminal.termout.as_mut().unwrap();
+    assert_eq!(
+        termout
+            .set_read_timeout(Some(Duration::ZERO))
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidInput
+    );
+
+    let timeout = Duration::from_millis(100);
+    termout.set_read_timeout(Some(timeout)).unwrap();
+    assert_eq!(termout.read_timeout(), Some(timeout));
+
+    let start = Instant::now();
+    let err = termout.read(&mut [0; 16]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(start.elapsed() >= timeout);
+
+    let termin = terminal.termin.as_mut().unwrap();
+    termin.write_all(b"echo\n").unwrap();
+    termin.flush().unwrap();
+
+    let mut echoed = [0; 4];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut echoed)
+        .unwrap();
+    assert_eq!(&echoed, b"echo");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn write_timeout() {
+    use std::io;
+    use std::time::Duration;
+
+    // A canonical terminal drops input beyond a full line instead of blocking.
+    let mut terminal = Command::new("sleep")
+        .arg("10")
+        .spawn_terminal_with(TerminalOptions::new().raw_mode(true))
+        .expect("should be spawnable");
+
+    let termin = terminal.termin.as_mut().unwrap();
+    assert_eq!(
+        termin
+            .set_write_timeout(Some(Duration::ZERO))
+            .unwrap_err()
+            .kind(),
+        io::ErrorKind::InvalidInput
+    );
+
+    let timeout = Duration::from_millis(50);
+    termin.set_write_timeout(Some(timeout)).unwrap();
+    assert_eq!(termin.write_timeout(), Some(timeout));
+
+    // The child never reads, so the PTY fills up eventually.
+    let chunk = [b'x'; 4096];
+    let err = (0..1024)
+        .find_map(|_| termin.write(&chunk).err())
+        .expect("the terminal should stop taking input");
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert_eq!(
+        termin.try_write(&chunk).unwrap_err().kind(),
+        io::ErrorKind::WouldBlock
+    );
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn read_after_try_write() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let termin = terminal.termin.as_mut().unwrap();
+    assert_eq!(termin.try_write(b"echo\n").unwrap(), 5);
+
+    // The output shares the now non-blocking descriptor and still waits for data.
+    let mut echoed = [0; 4];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut echoed)
+        .unwrap();
+    assert_eq!(&echoed, b"echo");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn read_available() {
+    // Only the terminal's echo is output, the child reads nothing.
+    let mut terminal = Command::new("sleep")
+        .arg("10")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let mut output = Vec::new();
+    let termout = terminal.termout.as_mut().unwrap();
+    assert_eq!(termout.read_available(&mut output).unwrap(), 0);
+
+    terminal.termin.as_mut().unwrap().send_line("echo").unwrap();
+
+    // Spins until the echo arrived.
+    let termout = terminal.termout.as_mut().unwrap();
+    while !output.ends_with(b"echo\r\n") {
+        termout.read_available(&mut output).unwrap();
+    }
+    assert_eq!(termout.read_available(&mut output).unwrap(), 0);
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn readiness() {
+    use std::time::Duration;
+
+    // Without the terminal's echo, cat's copy is the only output.
+    let mut terminal = Command::new("cat")
+        .spawn_terminal_with(TerminalOptions::new().raw_mode(true))
+        .expect("should be spawnable");
+    let (mut termin, mut termout) = terminal.split().unwrap();
+
+    let timeout = Some(Duration::from_millis(50));
+    assert!(!termout.wait_readable(timeout).unwrap());
+    assert!(termin.wait_writable(timeout).unwrap());
+
+    termin.try_write(b"ping").unwrap();
+    assert!(termout.wait_readable(None).unwrap());
+
+    let mut output = [0; 4];
+    termout.read_exact(&mut output).unwrap();
+    assert_eq!(&output, b"ping");
+
+    terminal.unsplit(termin, termout);
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn buf_read() {
+    use std::io::BufRead;
+
+    let mut terminal = Command::new("printf")
+        .arg("$ first\\nsecond\\n")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let termout = terminal.termout.as_mut().unwrap();
+
+    // Peeking leaves the output to later reads.
+    let peeked = termout.fill_buf().unwrap().to_vec();
+    assert!(!peeked.is_empty());
+    assert_eq!(termout.fill_buf().unwrap(), peeked);
+
+    let mut line = String::new();
+    termout.read_line(&mut line).unwrap();
+    assert_eq!(line.trim_end(), "$ first");
+
+    let mut rest = String::new();
+    termout.read_to_string(&mut rest).unwrap();
+    assert!(rest.contains("second"));
+}
+
+#[cfg(unix)]
+#[test]
+fn lines() {
+    use pseudoterminal::LineEndings;
+
+    // The terminal turns each `\n` into `\r\n`.
+    let lines = |endings| {
+        let mut terminal = Command::new("printf")
+            .arg("one\\ntwo\\rthree\\npartial")
+            .spawn_terminal()
+            .expect("should be spawnable");
+        // All of the output is written once printf exited, it's then read up to the end.
+        assert!(terminal.wait().unwrap().success());
+
+        let termout = terminal.termout.as_mut().unwrap();
+        termout
+            .lines_with(endings)
+            .collect::<std::io::Result<Vec<_>>>()
+            .unwrap()
+    };
+
+    assert_eq!(
+        lines(LineEndings::Newline),
+        ["one", "two\rthree", "partial"]
+    );
+    assert_eq!(lines(LineEndings::Any), ["one", "two", "three", "partial"]);
+    assert_eq!(
+        lines(LineEndings::Raw),
+        ["one\r", "two\rthree\r", "partial"]
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn wait_with_output() {
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg("echo Hello; exit 3")
+        .spawn_terminal()
+        .expect("should be spawnable")
+        .wait_with_output()
+        .expect("the output should be collectable");
+
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(output.stdout, b"Hello\r\n");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn id() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let comm = std::fs::read_to_string(format!("/proc/{}/comm", terminal.id().unwrap())).unwrap();
+    assert_eq!(comm.trim(), "cat");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn into_parts() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let (handle, child, (termin, termout)) = terminal.into_parts();
+    let mut child = child.expect("the terminal should have a child");
+    assert!(termin.is_some() && termout.is_some());
+
+    let size = TerminalSize {
+        rows: 30,
+        columns: 90,
+    };
+    handle.set_term_size(size).unwrap();
+    assert_eq!(handle.get_term_size().unwrap(), size);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+fn wait_until_reaped(pid: u32) {
+    let deadline = std::time::Instant::now() + std::time::Duration::from_secs(5);
+    while std::path::Path::new(&format!("/proc/{pid}")).exists() {
+        assert!(
+            std::time::Instant::now() < deadline,
+            "the child should be reaped"
+        );
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn kill_on_drop() {
+    let terminal = Command::new("cat")
+        .spawn_terminal_with(TerminalOptions::new().kill_on_drop(true))
+        .expect("should be spawnable");
+
+    let pid = terminal.id().unwrap();
+    drop(terminal);
+
+    wait_until_reaped(pid);
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn drop_reaps_child() {
+    let terminal = Command::new("true")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let pid = terminal.id().unwrap();
+    drop(terminal);
+
+    wait_until_reaped(pid);
+}
+
+#[cfg(unix)]
+#[test]
+fn close_with() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::time::Duration;
+
+    use pseudoterminal::CloseBehavior;
+
+    let spawn = |script: &str| {
+        Command::new("sh")
+            .arg("-c")
+            .arg(script)
+            .spawn_terminal()
+            .expect("should be spawnable")
+    };
+
+    let status = spawn("sleep 10").close_with(CloseBehavior::Force).unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+
+    // `cat` only exits on EOF, which waiting mustn't deliver.
+    let terminal = spawn("cat");
+    let pid = terminal.id().unwrap();
+    let closing = std::thread::spawn(move || terminal.close_with(CloseBehavior::WaitOnly));
+    std::thread::sleep(Duration::from_millis(200));
+    assert!(!closing.is_finished());
+    assert_eq!(unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) }, 0);
+    let status = closing.join().unwrap().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+    let graceful = CloseBehavior::Graceful {
+        signal: libc::SIGTERM,
+        timeout: Duration::from_millis(200),
+    };
+
+    let status = spawn("sleep 10").close_with(graceful).unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+    let mut terminal = spawn("trap '' TERM; echo ready; while :; do sleep 0.01; done");
+    let mut ready = [0; 5];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut ready)
+        .unwrap();
+    let status = terminal.close_with(graceful).unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+}
+
+#[cfg(unix)]
+#[test]
+fn controller() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let controller = terminal.controller();
+    let size = TerminalSize {
+        rows: 12,
+        columns: 34,
+    };
+
+    let waiter = std::thread::spawn(move || {
+        controller.set_term_size(size).unwrap();
+        assert_eq!(controller.get_term_size().unwrap(), size);
+
+        controller.signal(libc::SIGTERM).unwrap();
+        controller.wait().unwrap()
+    });
+
+    let status = waiter.join().unwrap();
+    assert!(!status.success());
+
+    // The pid may be reused once the child was reaped.
+    assert_eq!(terminal.id(), None);
+    let controller = terminal.controller();
+    assert!(controller.signal(libc::SIGTERM).is_err());
+    assert!(controller.process_group_id().is_err());
+
+    drop(terminal);
+}
+
+#[cfg(unix)]
+#[test]
+fn controller_outlives_terminal() {
+    let terminal = Command::new("sh")
+        .args(["-c", "sleep 0.1; exit 3"])
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let controller = terminal.controller();
+    drop(terminal);
+    assert!(controller.is_alive());
+
+    // The child is left for the controller to wait for, instead of being reaped behind its back.
+    std::thread::sleep(std::time::Duration::from_millis(300));
+    assert_eq!(controller.wait().unwrap().code(), Some(3));
+    assert_eq!(controller.id(), None);
+    assert!(controller.signal(libc::SIGTERM).is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn split_and_unsplit() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let (mut termin, termout) = terminal.split().unwrap();
+    assert!(terminal.split().is_none());
+
+    termin.write_all(b"hi\n").unwrap();
+    terminal.unsplit(termin, termout);
+
+    let mut buf = [0; 4];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut buf)
+        .unwrap();
+    assert_eq!(&buf, b"hi\r\n");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn try_clone_halves() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let mut termin = terminal.termin.as_ref().unwrap().try_clone().unwrap();
+    let mut termout = terminal.termout.as_ref().unwrap().try_clone().unwrap();
+
+    // The originals go away, the clones keep working on their own descriptors.
+    terminal.split().unwrap();
+
+    termin.write_all(b"hi\n").unwrap();
+
+    let mut buf = [0; 4];
+    termout.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hi\r\n");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn from_raw_master() {
+    use std::ffi::CStr;
+    use std::fs::OpenOptions;
+    use std::os::fd::IntoRawFd;
+
+    use pseudoterminal::Terminal;
+
+    let master = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+    assert!(master >= 0);
+    assert_eq!(unsafe { libc::grantpt(master) }, 0);
+    assert_eq!(unsafe { libc::unlockpt(master) }, 0);
+    let slave_name = unsafe { CStr::from_ptr(libc::ptsname(master)) }
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    let mut terminal = unsafe { Terminal::from_raw_master(master) }.unwrap();
+    assert!(terminal.id().is_none());
+
+    let size = TerminalSize {
+        rows: 20,
+        columns: 70,
+    };
+    terminal.set_term_size(size).unwrap();
+    assert_eq!(terminal.get_term_size().unwrap(), size);
+
+    let mut slave = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(slave_name)
+        .unwrap();
+    slave.write_all(b"hi\n").unwrap();
+
+    let mut buf = [0; 4];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut buf)
+        .unwrap();
+    assert_eq!(&buf, b"hi\r\n");
+
+    terminal.close().expect("");
+
+    let file = std::fs::File::open("/dev/null").unwrap();
+    assert!(unsafe { Terminal::from_raw_master(file.into_raw_fd()) }.is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn pty_spawn() {
+    use pseudoterminal::Pty;
+
+    let size = TerminalSize {
+        rows: 10,
+        columns: 40,
+    };
+    let pty = Pty::open(size).expect("should be openable");
+    assert_eq!(pty.handle().get_term_size().unwrap(), size);
+
+    for word in ["one", "two"] {
+        let mut terminal = pty
+            .spawn(Command::new("echo").arg(word))
+            .expect("should be spawnable");
+
+        let mut output = String::new();
+        terminal
+            .termout
+            .as_mut()
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+        assert_eq!(output, format!("{word}\r\n"));
+
+        terminal.close().expect("");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn pty_pair() {
+    use std::process::Stdio;
+
+    use pseudoterminal::PtyPair;
+
+    let PtyPair { mut master, slave } =
+        PtyPair::open(&TerminalOptions::new()).expect("should be openable");
+
+    let size = TerminalSize {
+        rows: 8,
+        columns: 16,
+    };
+    master.set_term_size(size).unwrap();
+    assert_eq!(master.get_term_size().unwrap(), size);
+
+    let status = Command::new("echo")
+        .arg("hi")
+        .stdout(Stdio::from(slave))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let mut buf = [0; 4];
+    master.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"hi\r\n");
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn slave_names_on_threads() {
+    use std::os::fd::AsRawFd;
+
+    use pseudoterminal::PtyPair;
+
+    let threads: Vec<_> = (0..32)
+        .map(|_| {
+            std::thread::spawn(|| {
+                for _ in 0..500 {
+                    let PtyPair { master, slave } =
+                        PtyPair::open(&TerminalOptions::new()).expect("should be openable");
+
+                    let opened = std::fs::read_link(format!("/proc/self/fd/{}", slave.as_raw_fd()));
+                    assert_eq!(master.slave_name().unwrap(), opened.unwrap());
+                }
+            })
+        })
+        .collect();
+
+    for thread in threads {
+        thread.join().unwrap();
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn resize_keeps_pixel_size() {
+    use std::os::fd::AsRawFd;
+
+    use pseudoterminal::PtyPair;
+
+    let PtyPair {
+        master,
+        slave: _slave,
+    } = PtyPair::open(TerminalOptions::new().pixel_size(640, 480)).expect("should be openable");
+
+    master
+        .set_term_size(TerminalSize::new(8, 16).unwrap())
+        .unwrap();
+
+    let mut winsize: libc::winsize = unsafe { std::mem::zeroed() };
+    assert_eq!(
+        unsafe { libc::ioctl(master.as_raw_fd(), libc::TIOCGWINSZ, &mut winsize) },
+        0
+    );
+    assert_eq!((winsize.ws_row, winsize.ws_col), (8, 16));
+    assert_eq!((winsize.ws_xpixel, winsize.ws_ypixel), (640, 480));
+}
+
+#[cfg(unix)]
+#[test]
+fn respawn() {
+    let mut terminal = Command::new("echo")
+        .arg("one")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let first = terminal.id();
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "one\r\n");
+    assert!(terminal.wait().unwrap().success());
+
+    terminal
+        .respawn(Command::new("echo").arg("two"))
+        .expect("should be respawnable");
+    assert_ne!(terminal.id(), first);
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "two\r\n");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn reuse_command() {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "echo $$ $(ps -o sid= -p $$)"]);
+
+    let run = |terminal: &mut pseudoterminal::Terminal| {
+        let mut output = String::new();
+        terminal
+            .termout
+            .as_mut()
+            .unwrap()
+            .read_to_string(&mut output)
+            .unwrap();
+        assert!(terminal.wait().unwrap().success());
+
+        // The child leads its own session, so it got the terminal as its controlling one.
+        let ids: Vec<&str> = output.split_whitespace().collect();
+        assert_eq!(ids.len(), 2, "{output:?}");
+        assert_eq!(ids[0], ids[1], "{output:?}");
+    };
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+    run(&mut terminal);
+    terminal.respawn(&mut cmd).expect("should be respawnable");
+    run(&mut terminal);
+    run(&mut cmd.spawn_terminal().expect("should be spawnable again"));
+}
+
+#[cfg(unix)]
+#[test]
+fn reuse_command_with_other_options() {
+    let mut cmd = Command::new("sh");
+    cmd.args([
+        "-c",
+        "[ $$ = $(ps -o sid= -p $$) ] && echo leader; [ -t 5 ] && echo fd",
+    ]);
+
+    let output = |terminal: pseudoterminal::Terminal| {
+        String::from_utf8(terminal.wait_with_output().unwrap().stdout).unwrap()
+    };
+
+    let mut options = TerminalOptions::new();
+    let terminal = cmd.spawn_terminal_with(options.slave_fd(5)).unwrap();
+    assert_eq!(output(terminal), "leader\r\nfd\r\n");
+
+    // Nothing of the first spawn's setup is left on the command.
+    let mut options = TerminalOptions::new();
+    let terminal = cmd
+        .spawn_terminal_with(options.controlling_terminal(false))
+        .unwrap();
+    assert_eq!(output(terminal), "");
+}
+
+#[cfg(unix)]
+#[test]
+fn reuse_command_keeps_stdin() {
+    use std::process::Stdio;
+
+    let (mut reader, mut writer) = std::io::pipe().unwrap();
+    writer.write_all(b"hello").unwrap();
+    drop(writer);
+
+    let mut cmd = Command::new("head");
+    cmd.args(["-c", "2"])
+        .stdin(Stdio::from(reader.try_clone().unwrap()));
+
+    let mut options = TerminalOptions::new();
+    options.pty_stdin(false);
+    for expected in ["he", "ll"] {
+        let terminal = cmd.spawn_terminal_with(&options).unwrap();
+        let output = terminal.wait_with_output().unwrap().stdout;
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    let mut rest = String::new();
+    reader.read_to_string(&mut rest).unwrap();
+    assert_eq!(rest, "o");
+}
+
+#[cfg(unix)]
+#[test]
+fn slave_stdio_pipeline() {
+    use std::process::Stdio;
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let mut producer = Command::new("printf")
+        .arg("hello")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let status = Command::new("tr")
+        .args(["a-z", "A-Z"])
+        .stdin(producer.stdout.take().unwrap())
+        .stdout(terminal.slave_stdio().unwrap())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(producer.wait().unwrap().success());
+
+    let mut buf = [0; 5];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut buf)
+        .unwrap();
+    assert_eq!(&buf, b"HELLO");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn spawn_fn() {
+    use std::io::{self, IsTerminal};
+
+    use pseudoterminal::Terminal;
+
+    let size = TerminalSize {
+        rows: 12,
+        columns: 34,
+    };
+    let mut terminal = unsafe {
+        Terminal::spawn_fn(size, || {
+            if !io::stdout().is_terminal() {
+                return 1;
+            }
+            // `print!` would end up in the test harness' output capture.
+            io::stdout().write_all(b"forked").unwrap();
+            3
+        })
+    }
+    .expect("should be spawnable");
+    assert_eq!(terminal.get_term_size().unwrap(), size);
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "forked");
+    assert_eq!(terminal.wait().unwrap().code(), Some(3));
+}
+
+#[cfg(unix)]
+#[test]
+fn open_and_attach() {
+    use pseudoterminal::Terminal;
+
+    let mut terminal = Terminal::open(&TerminalOptions::new()).expect("should be openable");
+    assert_eq!(terminal.id(), None);
+    assert!(terminal.slave_name().unwrap().exists());
+
+    terminal
+        .attach(Command::new("echo").arg("late"))
+        .expect("should be attachable");
+    assert!(terminal.id().is_some());
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "late\r\n");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn separate_stderr() {
+    let mut terminal = Command::new("sh")
+        .args(["-c", "echo out; echo err >&2"])
+        .spawn_terminal_with(TerminalOptions::new().separate_stderr(true))
+        .expect("should be spawnable");
+
+    let mut stderr = String::new();
+    terminal
+        .termerr
+        .take()
+        .unwrap()
+        .read_to_string(&mut stderr)
+        .unwrap();
+    assert_eq!(stderr, "err\n");
+
+    let output = terminal.wait_with_output().unwrap();
+    assert_eq!(output.stdout, b"out\r\n");
+    assert!(output.stderr.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn wait_with_output_collects_separate_stderr() {
+    let output = Command::new("sh")
+        .args(["-c", "echo err >&2"])
+        .spawn_terminal_with(TerminalOptions::new().separate_stderr(true))
+        .expect("should be spawnable")
+        .wait_with_output()
+        .unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(output.stderr, b"err\n");
+}
+
+#[cfg(unix)]
+#[test]
+fn stdin_from_pipe() {
+    use std::process::Stdio;
+
+    let mut producer = Command::new("printf")
+        .arg("piped")
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "! [ -t 0 ] && [ -t 1 ] && cat"])
+        .stdin(producer.stdout.take().unwrap());
+    let mut terminal = cmd
+        .spawn_terminal_with(TerminalOptions::new().pty_stdin(false))
+        .expect("should be spawnable");
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "piped");
+    assert!(terminal.wait().unwrap().success());
+    assert!(producer.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn slave_fd() {
+    let mut terminal = Command::new("sh")
+        .args(["-c", "[ -t 3 ] && echo three >&3"])
+        .spawn_terminal_with(TerminalOptions::new().slave_fd(3))
+        .expect("should be spawnable");
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "three\r\n");
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn size_env() {
+    let size = TerminalSize {
+        rows: 12,
+        columns: 34,
+    };
+
+    let mut terminal = Command::new("sh")
+        .args(["-c", "echo $LINES $COLUMNS"])
+        .spawn_terminal_with(TerminalOptions::new().size(size).size_env(true))
+        .expect("should be spawnable");
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "12 34\r\n");
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn process_group_and_session() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let pid = terminal.id().unwrap();
+
+    // With a controlling terminal the child leads a session and process group of its own.
+    assert_eq!(terminal.process_group_id().unwrap(), pid);
+    assert_eq!(terminal.session_id().unwrap(), pid);
+    assert_eq!(terminal.controller().session_id().unwrap(), pid);
+
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn stream() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal_with(TerminalOptions::new().raw_mode(true))
+        .expect("should be spawnable");
+
+    let mut stream = terminal.stream().expect("both halves should be there");
+    assert!(terminal.stream().is_none());
+
+    stream.write_all(b"duplex").unwrap();
+    let mut buf = [0; 6];
+    stream.read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"duplex");
+
+    let (termin, termout) = stream.into_split();
+    terminal.unsplit(termin, termout);
+    terminal.close().expect("");
+}
+
+#[cfg(unix)]
+#[test]
+fn interact() {
+    // The test binary runs itself on a PTY to have a host terminal to interact through.
+    if std::env::var_os("PSEUDOTERMINAL_INTERACT_HOST").is_some() {
+        let stty = || Command::new("stty").arg("-g").output().unwrap().stdout;
+        let mode = stty();
+
+        let mut terminal = Command::new("sh")
+            .args(["-c", "stty size; echo ready; read line; echo \"got $line\""])
+            .spawn_terminal()
+            .expect("should be spawnable");
+        terminal.interact().unwrap();
+        assert!(terminal.wait().unwrap().success());
+
+        assert_eq!(stty(), mode);
+        std::io::stdout().write_all(b"restored\n").unwrap();
+        return;
+    }
+
+    let size = TerminalSize {
+        rows: 30,
+        columns: 100,
+    };
+    let mut terminal = Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", "interact", "--nocapture", "--test-threads=1"])
+        .env("PSEUDOTERMINAL_INTERACT_HOST", "1")
+        .spawn_terminal_with(TerminalOptions::new().size(size))
+        .expect("should be spawnable");
+    let termout = terminal.termout.as_mut().unwrap();
+
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    while !String::from_utf8_lossy(&output).contains("ready") {
+        let read = termout.read(&mut buf).unwrap();
+        assert_ne!(
+            read,
+            0,
+            "the host exited early: {}",
+            String::from_utf8_lossy(&output)
+        );
+        output.extend_from_slice(&buf[..read]);
+    }
+
+    // The host terminal is raw, the carriage return reaches the inner terminal untranslated.
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"hi\r")
+        .unwrap();
+    termout.read_to_end(&mut output).unwrap();
+
+    let output = String::from_utf8_lossy(&output);
+    assert!(output.contains("30 100"), "{output}");
+    assert!(output.contains("got hi"), "{output}");
+    assert!(output.contains("restored"), "{output}");
+    assert!(terminal.wait().unwrap().success());
+}
+
+// Runs the test binary's `test` on a PTY that serves it as the host terminal, with `env` set.
+#[cfg(unix)]
+fn spawn_host(test: &str, env: &str) -> pseudoterminal::Terminal {
+    Command::new(std::env::current_exe().unwrap())
+        .args(["--exact", test, "--nocapture", "--test-threads=1"])
+        .env(env, "1")
+        .spawn_terminal()
+        .expect("should be spawnable")
+}
+
+// Reads the host's output into `output` until it contains `needle`.
+#[cfg(unix)]
+fn read_until(host: &mut pseudoterminal::Terminal, output: &mut Vec<u8>, needle: &str) {
+    let termout = host.termout.as_mut().unwrap();
+
+    let mut buf = [0; 1024];
+    while !String::from_utf8_lossy(output).contains(needle) {
+        let read = termout.read(&mut buf).unwrap();
+        assert_ne!(
+            read,
+            0,
+            "the host exited early: {}",
+            String::from_utf8_lossy(output)
+        );
+        output.extend_from_slice(&buf[..read]);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn interact_interrupt_callback() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use pseudoterminal::{InteractOptions, InterruptPolicy};
+
+    if std::env::var_os("PSEUDOTERMINAL_INTERRUPT_HOST").is_some() {
+        // The child's terminal doesn't signal, so a forwarded Ctrl-C shows up in the line.
+        let mut terminal = Command::new("sh")
+            .args([
+                "-c",
+                "stty -isig; echo ready; read line; printf %s \"$line\" | od -An -c",
+            ])
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        // Only the second Ctrl-C is passed on.
+        let calls = Arc::new(AtomicUsize::new(0));
+        let seen = calls.clone();
+        let policy =
+            InterruptPolicy::Callback(Box::new(move || seen.fetch_add(1, Ordering::SeqCst) > 0));
+        terminal
+            .interact_with(InteractOptions::new().interrupt(policy))
+            .unwrap();
+        assert!(terminal.wait().unwrap().success());
+
+        println!("calls {}", calls.load(Ordering::SeqCst));
+        return;
+    }
+
+    let mut host = spawn_host(
+        "interact_interrupt_callback",
+        "PSEUDOTERMINAL_INTERRUPT_HOST",
+    );
+    let mut output = Vec::new();
+    read_until(&mut host, &mut output, "ready");
+
+    host.termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"a\x03\x03b\r")
+        .unwrap();
+    host.termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    let output = String::from_utf8_lossy(&output);
+    assert!(output.contains("a 003   b\r\n"), "{output}");
+    assert!(output.contains("calls 2"), "{output}");
+    assert!(host.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn interact_interrupt_local() {
+    use pseudoterminal::{InteractOptions, InterruptPolicy};
+
+    if std::env::var_os("PSEUDOTERMINAL_INTERRUPT_HOST").is_some() {
+        let mut terminal = Command::new("sh")
+            .args(["-c", "echo ready; read line"])
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        let err = terminal
+            .interact_with(InteractOptions::new().interrupt(InterruptPolicy::Local))
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+        assert!(terminal.is_alive());
+
+        terminal.close().unwrap();
+        println!("interrupted");
+        return;
+    }
+
+    let mut host = spawn_host("interact_interrupt_local", "PSEUDOTERMINAL_INTERRUPT_HOST");
+    let mut output = Vec::new();
+    read_until(&mut host, &mut output, "ready");
+
+    host.termin.as_mut().unwrap().write_all(b"\x03").unwrap();
+    read_until(&mut host, &mut output, "interrupted");
+    assert!(host.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn interact_suspend() {
+    use pseudoterminal::{InteractOptions, SuspendPolicy};
+
+    if std::env::var_os("PSEUDOTERMINAL_SUSPEND_HOST").is_some() {
+        // A single byte only reaches the child without a newline if the host is in raw mode.
+        let mut terminal = Command::new("sh")
+            .args([
+                "-c",
+                "stty raw -echo; echo ready; dd bs=1 count=1 2>/dev/null | od -An -c",
+            ])
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        terminal
+            .interact_with(InteractOptions::new().suspend(SuspendPolicy::Suspend))
+            .unwrap();
+        assert!(terminal.wait().unwrap().success());
+
+        println!("done");
+        return;
+    }
+
+    // A shell with job control reports the mode while the host is stopped and continues it.
+    let mut host = Command::new("sh")
+        .args([
+            "-c",
+            "set -m; stty -g; \"$0\" --exact interact_suspend --nocapture --test-threads=1; \
+             echo stopped; stty -g; fg",
+        ])
+        .arg(std::env::current_exe().unwrap())
+        .env("PSEUDOTERMINAL_SUSPEND_HOST", "1")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let mut output = Vec::new();
+    read_until(&mut host, &mut output, "ready");
+
+    host.termin.as_mut().unwrap().write_all(b"\x1a").unwrap();
+    read_until(&mut host, &mut output, "stopped");
+    read_until(&mut host, &mut output, "interact_suspend");
+
+    // `fg` printed the job before continuing it.
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    host.termin.as_mut().unwrap().write_all(b"x").unwrap();
+    host.termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    let output = String::from_utf8_lossy(&output);
+    let modes: Vec<_> = output
+        .lines()
+        .filter(|line| line.matches(':').count() > 10)
+        .collect();
+    assert_eq!(modes.len(), 2, "{output}");
+    assert_eq!(modes[0], modes[1], "{output}");
+    assert!(output.contains("   x"), "{output}");
+    assert!(output.contains("done"), "{output}");
+    assert!(host.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn interact_restores_after_panic() {
+    use pseudoterminal::{InteractOptions, InterruptPolicy};
+
+    if std::env::var_os("PSEUDOTERMINAL_RESTORE_HOST").is_some() {
+        let stty = || Command::new("stty").arg("-g").output().unwrap().stdout;
+        let mode = stty();
+
+        let mut terminal = Command::new("sh")
+            .args(["-c", "echo ready; read line"])
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        let policy = InterruptPolicy::Callback(Box::new(|| panic!("the callback panicked")));
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            terminal.interact_with(InteractOptions::new().interrupt(policy))
+        }));
+        assert!(result.is_err());
+        assert_eq!(stty(), mode);
+
+        terminal.close().unwrap();
+        println!("restored");
+        return;
+    }
+
+    let mut host = spawn_host(
+        "interact_restores_after_panic",
+        "PSEUDOTERMINAL_RESTORE_HOST",
+    );
+    let mut output = Vec::new();
+    read_until(&mut host, &mut output, "ready");
+
+    host.termin.as_mut().unwrap().write_all(b"\x03").unwrap();
+    read_until(&mut host, &mut output, "restored");
+    assert!(host.wait().unwrap().success());
+}
+
+#[cfg(all(unix, feature = "title"))]
+#[test]
+fn interact_restores_after_output_panic() {
+    if std::env::var_os("PSEUDOTERMINAL_RESTORE_HOST").is_some() {
+        let stty = || Command::new("stty").arg("-g").output().unwrap().stdout;
+        let mode = stty();
+
+        let mut terminal = Command::new("sh")
+            .args([
+                "-c",
+                "echo ready; read line; printf '\\033]2;title\\007'; read line",
+            ])
+            .spawn_terminal()
+            .expect("should be spawnable");
+        terminal.on_title_change(|_| panic!("the callback panicked"));
+
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| terminal.interact()));
+        assert!(result.is_err());
+        assert_eq!(stty(), mode);
+
+        terminal.close().unwrap();
+        println!("restored");
+        return;
+    }
+
+    let mut host = spawn_host(
+        "interact_restores_after_output_panic",
+        "PSEUDOTERMINAL_RESTORE_HOST",
+    );
+    let mut output = Vec::new();
+    read_until(&mut host, &mut output, "ready");
+
+    host.termin.as_mut().unwrap().write_all(b"hi\r").unwrap();
+    read_until(&mut host, &mut output, "restored");
+    assert!(host.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn interact_restores_after_child_crash() {
+    use std::os::unix::process::ExitStatusExt;
+
+    if std::env::var_os("PSEUDOTERMINAL_RESTORE_HOST").is_some() {
+        let stty = || Command::new("stty").arg("-g").output().unwrap().stdout;
+        let mode = stty();
+
+        let mut terminal = Command::new("sh")
+            .args(["-c", "echo ready; read line; kill -KILL $$"])
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        terminal.interact().unwrap();
+        assert_eq!(terminal.wait().unwrap().signal(), Some(libc::SIGKILL));
+        assert_eq!(stty(), mode);
+
+        println!("restored");
+        return;
+    }
+
+    let mut host = spawn_host(
+        "interact_restores_after_child_crash",
+        "PSEUDOTERMINAL_RESTORE_HOST",
+    );
+    let mut output = Vec::new();
+    read_until(&mut host, &mut output, "ready");
+
+    host.termin.as_mut().unwrap().write_all(b"hi\r").unwrap();
+    read_until(&mut host, &mut output, "restored");
+    assert!(host.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn interact_restores_on_sigterm() {
+    if std::env::var_os("PSEUDOTERMINAL_RESTORE_HOST").is_some() {
+        let mut terminal = Command::new("sh")
+            .args(["-c", "echo ready; read line"])
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        println!("pid {}", std::process::id());
+        terminal.interact().unwrap();
+        unreachable!("the host should have been terminated");
+    }
+
+    // The shell reports the mode before the host started and after it was terminated.
+    let mut host = Command::new("sh")
+        .args([
+            "-c",
+            "stty -g; \"$0\" --exact interact_restores_on_sigterm --nocapture \
+             --test-threads=1; echo \"status $?\"; stty -g",
+        ])
+        .arg(std::env::current_exe().unwrap())
+        .env("PSEUDOTERMINAL_RESTORE_HOST", "1")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let mut output = Vec::new();
+    read_until(&mut host, &mut output, "ready");
+
+    let pid = String::from_utf8_lossy(&output)
+        .split("pid ")
+        .nth(1)
+        .and_then(|rest| rest.split_whitespace().next()?.parse().ok())
+        .expect("the host should report its pid");
+    assert_eq!(unsafe { libc::kill(pid, libc::SIGTERM) }, 0);
+    host.termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    let output = String::from_utf8_lossy(&output);
+    let modes: Vec<_> = output
+        .lines()
+        .filter(|line| line.matches(':').count() > 10)
+        .collect();
+    assert_eq!(modes.len(), 2, "{output}");
+    assert_eq!(modes[0], modes[1], "{output}");
+    assert!(
+        output.contains(&format!("status {}", 128 + libc::SIGTERM)),
+        "{output}"
+    );
+    assert!(host.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn follow_host_resize() {
+    use std::time::{Duration, Instant};
+
+    // The test binary runs itself on a PTY to have a host terminal to follow.
+    if std::env::var_os("PSEUDOTERMINAL_RESIZE_HOST").is_some() {
+        let mut terminal = Command::new("cat")
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        let follower = terminal.follow_host_resize().unwrap();
+        assert_eq!(
+            terminal.get_term_size().unwrap(),
+            TerminalSize {
+                rows: 30,
+                columns: 100
+            }
+        );
+        std::io::stdout().write_all(b"ready\n").unwrap();
+
+        let resized = TerminalSize {
+            rows: 40,
+            columns: 120,
+        };
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while terminal.get_term_size().unwrap() != resized {
+            assert!(Instant::now() < deadline, "the resize was not followed");
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        drop(follower);
+        terminal.close().expect("");
+        std::io::stdout().write_all(b"followed\n").unwrap();
+        return;
+    }
+
+    let size = TerminalSize {
+        rows: 30,
+        columns: 100,
+    };
+    let mut terminal = Command::new(std::env::current_exe().unwrap())
+        .args([
+            "--exact",
+            "follow_host_resize",
+            "--nocapture",
+            "--test-threads=1",
+        ])
+        .env("PSEUDOTERMINAL_RESIZE_HOST", "1")
+        .spawn_terminal_with(TerminalOptions::new().size(size))
+        .expect("should be spawnable");
+    let termout = terminal.termout.as_mut().unwrap();
+
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    while !String::from_utf8_lossy(&output).contains("ready") {
+        let read = termout.read(&mut buf).unwrap();
+        assert_ne!(
+            read,
+            0,
+            "the host exited early: {}",
+            String::from_utf8_lossy(&output)
+        );
+        output.extend_from_slice(&buf[..read]);
+    }
+
+    terminal
+        .set_term_size(TerminalSize {
+            rows: 40,
+            columns: 120,
+        })
+        .unwrap();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    let output = String::from_utf8_lossy(&output);
+    assert!(output.contains("followed"), "{output}");
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn close_input() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"hello\n")
+        .unwrap();
+    terminal.close_input().unwrap();
+    assert!(terminal.close_input().is_err());
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert_eq!(output, "hello\r\nhello\r\n");
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn drop_input_delivers_eof() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    drop(terminal.termin.take());
+
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn drop_input_spares_raw_mode() {
+    // In raw mode with a timeout, `dd` returns what arrives within a second, nothing otherwise.
+    let mut terminal = Command::new("sh")
+        .args([
+            "-c",
+            "stty raw -echo min 0 time 10; printf ready; dd bs=1 count=1 2>/dev/null | od -An -c; \
+             printf done",
+        ])
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let termout = terminal.termout.as_mut().unwrap();
+    let mut output = Vec::new();
+    while !output.ends_with(b"ready") {
+        termout.wait_readable(None).unwrap();
+        termout.read_available(&mut output).unwrap();
+    }
+    drop(terminal.termin.take());
+
+    let mut output = String::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_string(&mut output)
+        .unwrap();
+    assert!(!output.contains("004"), "{output:?}");
+    assert!(output.ends_with("done"), "{output:?}");
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn send_eof_with_custom_character() {
+    let mut terminal = Command::new("sh")
+        .args(["-c", "stty eof ^B; echo ready; cat; echo done"])
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let mut output = Vec::new();
+    let mut buf = [0; 64];
+    while !String::from_utf8_lossy(&output).contains("ready") {
+        let read = terminal.termout.as_mut().unwrap().read(&mut buf).unwrap();
+        output.extend_from_slice(&buf[..read]);
+    }
+
+    terminal.termin.as_mut().unwrap().send_eof().unwrap();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    assert!(String::from_utf8_lossy(&output).contains("done"));
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[test]
+fn send_interrupt() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut terminal = Command::new("sleep")
+        .arg("10")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    terminal.termin.as_mut().unwrap().send_interrupt().unwrap();
+
+    assert_eq!(terminal.wait().unwrap().signal(), Some(libc::SIGINT));
+}
+
+#[cfg(unix)]
+#[test]
+fn send_line() {
+    // Once carriage returns aren't translated anymore, only a newline ends the line.
+    let mut terminal = Command::new("sh")
+        .args([
+            "-c",
+            "read a; stty -icrnl; echo ready; read b; echo \"got $a $b\"",
+        ])
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let termout = terminal.termout.as_mut().unwrap();
+
+    terminal.termin.as_mut().unwrap().send_line("one").unwrap();
+    let mut output = Vec::new();
+    let mut buf = [0; 64];
+    while !String::from_utf8_lossy(&output).contains("ready") {
+        let read = termout.read(&mut buf).unwrap();
+        output.extend_from_slice(&buf[..read]);
+    }
+    terminal.termin.as_mut().unwrap().send_line("two").unwrap();
+
+    termout.read_to_end(&mut output).unwrap();
+    let output = String::from_utf8_lossy(&output);
+    assert!(output.contains("got one two"), "{output}");
+
+    // A raw terminal gets the carriage return of the Enter key.
+    let mut terminal = Command::new("cat")
+        .spawn_terminal_with(TerminalOptions::new().raw_mode(true))
+        .expect("should be spawnable");
+
+    terminal.termin.as_mut().unwrap().send_line("raw").unwrap();
+    let mut buf = [0; 4];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut buf)
+        .unwrap();
+    assert_eq!(&buf, b"raw\r");
+
+    terminal.close().expect("");
+}
+
+#[cfg(all(unix, feature = "channels"))]
+#[test]
+fn into_channels() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (sender, receiver, worker) = terminal.into_channels().unwrap();
+
+    sender.send(b"ping\n".to_vec()).unwrap();
+
+    // The terminal's echo, followed by cat's copy.
+    let mut output = Vec::new();
+    while !output.ends_with(b"ping\r\nping\r\n") {
+        let chunk = receiver.recv().expect("the output ended early");
+        output.extend_from_slice(&chunk);
+    }
+
+    // Dropping the sender delivers EOF, so cat exits.
+    drop(sender);
+    assert!(receiver.recv().is_err());
+    assert!(worker.join().unwrap().unwrap().success());
+}
+
+#[cfg(all(unix, feature = "utf8"))]
+#[test]
+fn utf8_chunks() {
+    let mut terminal = Command::new("printf")
+        .arg(r"gr\303\274\303\237e \346\227\245\346\234\254")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let text = terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .utf8_chunks()
+        .collect::<std::io::Result<String>>()
+        .unwrap();
+
+    assert_eq!(text, "grüße 日本");
+}
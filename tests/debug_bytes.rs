@@ -0,0 +1,16 @@
+#![cfg(feature = "debug-bytes")]
+
+use pseudoterminal::DebugBytes;
+
+#[test]
+fn renders_control_characters() {
+    assert_eq!(
+        DebugBytes(b"\x1b[2J\x03ls -l\r\n\x7f\xff").to_string(),
+        "ESC[2J^Cls -l\\r\\n^?\\xff"
+    );
+}
+
+#[test]
+fn debug_is_quoted() {
+    assert_eq!(format!("{:?}", DebugBytes(b"\x04")), "\"^D\"");
+}
@@ -0,0 +1,150 @@
+#![cfg(target_os = "linux")]
+
+use std::io::{self, Read};
+use std::os::unix::process::CommandExt as _;
+use std::process::Command;
+
+use pseudoterminal::CommandExt;
+
+fn open_fds() -> usize {
+    std::fs::read_dir("/proc/self/fd").unwrap().count()
+}
+
+// All stages run in a single test so that no other test opens descriptors concurrently.
+#[test]
+fn failed_spawns_release_descriptors() {
+    let before = open_fds();
+
+    let mut cmd = Command::new("/nonexistent/program");
+    assert!(cmd.spawn_terminal().is_err());
+    drop(cmd);
+    assert_eq!(before, open_fds(), "descriptors leaked after a failed exec");
+
+    let mut cmd = Command::new("true");
+    unsafe {
+        cmd.pre_exec(|| Err(io::ErrorKind::PermissionDenied.into()));
+    }
+    assert!(cmd.spawn_terminal().is_err());
+    assert_eq!(
+        before,
+        open_fds(),
+        "descriptors leaked after a failing pre_exec hook"
+    );
+    drop(cmd);
+
+    let mut cmd = Command::new("true");
+    unsafe {
+        cmd.pre_exec(|| panic!("pre_exec hook panicked"));
+    }
+    // std aborts the forked child on panic, which leaves a terminal whose child already died.
+    match cmd.spawn_terminal() {
+        Ok(mut terminal) => {
+            let mut buf = Vec::new();
+            let _ = terminal.termout.as_mut().unwrap().read_to_end(&mut buf);
+            assert!(!terminal.wait().unwrap().success());
+            drop(terminal);
+            assert_eq!(
+                before,
+                open_fds(),
+                "descriptors leaked after a panicking pre_exec hook"
+            );
+        }
+        Err(_) => assert_eq!(
+            before,
+            open_fds(),
+            "descriptors leaked after a pre_exec hook panicked the spawn"
+        ),
+    }
+    drop(cmd);
+
+    // Each limit runs a different step of the spawn out of descriptors, the command must not keep
+    // any of the slaves handed to it until then.
+    let mut cmd = Command::new("true");
+    for spare in 0..8 {
+        let spawned = with_spare_fds(before + spare, || cmd.spawn_terminal());
+        if let Ok(terminal) = spawned {
+            terminal.close().expect("");
+        }
+        assert_eq!(
+            before,
+            open_fds(),
+            "descriptors leaked with {spare} descriptors to spare"
+        );
+    }
+    drop(cmd);
+
+    let mut cmd = Command::new("true");
+    let terminal = cmd.spawn_terminal().expect("should be spawnable");
+    terminal.close().expect("");
+    assert_eq!(before, open_fds(), "descriptors leaked after closing");
+
+    #[cfg(feature = "non-blocking")]
+    non_blocking_spawns_release_descriptors();
+}
+
+#[cfg(feature = "non-blocking")]
+fn non_blocking_spawns_release_descriptors() {
+    use pseudoterminal::non_blocking::CommandExt;
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        // The first spawn sets up the runtime's child signal handling, which stays open.
+        let terminal = Command::new("true").spawn_terminal().unwrap();
+        terminal.close().await.expect("");
+        let before = open_fds();
+
+        assert!(Command::new("/nonexistent/program")
+            .spawn_terminal()
+            .is_err());
+        assert_eq!(before, open_fds(), "descriptors leaked after a failed exec");
+
+        let mut cmd = Command::new("true");
+        unsafe {
+            cmd.pre_exec(|| Err(io::ErrorKind::PermissionDenied.into()));
+        }
+        assert!(cmd.spawn_terminal().is_err());
+        assert_eq!(
+            before,
+            open_fds(),
+            "descriptors leaked after a failing pre_exec hook"
+        );
+
+        for spare in 0..8 {
+            let spawned = with_spare_fds(before + spare, || Command::new("true").spawn_terminal());
+            if let Ok(terminal) = spawned {
+                terminal.close().await.expect("");
+            }
+            assert_eq!(
+                before,
+                open_fds(),
+                "descriptors leaked with {spare} descriptors to spare"
+            );
+        }
+
+        let terminal = Command::new("true").spawn_terminal().unwrap();
+        terminal.close().await.expect("");
+        assert_eq!(before, open_fds(), "descriptors leaked after closing");
+    });
+}
+
+// Runs `f` with descriptors limited to `limit`.
+fn with_spare_fds<T>(limit: usize, f: impl FnOnce() -> T) -> T {
+    let mut saved = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    assert_eq!(
+        unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut saved) },
+        0
+    );
+
+    let lowered = libc::rlimit {
+        rlim_cur: limit as libc::rlim_t,
+        ..saved
+    };
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &lowered) }, 0);
+    let result = f();
+    assert_eq!(unsafe { libc::setrlimit(libc::RLIMIT_NOFILE, &saved) }, 0);
+
+    result
+}
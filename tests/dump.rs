@@ -0,0 +1,51 @@
+#![cfg(feature = "dump")]
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::process::Command;
+
+use pseudoterminal::dump::{decode, Direction, TrafficDump};
+use pseudoterminal::CommandExt;
+
+#[cfg(unix)]
+#[test]
+fn dump_round_trip() {
+    let path = std::env::temp_dir().join(format!("pseudoterminal-dump-{}", std::process::id()));
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    terminal.set_traffic_dump(TrafficDump::create(&path).unwrap());
+
+    const TEST_STRING: &str = "Hello, World!\r\n";
+
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(TEST_STRING.as_bytes())
+        .unwrap();
+
+    let mut buf = vec![0; TEST_STRING.len()];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut buf)
+        .expect("terminal output was not readable");
+
+    terminal.close().expect("");
+
+    let records = decode(File::open(&path).unwrap())
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0].direction, Direction::Input);
+    assert_eq!(records[0].data, TEST_STRING.as_bytes());
+    assert_eq!(records[1].direction, Direction::Output);
+    assert_eq!(records[1].data, TEST_STRING.as_bytes());
+    assert!(records[0].timestamp <= records[1].timestamp);
+}
@@ -0,0 +1,86 @@
+#![cfg(unix)]
+
+use std::error::Error;
+use std::io;
+use std::process::Command;
+
+use pseudoterminal::{CommandExt, PtyError, SizeError, TerminalOptions, TerminalSize};
+
+#[test]
+fn spawn_error() {
+    let err = Command::new("/nonexistent/program")
+        .spawn_terminal()
+        .map(drop)
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+    assert!(err
+        .to_string()
+        .starts_with("failed to spawn the child: running \"/nonexistent/program\": "));
+
+    let err = PtyError::from(err);
+    assert!(matches!(err, PtyError::Spawn(_)));
+    assert_eq!(err.kind(), io::ErrorKind::NotFound);
+}
+
+#[test]
+fn resize_error() {
+    let mut terminal = Command::new("true").spawn_terminal().unwrap();
+
+    let empty = TerminalSize {
+        rows: 0,
+        columns: 0,
+    };
+    let err = PtyError::from(terminal.set_term_size(empty).unwrap_err());
+    assert!(matches!(err, PtyError::Resize(_)));
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+
+    let source = err.source().unwrap().downcast_ref::<io::Error>().unwrap();
+    assert!(source.get_ref().is_some_and(|err| err.is::<SizeError>()));
+
+    let err = PtyError::from(terminal.controller().set_term_size(empty).unwrap_err());
+    assert!(matches!(err, PtyError::Resize(_)));
+
+    terminal.wait().unwrap();
+}
+
+#[test]
+fn open_error() {
+    let mut options = TerminalOptions::new();
+    options.size(TerminalSize {
+        rows: 0,
+        columns: 0,
+    });
+
+    let err = Command::new("true")
+        .spawn_terminal_with(&options)
+        .map(drop)
+        .unwrap_err();
+    let err = PtyError::from(err);
+    assert!(matches!(err, PtyError::Open(_)));
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+#[test]
+fn not_a_pty() {
+    use std::os::fd::IntoRawFd;
+
+    use pseudoterminal::Terminal;
+
+    let fd = std::fs::File::open("/dev/null").unwrap().into_raw_fd();
+    let err = unsafe { Terminal::from_raw_master(fd) }
+        .map(drop)
+        .unwrap_err();
+    assert!(matches!(PtyError::from(err), PtyError::Open(err)
+        if err.to_string().starts_with(&format!("fd {fd} is not a PTY master: "))));
+}
+
+#[test]
+fn other_errors() {
+    let err = PtyError::from(io::Error::other("unrelated"));
+    assert!(matches!(err, PtyError::Io(_)));
+    assert_eq!(err.to_string(), "unrelated");
+
+    // Converting back doesn't wrap plain errors a second time.
+    let err = io::Error::from(err);
+    assert!(err.get_ref().is_some_and(|err| !err.is::<PtyError>()));
+}
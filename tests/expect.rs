@@ -0,0 +1,236 @@
+#![cfg(all(unix, feature = "expect"))]
+
+use std::io;
+use std::process::Command;
+
+use pseudoterminal::expect::{ReplSession, Session};
+use pseudoterminal::CommandExt;
+
+fn greeter() -> Session {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "printf 'name? '; read name; echo \"hello $name\""]);
+
+    Session::from(cmd.spawn_terminal().expect("should be spawnable"))
+}
+
+#[test]
+fn exp_string() {
+    let mut session = greeter();
+
+    assert_eq!(session.exp_string("name? ").unwrap(), "");
+    session.send_line("bob").unwrap();
+
+    // The terminal's echo comes before the answer.
+    assert_eq!(session.exp_string("hello").unwrap(), "bob\r\n");
+    assert_eq!(session.exp_eof().unwrap(), " bob\r\n");
+    assert!(session.get_mut().wait().unwrap().success());
+}
+
+#[test]
+fn exp_regex() {
+    let mut session = greeter();
+
+    session.exp_string("name? ").unwrap();
+    session.send_line("alice").unwrap();
+
+    let (before, matched) = session.exp_regex(r"hello \w+").unwrap();
+    assert_eq!(before, "alice\r\n");
+    assert_eq!(matched, "hello alice");
+    assert_eq!(session.buffer(), b"\r\n");
+
+    let err = session.exp_string("never").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+}
+
+#[test]
+fn invalid_regex() {
+    let err = greeter().exp_regex("(").unwrap_err();
+
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+}
+
+fn shell() -> ReplSession {
+    let mut cmd = Command::new("sh");
+    cmd.env("PS1", "PROMPT> ");
+
+    let session = Session::from(cmd.spawn_terminal().expect("should be spawnable"));
+    ReplSession::new(session, "PROMPT> ").unwrap()
+}
+
+#[test]
+fn execute() {
+    let mut repl = shell();
+
+    assert_eq!(repl.execute("echo hi").unwrap(), "hi\n");
+    assert_eq!(repl.execute("false; echo $?").unwrap(), "1\n");
+    assert_eq!(repl.execute("true").unwrap(), "");
+}
+
+#[test]
+fn resync() {
+    let mut repl = shell();
+
+    // cat never returns to the prompt on its own.
+    repl.get_mut().send_line("cat").unwrap();
+    repl.resync().unwrap();
+
+    assert_eq!(repl.execute("echo ok").unwrap(), "ok\n");
+}
+
+#[test]
+fn exp_shell_event() {
+    use pseudoterminal::shell_integration::ShellEvent;
+
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", r"printf 'output\033]133;D;3\007\033]133;A\007$ '"]);
+    let mut session = Session::from(cmd.spawn_terminal().expect("should be spawnable"));
+
+    let (before, event) = session.exp_shell_event().unwrap();
+    assert_eq!(before, "output");
+    assert_eq!(event, ShellEvent::CommandFinished { exit_code: Some(3) });
+
+    assert_eq!(
+        session.exp_shell_event().unwrap(),
+        (String::new(), ShellEvent::PromptStart)
+    );
+    assert_eq!(session.exp_eof().unwrap(), "$ ");
+}
+
+#[test]
+fn run_script() {
+    use pseudoterminal::expect::{Script, ScriptStep};
+
+    let script = Script {
+        steps: vec![
+            ScriptStep::Expect("name? ".into()),
+            ScriptStep::Send(b"bob\r".to_vec()),
+            ScriptStep::Expect("hello bob".into()),
+            ScriptStep::Eof,
+        ],
+    };
+    assert_eq!(
+        script.to_string(),
+        "expect -exact \"name? \"\nsend -- \"bob\\r\"\nexpect -exact \"hello bob\"\nexpect eof\n"
+    );
+
+    let mut session = greeter();
+    script.run(&mut session).unwrap();
+    assert!(session.get_mut().wait().unwrap().success());
+}
+
+#[test]
+fn record_script() {
+    use pseudoterminal::expect::Script;
+
+    // The test binary runs itself on a PTY to have a host terminal to interact through.
+    if std::env::var_os("PSEUDOTERMINAL_INTERACT_HOST").is_some() {
+        let mut terminal = greeter().into_inner();
+        let script = Script::record(&mut terminal).unwrap();
+        assert!(terminal.wait().unwrap().success());
+
+        println!("recorded:\n{script}");
+        return;
+    }
+
+    let mut cmd = Command::new(std::env::current_exe().unwrap());
+    cmd.args([
+        "--exact",
+        "record_script",
+        "--nocapture",
+        "--test-threads=1",
+    ])
+    .env("PSEUDOTERMINAL_INTERACT_HOST", "1");
+    let mut host = Session::from(cmd.spawn_terminal().unwrap());
+
+    host.exp_string("name? ").unwrap();
+    host.send("bob\r").unwrap();
+
+    let recorded = host.exp_eof().unwrap();
+    let script = recorded.split("recorded:\r\n").nth(1).expect(&recorded);
+    assert!(
+        script.starts_with("expect -exact \"name? \"\r\nsend -- \"bob\\r\"\r\nexpect eof\r\n"),
+        "{script}"
+    );
+}
+
+#[test]
+fn chat() {
+    use pseudoterminal::expect::Chat;
+
+    let mut cmd = Command::new("sh");
+    cmd.args([
+        "-c",
+        "printf 'name? '; read name; printf 'age? '; read age; echo \"$name is $age\"",
+    ]);
+    let mut session = Session::from(cmd.spawn_terminal().expect("should be spawnable"));
+
+    Chat::new()
+        .step(r"name\? ", "bob")
+        .unwrap()
+        .step(r"age\? ", "42")
+        .unwrap()
+        .run(&mut session)
+        .unwrap();
+
+    assert!(session.exp_eof().unwrap().ends_with("bob is 42\r\n"));
+}
+
+#[test]
+fn chat_timeout() {
+    use std::time::{Duration, Instant};
+
+    use pseudoterminal::expect::Chat;
+
+    let mut session = Session::from(Command::new("cat").spawn_terminal().unwrap());
+    let mut chat = Chat::new();
+    chat.step_with_timeout("never", "", Duration::from_millis(100))
+        .unwrap();
+
+    let start = Instant::now();
+    let err = chat.run(&mut session).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn timeout() {
+    use std::time::Duration;
+
+    use pseudoterminal::expect::TimeoutError;
+
+    let mut session = greeter();
+    session.set_timeout(Some(Duration::from_millis(100)));
+
+    let err = session.exp_string("never").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    let err = err
+        .get_ref()
+        .and_then(|err| err.downcast_ref::<TimeoutError>())
+        .expect("should be a TimeoutError");
+    assert_eq!(err.timeout(), Duration::from_millis(100));
+    assert_eq!(err.unmatched(), b"name? ");
+    assert_eq!(session.buffer(), b"name? ");
+
+    // The output is still there for the next expectation.
+    session.exp_string("name? ").unwrap();
+}
+
+#[test]
+fn timeout_per_call() {
+    use std::time::Duration;
+
+    let mut session = Session::from(Command::new("cat").spawn_terminal().unwrap());
+    session.set_timeout(Some(Duration::from_secs(60)));
+    session.send_line("hello").unwrap();
+
+    let err = session
+        .exp_regex_with_timeout("never", Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+
+    let err = session
+        .exp_eof_with_timeout(Duration::from_millis(100))
+        .unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(session.buffer().starts_with(b"hello\r\n"));
+}
@@ -0,0 +1,72 @@
+#![cfg(all(unix, feature = "futures-io"))]
+
+use std::process::Command;
+
+use futures::executor::block_on;
+use futures::io::{AsyncReadExt, AsyncWriteExt};
+use pseudoterminal::futures::{TerminalIn, TerminalOut, TerminalStream};
+use pseudoterminal::CommandExt;
+
+#[test]
+fn read_to_end() {
+    let mut terminal = Command::new("echo")
+        .arg("Hello, World!")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let mut termout = TerminalOut::new(terminal.termout.take().unwrap()).unwrap();
+
+    let mut output = String::new();
+    block_on(termout.read_to_string(&mut output)).unwrap();
+
+    assert_eq!(output, "Hello, World!\r\n");
+}
+
+#[test]
+fn write_and_read() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (termin, termout) = terminal.split().unwrap();
+    let mut stream = TerminalStream::new(
+        TerminalIn::new(termin).unwrap(),
+        TerminalOut::new(termout).unwrap(),
+    );
+
+    block_on(async {
+        stream.write_all(b"ping\n").await.unwrap();
+        stream.flush().await.unwrap();
+
+        // The terminal's echo, followed by cat's copy.
+        let mut output = Vec::new();
+        let mut buf = [0; 64];
+        while !output.ends_with(b"ping\r\nping\r\n") {
+            let read = stream.read(&mut buf).await.unwrap();
+            assert_ne!(read, 0, "the output ended early");
+            output.extend_from_slice(&buf[..read]);
+        }
+    });
+
+    // Dropping the input delivers EOF, so cat exits.
+    drop(stream);
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[test]
+fn close_delivers_eof() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let mut termin = TerminalIn::new(terminal.termin.take().unwrap()).unwrap();
+
+    block_on(async {
+        termin.write_all(b"ping\n").await.unwrap();
+        termin.close().await.unwrap();
+        termin.close().await.unwrap();
+
+        assert!(termin.write_all(b"pong\n").await.is_err());
+    });
+
+    // cat exits on the EOF while the input is still open.
+    assert!(terminal.wait().unwrap().success());
+    drop(termin);
+}
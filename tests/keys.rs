@@ -0,0 +1,145 @@
+#![cfg(feature = "keys")]
+
+use pseudoterminal::keys::{Key, KeyEncoder, Modifiers, MouseButton, MouseEvent, MouseEventKind};
+
+#[test]
+fn xterm() {
+    let keys = KeyEncoder::for_term("xterm-256color");
+    let encode = |key, modifiers| keys.encode(key, modifiers).unwrap();
+
+    assert_eq!(encode(Key::Up, Modifiers::NONE), b"\x1b[A");
+    assert_eq!(encode(Key::Home, Modifiers::NONE), b"\x1b[H");
+    assert_eq!(encode(Key::PageDown, Modifiers::NONE), b"\x1b[6~");
+    assert_eq!(encode(Key::F(1), Modifiers::NONE), b"\x1bOP");
+    assert_eq!(encode(Key::F(12), Modifiers::NONE), b"\x1b[24~");
+
+    assert_eq!(encode(Key::Right, Modifiers::CTRL), b"\x1b[1;5C");
+    assert_eq!(encode(Key::Up, Modifiers::ALT), b"\x1b[1;3A");
+    assert_eq!(
+        encode(Key::Delete, Modifiers::SHIFT | Modifiers::CTRL),
+        b"\x1b[3;6~"
+    );
+    assert_eq!(encode(Key::F(2), Modifiers::SHIFT), b"\x1b[1;2Q");
+    assert_eq!(encode(Key::Tab, Modifiers::SHIFT), b"\x1b[Z");
+
+    assert_eq!(keys.encode(Key::F(13), Modifiers::NONE), None);
+}
+
+#[test]
+fn application_cursor() {
+    let mut keys = KeyEncoder::new();
+    keys.application_cursor(true);
+
+    assert_eq!(keys.encode(Key::Left, Modifiers::NONE).unwrap(), b"\x1bOD");
+    assert_eq!(keys.encode(Key::End, Modifiers::NONE).unwrap(), b"\x1bOF");
+    assert_eq!(
+        keys.encode(Key::Left, Modifiers::CTRL).unwrap(),
+        b"\x1b[1;5D"
+    );
+}
+
+#[test]
+fn other_terminals() {
+    let linux = KeyEncoder::for_term("linux");
+    assert_eq!(
+        linux.encode(Key::F(1), Modifiers::NONE).unwrap(),
+        b"\x1b[[A"
+    );
+    assert_eq!(
+        linux.encode(Key::Home, Modifiers::NONE).unwrap(),
+        b"\x1b[1~"
+    );
+    assert_eq!(
+        linux.encode(Key::Up, Modifiers::ALT).unwrap(),
+        b"\x1b\x1b[A"
+    );
+
+    let rxvt = KeyEncoder::for_term("rxvt-unicode");
+    assert_eq!(rxvt.encode(Key::Home, Modifiers::NONE).unwrap(), b"\x1b[7~");
+    assert_eq!(
+        rxvt.encode(Key::F(1), Modifiers::NONE).unwrap(),
+        b"\x1b[11~"
+    );
+
+    let vt220 = KeyEncoder::for_term("vt220");
+    assert_eq!(
+        vt220.encode(Key::Backspace, Modifiers::NONE).unwrap(),
+        b"\x08"
+    );
+    assert_eq!(vt220.encode(Key::F(5), Modifiers::NONE), None);
+}
+
+#[test]
+fn chords() {
+    let keys = KeyEncoder::new();
+    let encode = |key, modifiers| keys.encode(key, modifiers).unwrap();
+
+    assert_eq!(encode(Key::Char('c'), Modifiers::CTRL), b"\x03");
+    assert_eq!(encode(Key::Char(' '), Modifiers::CTRL), b"\x00");
+    assert_eq!(encode(Key::Char('['), Modifiers::CTRL), b"\x1b");
+    assert_eq!(encode(Key::Char('x'), Modifiers::ALT), b"\x1bx");
+    assert_eq!(
+        encode(Key::Char('d'), Modifiers::ALT | Modifiers::CTRL),
+        b"\x1b\x04"
+    );
+    assert_eq!(encode(Key::Char('a'), Modifiers::SHIFT), b"A");
+    assert_eq!(encode(Key::Char('é'), Modifiers::NONE), "é".as_bytes());
+    assert_eq!(encode(Key::Enter, Modifiers::ALT), b"\x1b\r");
+    assert_eq!(encode(Key::Backspace, Modifiers::NONE), b"\x7f");
+}
+
+#[test]
+fn mouse() {
+    let keys = KeyEncoder::new();
+
+    let press = MouseEvent::new(MouseEventKind::Press(MouseButton::Left), 0, 0);
+    assert_eq!(keys.encode_mouse(press), b"\x1b[<0;1;1M");
+
+    let release = MouseEvent::new(MouseEventKind::Release(MouseButton::Right), 9, 4);
+    assert_eq!(keys.encode_mouse(release), b"\x1b[<2;10;5m");
+
+    let mut drag = MouseEvent::new(MouseEventKind::Drag(MouseButton::Middle), 299, 99);
+    drag.modifiers = Modifiers::SHIFT | Modifiers::CTRL;
+    assert_eq!(keys.encode_mouse(drag), b"\x1b[<53;300;100M");
+
+    let mut wheel = MouseEvent::new(MouseEventKind::Press(MouseButton::WheelDown), 2, 3);
+    wheel.modifiers = Modifiers::ALT;
+    assert_eq!(keys.encode_mouse(wheel), b"\x1b[<73;3;4M");
+
+    let moved = MouseEvent::new(MouseEventKind::Move, 5, 6);
+    assert_eq!(keys.encode_mouse(moved), b"\x1b[<35;6;7M");
+}
+
+#[cfg(unix)]
+#[test]
+fn write_key() {
+    use std::io::Read;
+    use std::process::Command;
+
+    use pseudoterminal::{CommandExt, TerminalOptions};
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal_with(TerminalOptions::new().term("xterm").raw_mode(true))
+        .expect("should be spawnable");
+    let keys = terminal.key_encoder();
+
+    let termin = terminal.termin.as_mut().unwrap();
+    keys.write_key(termin, Key::Up, Modifiers::NONE).unwrap();
+    assert!(keys.write_key(termin, Key::F(20), Modifiers::NONE).is_err());
+    keys.write_mouse(
+        termin,
+        MouseEvent::new(MouseEventKind::Press(MouseButton::Left), 1, 2),
+    )
+    .unwrap();
+
+    let mut buf = [0; 12];
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_exact(&mut buf)
+        .unwrap();
+    assert_eq!(&buf, b"\x1b[A\x1b[<0;2;3M");
+
+    terminal.close().expect("");
+}
@@ -0,0 +1,919 @@
+#![cfg(feature = "non-blocking")]
+
+use std::process::Command;
+
+use pseudoterminal::non_blocking::CommandExt;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn wait_for_hangup() {
+    let terminal = Command::new("true")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    terminal
+        .wait_for_hangup()
+        .await
+        .expect("the session should hang up once the child exits");
+
+    terminal.close().await.expect("");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn wait() {
+    let mut cmd = Command::new("sh");
+    cmd.arg("-c").arg("exit 3");
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    assert_eq!(terminal.exit_status(), None);
+    let status = terminal.wait().await.expect("the child should be waitable");
+
+    assert_eq!(status.code(), Some(3));
+    assert_eq!(terminal.exit_status(), Some(status));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn exited() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let exited = tokio::spawn(terminal.exited());
+
+    // Dropping the input delivers EOF, so cat exits.
+    let (termin, _termout) = terminal.split().unwrap();
+    drop(termin);
+
+    assert!(exited.await.unwrap().unwrap().success());
+    assert!(terminal.exit_status().unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn close_while_exited_pending() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::time::Duration;
+
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let controller = terminal.controller();
+
+    let exited = tokio::spawn(terminal.exited());
+    // Gives the wait time to take hold of the child.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(!exited.is_finished());
+    assert_eq!(controller.try_wait().await.unwrap(), None);
+
+    tokio::time::timeout(Duration::from_secs(5), terminal.close())
+        .await
+        .expect("closing shouldn't wait for the pending wait")
+        .unwrap();
+
+    let status = exited.await.unwrap().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+    assert_eq!(controller.exit_status(), Some(status));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn close_with() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::time::Duration;
+
+    use pseudoterminal::CloseBehavior;
+
+    let mut cmd = Command::new("sleep");
+    cmd.arg("10");
+
+    let status = cmd
+        .spawn_terminal()
+        .expect("should be spawnable")
+        .close_with(CloseBehavior::Graceful {
+            signal: libc::SIGTERM,
+            timeout: Duration::from_secs(1),
+        })
+        .await
+        .unwrap();
+
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+
+    // `cat` only exits on EOF, which waiting mustn't deliver.
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let controller = terminal.controller();
+    let closing = tokio::spawn(terminal.close_with(CloseBehavior::WaitOnly));
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(!closing.is_finished());
+    controller.signal(libc::SIGTERM).unwrap();
+    let status = closing.await.unwrap().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn controller() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let controller = terminal.controller();
+    let waiter = tokio::spawn(async move {
+        controller.signal(libc::SIGTERM).unwrap();
+        controller.wait().await.unwrap()
+    });
+
+    assert!(!waiter.await.unwrap().success());
+
+    // The pid may be reused once the child was reaped.
+    assert_eq!(terminal.id(), None);
+    assert!(terminal.controller().signal(libc::SIGTERM).is_err());
+
+    terminal.close().await.expect("");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn is_alive() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    assert!(terminal.is_alive());
+
+    // Answered right away while the controller holds the child to wait for it.
+    let controller = terminal.controller();
+    let waiter = tokio::spawn(async move { controller.wait().await.unwrap() });
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    assert!(terminal.is_alive());
+    assert!(terminal.controller().is_alive());
+
+    terminal.controller().signal(libc::SIGTERM).unwrap();
+    assert!(!waiter.await.unwrap().success());
+    assert!(!terminal.is_alive());
+    assert!(!terminal.controller().is_alive());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn stream() {
+    use tokio::io::AsyncWriteExt;
+
+    let mut terminal = Command::new("sh")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let mut stream = terminal.stream().expect("both halves should be there");
+    assert!(terminal.stream().is_none());
+
+    stream.write_all(b"exit 5\n").await.unwrap();
+    stream.flush().await.unwrap();
+
+    let status = terminal.wait().await.unwrap();
+    assert_eq!(status.code(), Some(5));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn interact() {
+    use std::io::{Read, Write};
+
+    // The test binary runs itself on a PTY to have a host terminal to interact through.
+    if std::env::var_os("PSEUDOTERMINAL_INTERACT_HOST").is_some() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo ready; read line; echo \"got $line\""]);
+
+        let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+        terminal.interact().await.unwrap();
+        assert!(terminal.wait().await.unwrap().success());
+        return;
+    }
+
+    let mut cmd = Command::new(std::env::current_exe().unwrap());
+    cmd.args(["--exact", "interact", "--nocapture", "--test-threads=1"])
+        .env("PSEUDOTERMINAL_INTERACT_HOST", "1");
+    let mut terminal = pseudoterminal::CommandExt::spawn_terminal(&mut cmd).unwrap();
+    let termout = terminal.termout.as_mut().unwrap();
+
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    while !String::from_utf8_lossy(&output).contains("ready") {
+        let read = termout.read(&mut buf).unwrap();
+        assert_ne!(
+            read,
+            0,
+            "the host exited early: {}",
+            String::from_utf8_lossy(&output)
+        );
+        output.extend_from_slice(&buf[..read]);
+    }
+
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"hi\r")
+        .unwrap();
+    termout.read_to_end(&mut output).unwrap();
+
+    let output = String::from_utf8_lossy(&output);
+    assert!(output.contains("got hi"), "{output}");
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn interact_interrupt_local() {
+    use std::io::{Read, Write};
+
+    use pseudoterminal::{InteractOptions, InterruptPolicy};
+
+    if std::env::var_os("PSEUDOTERMINAL_INTERRUPT_HOST").is_some() {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", "echo ready; read line"]);
+
+        let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+        let err = terminal
+            .interact_with(InteractOptions::new().interrupt(InterruptPolicy::Local))
+            .await
+            .unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::Interrupted);
+        assert!(terminal.is_alive());
+
+        terminal.close().await.unwrap();
+        println!("interrupted");
+        return;
+    }
+
+    let mut cmd = Command::new(std::env::current_exe().unwrap());
+    cmd.args([
+        "--exact",
+        "interact_interrupt_local",
+        "--nocapture",
+        "--test-threads=1",
+    ])
+    .env("PSEUDOTERMINAL_INTERRUPT_HOST", "1");
+    let mut terminal = pseudoterminal::CommandExt::spawn_terminal(&mut cmd).unwrap();
+    let termout = terminal.termout.as_mut().unwrap();
+
+    let mut output = Vec::new();
+    let mut buf = [0; 1024];
+    while !String::from_utf8_lossy(&output).contains("ready") {
+        let read = termout.read(&mut buf).unwrap();
+        assert_ne!(read, 0, "the host exited early");
+        output.extend_from_slice(&buf[..read]);
+    }
+
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"\x03")
+        .unwrap();
+    termout.read_to_end(&mut output).unwrap();
+
+    let output = String::from_utf8_lossy(&output);
+    assert!(output.contains("interrupted"), "{output}");
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn close_input() {
+    use tokio::io::AsyncWriteExt;
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .write_all(b"hello\n")
+        .await
+        .unwrap();
+    terminal.close_input().await.unwrap();
+
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn send_interrupt() {
+    use std::os::unix::process::ExitStatusExt;
+
+    let mut cmd = Command::new("sleep");
+    cmd.arg("10");
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .send_interrupt()
+        .await
+        .unwrap();
+
+    assert_eq!(terminal.wait().await.unwrap().signal(), Some(libc::SIGINT));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn send_line() {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "read code; exit $code"]);
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+    terminal
+        .termin
+        .as_mut()
+        .unwrap()
+        .send_line("7")
+        .await
+        .unwrap();
+
+    assert_eq!(terminal.wait().await.unwrap().code(), Some(7));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn read_to_end() {
+    use tokio::io::AsyncReadExt;
+
+    let mut cmd = Command::new("echo");
+    cmd.arg("Hello, World!");
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    let mut output = String::new();
+    let termout = terminal.termout.as_mut().unwrap();
+    termout.read_to_string(&mut output).await.unwrap();
+
+    assert_eq!(output, "Hello, World!\r\n");
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn stream_read() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let mut stream = terminal.stream().unwrap();
+
+    stream.write_all(b"ping\n").await.unwrap();
+
+    // The terminal's echo, followed by cat's copy.
+    let mut output = Vec::new();
+    let mut buf = [0; 64];
+    while !output.ends_with(b"ping\r\nping\r\n") {
+        let read = stream.read(&mut buf).await.unwrap();
+        assert_ne!(read, 0, "the output ended early");
+        output.extend_from_slice(&buf[..read]);
+    }
+
+    drop(stream);
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn wait_with_output() {
+    let mut cmd = Command::new("sh");
+    cmd.args(["-c", "echo out; exit 2"]);
+
+    let output = cmd
+        .spawn_terminal()
+        .expect("should be spawnable")
+        .wait_with_output()
+        .await
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(2));
+    assert_eq!(output.stdout, b"out\r\n");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn bridge() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut cmd = Command::new("sh");
+    cmd.args([
+        "-c",
+        "stty -echo; echo ready; read line; echo \"got $line\"",
+    ]);
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    let (mut client, mut server) = tokio::io::duplex(1024);
+    let client = async {
+        let mut output = Vec::new();
+        let mut buf = [0; 64];
+        while !output.ends_with(b"ready\r\n") {
+            let read = client.read(&mut buf).await.unwrap();
+            assert_ne!(read, 0, "the output ended early");
+            output.extend_from_slice(&buf[..read]);
+        }
+        client.write_all(b"hi\n").await.unwrap();
+
+        // The bridge shuts the client's side down once the child's output ended.
+        client.read_to_end(&mut output).await.unwrap();
+        output
+    };
+
+    let (stats, output) = tokio::join!(terminal.bridge(&mut server), client);
+    let stats = stats.unwrap();
+
+    assert!(output.ends_with(b"got hi\r\n"));
+    assert_eq!(stats.to_terminal, 3);
+    assert_eq!(stats.from_terminal, output.len() as u64);
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(all(unix, feature = "futures-io"))]
+#[tokio::test]
+async fn futures_io_read() {
+    use futures::io::AsyncReadExt;
+
+    let mut cmd = Command::new("echo");
+    cmd.arg("Hello, World!");
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    let mut output = String::new();
+    let termout = terminal.termout.as_mut().unwrap();
+    AsyncReadExt::read_to_string(termout, &mut output)
+        .await
+        .unwrap();
+
+    assert_eq!(output, "Hello, World!\r\n");
+}
+
+#[cfg(all(unix, feature = "stream"))]
+#[tokio::test]
+async fn into_stream() {
+    use futures::StreamExt;
+
+    let mut cmd = Command::new("echo");
+    cmd.arg("Hello, World!");
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+    let chunks: Vec<_> = terminal
+        .termout
+        .take()
+        .unwrap()
+        .into_stream()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(chunks.concat(), b"Hello, World!\r\n");
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(all(unix, feature = "stream"))]
+#[tokio::test]
+async fn into_sink() {
+    use bytes::Bytes;
+    use futures::{stream, StreamExt};
+    use tokio::io::AsyncReadExt;
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (termin, mut termout) = terminal.split().unwrap();
+
+    let frames = [Bytes::from("pi"), Bytes::from("ng\n")];
+    stream::iter(frames.map(Ok))
+        .forward(termin.into_sink())
+        .await
+        .unwrap();
+
+    // The terminal's echo, followed by cat's copy. Forwarding dropped the input, so
+    // cat exits after that.
+    let mut output = Vec::new();
+    termout.read_to_end(&mut output).await.unwrap();
+    assert_eq!(output, b"ping\r\nping\r\n");
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(all(unix, feature = "stream"))]
+#[tokio::test]
+async fn lines_stream() {
+    use futures::TryStreamExt;
+    use pseudoterminal::LineEndings;
+
+    let mut cmd = Command::new("printf");
+    cmd.arg("one\\ntwo\\a\\nthree");
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    let lines = terminal.termout.take().unwrap().lines_stream();
+    let lines: Vec<_> = lines.try_collect().await.unwrap();
+    assert_eq!(lines, ["one", "two\x07", "three"]);
+
+    let mut cmd = Command::new("printf");
+    cmd.arg("10%%\\r20%%\\a\\n");
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    let termout = terminal.termout.take().unwrap();
+    let mut lines = termout.lines_stream_with(LineEndings::Any);
+    lines.set_trim_controls(true);
+    let lines: Vec<_> = lines.try_collect().await.unwrap();
+    assert_eq!(lines, ["10%", "20%"]);
+}
+
+#[cfg(all(unix, feature = "stream"))]
+#[tokio::test]
+async fn events() {
+    use futures::StreamExt;
+    use pseudoterminal::non_blocking::TerminalEvent;
+
+    let mut cmd = Command::new("echo");
+    cmd.arg("Hello, World!");
+
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+    let mut events = terminal.events().unwrap();
+
+    let (mut output, mut hangup, mut status) = (Vec::new(), false, None);
+    while let Some(event) = events.next().await {
+        match event.unwrap() {
+            TerminalEvent::Output(chunk) => {
+                assert!(!hangup, "output after the hangup");
+                output.extend_from_slice(&chunk);
+            }
+            TerminalEvent::Hangup => hangup = true,
+            TerminalEvent::Exited(exited) => status = Some(exited),
+        }
+    }
+
+    assert_eq!(output, b"Hello, World!\r\n");
+    assert!(hangup);
+    assert!(status.unwrap().success());
+    assert!(terminal.events().is_err());
+}
+
+#[cfg(all(unix, feature = "stream"))]
+#[tokio::test]
+async fn close_while_events_pending() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::time::Duration;
+
+    use futures::StreamExt;
+    use pseudoterminal::non_blocking::TerminalEvent;
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let mut events = terminal.events().unwrap();
+
+    let consumer = tokio::spawn(async move {
+        let mut status = None;
+        while let Some(event) = events.next().await {
+            if let TerminalEvent::Exited(exited) = event.unwrap() {
+                status = Some(exited);
+            }
+        }
+        status
+    });
+    tokio::time::sleep(Duration::from_millis(100)).await;
+
+    tokio::time::timeout(Duration::from_secs(5), terminal.close())
+        .await
+        .expect("closing shouldn't wait for the event stream")
+        .unwrap();
+
+    let status = consumer.await.unwrap().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGKILL));
+}
+
+#[cfg(all(unix, feature = "broadcast"))]
+#[tokio::test]
+async fn broadcast_output() {
+    use tokio::io::AsyncWriteExt;
+    use tokio::sync::broadcast::{error::RecvError, Receiver};
+
+    async fn receive_all<T: AsRef<[u8]> + Clone>(mut receiver: Receiver<T>) -> Vec<u8> {
+        let mut output = Vec::new();
+        loop {
+            match receiver.recv().await {
+                Ok(chunk) => output.extend_from_slice(chunk.as_ref()),
+                Err(RecvError::Closed) => return output,
+                Err(RecvError::Lagged(_)) => panic!("the subscriber should keep up"),
+            }
+        }
+    }
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let output = terminal.broadcast_output(16).unwrap();
+    assert!(terminal.termout.is_none());
+
+    let first = tokio::spawn(receive_all(output.subscribe()));
+    let second = tokio::spawn(receive_all(output.subscribe()));
+
+    let mut termin = terminal.termin.take().unwrap();
+    termin.write_all(b"ping\n").await.unwrap();
+    termin.close().await.unwrap();
+
+    assert!(terminal.wait().await.unwrap().success());
+    output.finished().await.unwrap();
+
+    // The terminal's echo, followed by cat's copy.
+    assert_eq!(first.await.unwrap(), b"ping\r\nping\r\n");
+    assert_eq!(second.await.unwrap(), b"ping\r\nping\r\n");
+}
+
+#[cfg(all(unix, feature = "channels"))]
+#[tokio::test]
+async fn into_channels() {
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (sender, mut receiver, pumps) = terminal.into_channels(8).unwrap();
+
+    sender.send("ping\n".into()).await.unwrap();
+
+    // The terminal's echo, followed by cat's copy.
+    let mut output = Vec::new();
+    while !output.ends_with(b"ping\r\nping\r\n") {
+        let chunk = receiver.recv().await.expect("the output ended early");
+        output.extend_from_slice(&chunk);
+    }
+
+    // Dropping the sender delivers EOF, so cat exits.
+    drop(sender);
+    assert!(receiver.recv().await.is_none());
+    assert!(pumps.await.unwrap().unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn readiness() {
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (termin, termout) = terminal.split().unwrap();
+
+    let mut input: &[u8] = b"ping\n";
+    while !input.is_empty() {
+        termin.writable().await.unwrap();
+        match termin.try_write(input) {
+            Ok(written) => input = &input[written..],
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(err) => panic!("writing failed: {err}"),
+        }
+    }
+
+    // The terminal's echo, followed by cat's copy.
+    let mut output = Vec::new();
+    let mut buf = [0; 64];
+    while !output.ends_with(b"ping\r\nping\r\n") {
+        termout.readable().await.unwrap();
+        match termout.try_read(&mut buf) {
+            Ok(read) => {
+                assert_ne!(read, 0, "the output ended early");
+                output.extend_from_slice(&buf[..read]);
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::WouldBlock => {}
+            Err(err) => panic!("reading failed: {err}"),
+        }
+    }
+
+    // Dropping the input delivers EOF, so cat exits.
+    drop(termin);
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn read_timeout() {
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::time::error::Elapsed;
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (mut termin, mut termout) = terminal.split().unwrap();
+
+    let mut buf = [0; 64];
+    let err = termout
+        .read_timeout(&mut buf, Duration::from_millis(50))
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    assert!(err.get_ref().unwrap().is::<Elapsed>());
+
+    termin.write_all(b"ping\n").await.unwrap();
+    let read = termout
+        .read_timeout(&mut buf, Duration::from_secs(5))
+        .await
+        .unwrap();
+    // The terminal's echo, possibly followed by cat's copy.
+    assert_ne!(read, 0);
+    assert!(b"ping\r\nping\r\n".starts_with(&buf[..read]));
+
+    // Dropping the input delivers EOF, so cat exits.
+    drop(termin);
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(all(unix, feature = "cancellation"))]
+#[tokio::test]
+async fn wait_until_cancelled() {
+    use std::os::unix::process::ExitStatusExt;
+    use std::time::Duration;
+
+    use pseudoterminal::CloseBehavior;
+    use tokio_util::sync::CancellationToken;
+
+    let mut cmd = Command::new("sleep");
+    cmd.arg("10");
+
+    let token = CancellationToken::new();
+    let session = tokio::spawn(
+        cmd.spawn_terminal()
+            .expect("should be spawnable")
+            .wait_until_cancelled(
+                token.clone(),
+                CloseBehavior::Graceful {
+                    signal: libc::SIGTERM,
+                    timeout: Duration::from_secs(1),
+                },
+            ),
+    );
+
+    token.cancel();
+    let status = session.await.unwrap().unwrap();
+    assert_eq!(status.signal(), Some(libc::SIGTERM));
+}
+
+#[cfg(all(unix, feature = "cancellation"))]
+#[tokio::test]
+async fn wait_until_cancelled_exit() {
+    use pseudoterminal::CloseBehavior;
+    use tokio_util::sync::CancellationToken;
+
+    let status = Command::new("true")
+        .spawn_terminal()
+        .expect("should be spawnable")
+        .wait_until_cancelled(CancellationToken::new(), CloseBehavior::Force)
+        .await
+        .unwrap();
+
+    assert!(status.success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn shutdown() {
+    use tokio::io::AsyncWriteExt;
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let termin = terminal.termin.as_mut().unwrap();
+
+    tokio::io::copy(&mut &b"ping\n"[..], termin).await.unwrap();
+    termin.shutdown().await.unwrap();
+    assert!(termin.write(b"pong\n").await.is_err());
+
+    // The input is still around, but the shutdown delivered EOF, so cat exits.
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn into_split() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (mut termin, mut termout, controller) = terminal.into_split().unwrap();
+
+    // Dropping the input afterwards delivers EOF, so cat exits.
+    let writer = tokio::spawn(async move { termin.write_all(b"ping\n").await });
+    let reader = tokio::spawn(async move {
+        let mut output = Vec::new();
+        termout.read_to_end(&mut output).await.map(|_| output)
+    });
+
+    writer.await.unwrap().unwrap();
+    assert!(controller.wait().await.unwrap().success());
+    assert_eq!(reader.await.unwrap().unwrap(), b"ping\r\nping\r\n");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn write_vectored() {
+    use std::io::IoSlice;
+    use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    let mut terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    let (mut termin, mut termout) = terminal.split().unwrap();
+    assert!(termin.is_write_vectored());
+
+    let bufs = [IoSlice::new(b"pi"), IoSlice::new(b"ng\n")];
+    assert_eq!(termin.write_vectored(&bufs).await.unwrap(), 5);
+    drop(termin);
+
+    // The terminal's echo, followed by cat's copy.
+    let mut output = Vec::new();
+    termout.read_to_end(&mut output).await.unwrap();
+    assert_eq!(output, b"ping\r\nping\r\n");
+    assert!(terminal.wait().await.unwrap().success());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn close_blocking() {
+    let mut cmd = Command::new("sleep");
+    cmd.arg("10");
+
+    let terminal = cmd.spawn_terminal().expect("should be spawnable");
+    let controller = terminal.controller();
+
+    tokio::task::spawn_blocking(move || terminal.close_blocking())
+        .await
+        .unwrap()
+        .unwrap();
+
+    assert!(!controller.exit_status().unwrap().success());
+}
+
+#[cfg(all(unix, feature = "scrollback"))]
+#[tokio::test]
+async fn scrollback() {
+    use tokio::io::AsyncReadExt;
+
+    let mut cmd = Command::new("seq");
+    cmd.args(["1", "100"]);
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+    terminal.enable_scrollback(32);
+
+    let mut output = Vec::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .await
+        .unwrap();
+
+    let scrollback = terminal.scrollback().unwrap();
+    assert!(output.ends_with(&scrollback));
+    assert!(scrollback.ends_with(b"99\r\n100\r\n"));
+
+    terminal.close().await.unwrap();
+}
+
+#[cfg(all(unix, feature = "title"))]
+#[tokio::test]
+async fn title_change() {
+    use std::sync::{Arc, Mutex};
+
+    use tokio::io::AsyncReadExt;
+
+    let mut cmd = Command::new("printf");
+    cmd.arg(r"\033]2;hello\007");
+    let mut terminal = cmd.spawn_terminal().expect("should be spawnable");
+
+    let titles = Arc::new(Mutex::new(Vec::new()));
+    let seen = titles.clone();
+    terminal.on_title_change(move |title| seen.lock().unwrap().push(title.to_owned()));
+
+    let mut output = Vec::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .await
+        .unwrap();
+
+    assert_eq!(*titles.lock().unwrap(), ["hello"]);
+
+    terminal.close().await.unwrap();
+}
+
+#[cfg(unix)]
+#[tokio::test]
+#[should_panic]
+async fn close_blocking_on_runtime() {
+    // cat exits on its own once the terminal is dropped while panicking.
+    let terminal = Command::new("cat")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let _ = terminal.close_blocking();
+}
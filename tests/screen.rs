@@ -0,0 +1,290 @@
+#![cfg(feature = "screen")]
+
+use pseudoterminal::screen::{assert_screen_contains, CellChange, Color, Parser, Screen};
+use pseudoterminal::TerminalSize;
+
+fn parser(rows: u16, columns: u16) -> Parser {
+    Parser::new(TerminalSize { rows, columns })
+}
+
+fn text(screen: &Screen, row: u16) -> String {
+    screen.row_contents(row).unwrap()
+}
+
+#[test]
+fn print_and_wrap() {
+    let mut parser = parser(3, 5);
+    parser.process(b"hello world\r\nnext");
+
+    let screen = parser.screen();
+    assert_eq!(text(screen, 0), " worl");
+    assert_eq!(text(screen, 1), "d");
+    assert_eq!(text(screen, 2), "next");
+    assert_eq!(screen.cursor_position(), (2, 4));
+}
+
+#[test]
+fn scroll() {
+    let mut parser = parser(2, 10);
+    parser.process(b"one\r\ntwo\r\nthree");
+
+    assert_eq!(text(parser.screen(), 0), "two");
+    assert_eq!(text(parser.screen(), 1), "three");
+}
+
+#[test]
+fn cursor_movement_and_erase() {
+    let mut parser = parser(3, 10);
+    parser.process(b"abcdefgh\x1b[1;3H\x1b[K\x1b[3;5Hx\x1b[2A\x1b[Dyz\x1b[2;1H\x1b[2@-");
+
+    let screen = parser.screen();
+    assert_eq!(text(screen, 0), "ab  yz");
+    assert_eq!(text(screen, 1), "-");
+    assert_eq!(text(screen, 2), "    x");
+
+    parser.process(b"\x1b[2J");
+    assert!((0..3).all(|row| text(parser.screen(), row).is_empty()));
+}
+
+#[test]
+fn graphic_rendition() {
+    let mut parser = parser(1, 10);
+    parser.process(b"\x1b[1;31ma\x1b[38;5;200;48;2;1;2;3mb\x1b[38:2::4:5:6;22mc\x1b[0md");
+
+    let row = parser.screen().row(0).unwrap();
+    let a = row[0].attributes();
+    assert!(a.bold);
+    assert_eq!(a.foreground, Color::Indexed(1));
+
+    let b = row[1].attributes();
+    assert!(b.bold);
+    assert_eq!(b.foreground, Color::Indexed(200));
+    assert_eq!(b.background, Color::Rgb(1, 2, 3));
+
+    let c = row[2].attributes();
+    assert!(!c.bold);
+    assert_eq!(c.foreground, Color::Rgb(4, 5, 6));
+
+    assert_eq!(row[3].attributes(), Default::default());
+}
+
+#[test]
+fn scroll_region() {
+    let mut parser = parser(4, 10);
+    parser.process(b"head\r\n1\r\n2\r\nfoot\x1b[2;3r\x1b[3;1H\n3");
+
+    let screen = parser.screen();
+    assert_eq!(text(screen, 0), "head");
+    assert_eq!(text(screen, 1), "2");
+    assert_eq!(text(screen, 2), "3");
+    assert_eq!(text(screen, 3), "foot");
+}
+
+#[test]
+fn alternate_screen() {
+    let mut parser = parser(2, 10);
+    parser.process(b"shell\x1b[?1049h\x1b[Hfull\x1b[?25l");
+
+    assert!(parser.screen().alternate_screen());
+    assert!(!parser.screen().cursor_visible());
+    assert_eq!(text(parser.screen(), 0), "full");
+
+    parser.process(b"\x1b[?1049l");
+    assert!(!parser.screen().alternate_screen());
+    assert_eq!(text(parser.screen(), 0), "shell");
+    assert_eq!(parser.screen().cursor_position(), (0, 5));
+}
+
+#[test]
+fn wide_characters() {
+    let mut parser = parser(2, 4);
+    parser.process("a日本\u{301}".as_bytes());
+
+    let screen = parser.screen();
+    let row = screen.row(0).unwrap();
+    assert!(row[1].is_wide());
+    assert!(row[2].is_wide_continuation());
+    // The second one doesn't fit into the last column and goes to the next row.
+    assert_eq!(text(screen, 0), "a日");
+    assert_eq!(screen.row(1).unwrap()[0].contents(), "本\u{301}");
+}
+
+#[test]
+fn split_sequences() {
+    let mut parser = parser(1, 10);
+    for byte in "\x1b[31mä".as_bytes() {
+        parser.process(&[*byte]);
+    }
+
+    let cell = &parser.screen().row(0).unwrap()[0];
+    assert_eq!(cell.contents(), "ä");
+    assert_eq!(cell.attributes().foreground, Color::Indexed(1));
+}
+
+#[test]
+fn resize() {
+    let mut parser = parser(3, 10);
+    parser.process(b"one\r\ntwo\r\nthree");
+    parser.set_size(TerminalSize {
+        rows: 2,
+        columns: 4,
+    });
+
+    let screen = parser.screen();
+    assert_eq!(
+        screen.size(),
+        TerminalSize {
+            rows: 2,
+            columns: 4
+        }
+    );
+    assert_eq!(text(screen, 0), "two");
+    assert_eq!(text(screen, 1), "thre");
+    assert_eq!(screen.cursor_position(), (1, 3));
+}
+
+#[cfg(unix)]
+#[test]
+fn terminal_screen() {
+    use std::io::Read;
+    use std::process::Command;
+
+    use pseudoterminal::{CommandExt, TerminalOptions};
+
+    let mut options = TerminalOptions::new();
+    options.size(TerminalSize {
+        rows: 5,
+        columns: 20,
+    });
+
+    let mut terminal = Command::new("printf")
+        .arg(r"\033[2;3H\033[1mhi")
+        .spawn_terminal_with(&options)
+        .expect("should be spawnable");
+    assert!(terminal.screen().is_none());
+    terminal.enable_screen().unwrap();
+
+    let mut output = Vec::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    let screen = terminal.screen().unwrap();
+    assert_eq!(
+        screen.size(),
+        TerminalSize {
+            rows: 5,
+            columns: 20
+        }
+    );
+    assert_eq!(text(&screen, 1), "  hi");
+    assert!(screen.row(1).unwrap()[2].attributes().bold);
+
+    terminal
+        .set_term_size(TerminalSize {
+            rows: 10,
+            columns: 40,
+        })
+        .unwrap();
+    assert_eq!(
+        terminal.screen().unwrap().size(),
+        TerminalSize {
+            rows: 10,
+            columns: 40
+        }
+    );
+}
+
+#[test]
+fn contents() {
+    let mut parser = parser(4, 10);
+    parser.process("\x1b[2;3Hab\x1b[31mc  \r\n日x".as_bytes());
+
+    let screen = parser.screen();
+    assert_eq!(screen.contents(), "\n  abc\n日x");
+    assert_eq!(screen.to_string(), screen.contents());
+    assert!(screen.contains("abc"));
+    assert!(!screen.contains("abc\n"));
+
+    let cell = screen.cell_at(1, 4).unwrap();
+    assert_eq!(cell.contents(), "c");
+    assert_eq!(cell.attributes().foreground, Color::Indexed(1));
+    assert!(screen.cell_at(4, 0).is_none());
+    assert!(screen.cell_at(0, 10).is_none());
+}
+
+#[test]
+fn assert_contains() {
+    let mut parser = parser(2, 10);
+    parser.process(b"one\r\ntwo");
+    assert_screen_contains(parser.screen(), "two");
+
+    let panic =
+        std::panic::catch_unwind(|| assert_screen_contains(parser.screen(), "three")).unwrap_err();
+    let message = panic.downcast_ref::<String>().unwrap();
+    assert!(message.contains("\"three\""));
+    assert!(message.ends_with("one\ntwo"));
+}
+
+#[test]
+fn damage() {
+    let mut parser = parser(4, 10);
+    assert_eq!(parser.take_damage(), [0, 1, 2, 3]);
+    assert!(parser.take_damage().is_empty());
+
+    parser.process(b"\x1b[2;1Hab\x1b[4;1Hc");
+    assert_eq!(parser.take_damage(), [1, 3]);
+
+    // Moving the cursor draws nothing.
+    parser.process(b"\x1b[H");
+    assert!(parser.take_damage().is_empty());
+
+    // Scrolling moves every row.
+    parser.process(b"\x1b[4;1H\n");
+    assert_eq!(parser.take_damage(), [0, 1, 2, 3]);
+}
+
+#[test]
+fn diff() {
+    let mut parser = parser(3, 10);
+    parser.process(b"one\r\ntwo");
+    let before = parser.screen().clone();
+
+    parser.process(b"\x1b[2;2HW\x1b[3;1H\x1b[1mx");
+    let after = parser.screen();
+
+    let changes = after.diff(&before);
+    assert_eq!(
+        changes
+            .iter()
+            .map(|change| (change.row, change.column, change.cell.contents()))
+            .collect::<Vec<_>>(),
+        [(1, 1, "W"), (2, 0, "x")]
+    );
+    assert!(changes[1].cell.attributes().bold);
+    assert!(after.diff(after).is_empty());
+
+    // Erasing a blank row touches it without changing a cell.
+    parser.process(b"\x1b[3;1H\x1b[2K");
+    let erased = parser.screen().clone();
+    parser.process(b"\x1b[2K");
+    assert!(parser.screen().diff(&erased).is_empty());
+
+    parser.set_size(TerminalSize {
+        rows: 2,
+        columns: 2,
+    });
+    let resized = parser.screen().diff(&erased);
+    assert_eq!(resized.len(), 4);
+    assert_eq!(
+        resized[0],
+        CellChange {
+            row: 0,
+            column: 0,
+            cell: parser.screen().cell_at(0, 0).unwrap().clone(),
+        }
+    );
+}
@@ -0,0 +1,50 @@
+#![cfg(all(unix, feature = "scrollback"))]
+
+use std::io::Read;
+use std::process::Command;
+
+use pseudoterminal::CommandExt;
+
+#[test]
+fn scrollback() {
+    let mut terminal = Command::new("seq")
+        .args(["1", "1000"])
+        .spawn_terminal()
+        .expect("should be spawnable");
+    assert!(terminal.scrollback().is_none());
+    terminal.enable_scrollback(64);
+
+    let mut output = Vec::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    let scrollback = terminal.scrollback().unwrap();
+    assert!(scrollback.len() <= 64);
+    assert!(output.ends_with(&scrollback));
+    // The oldest line cut in half was dropped entirely.
+    assert!(output[..output.len() - scrollback.len()].ends_with(b"\n"));
+    assert!(scrollback.ends_with(b"999\r\n1000\r\n"));
+}
+
+#[test]
+fn scrollback_long_line() {
+    let mut terminal = Command::new("printf")
+        .arg("%0200d")
+        .arg("0")
+        .spawn_terminal()
+        .expect("should be spawnable");
+    terminal.enable_scrollback(16);
+
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut Vec::new())
+        .unwrap();
+
+    assert_eq!(terminal.scrollback().unwrap(), [b'0'; 16]);
+}
@@ -0,0 +1,15 @@
+#![cfg(feature = "serde")]
+
+use pseudoterminal::TerminalSize;
+
+#[test]
+fn serde_round_trip() {
+    let size = TerminalSize {
+        rows: 40,
+        columns: 120,
+    };
+
+    let json = serde_json::to_string(&size).unwrap();
+    assert_eq!(json, r#"{"rows":40,"columns":120}"#);
+    assert_eq!(serde_json::from_str::<TerminalSize>(&json).unwrap(), size);
+}
@@ -0,0 +1,45 @@
+#![cfg(feature = "shell-integration")]
+
+use pseudoterminal::shell_integration::{ShellEvent, ShellIntegration};
+
+#[test]
+fn feed() {
+    let mut shell = ShellIntegration::new();
+
+    assert_eq!(
+        shell.feed(b"\x1b]133;A\x07$ \x1b]133;B\x1b\\ls\r\n\x1b]133;C\x07"),
+        [
+            ShellEvent::PromptStart,
+            ShellEvent::CommandStart,
+            ShellEvent::CommandExecuted
+        ],
+    );
+    assert_eq!(
+        shell.feed(b"\x1b]133;D\x07\x1b]133;D;127;aid=1\x07"),
+        [
+            ShellEvent::CommandFinished { exit_code: None },
+            ShellEvent::CommandFinished {
+                exit_code: Some(127)
+            },
+        ],
+    );
+}
+
+#[test]
+fn split_across_chunks() {
+    let mut shell = ShellIntegration::new();
+    let data = b"a\x1b]133;D;0\x1b\\b";
+
+    // Every byte in a chunk of its own.
+    let events: Vec<_> = data.iter().flat_map(|&byte| shell.feed(&[byte])).collect();
+    assert_eq!(events, [ShellEvent::CommandFinished { exit_code: Some(0) }]);
+}
+
+#[test]
+fn other_sequences() {
+    let mut shell = ShellIntegration::new();
+
+    // A window title, a color and a command cut short by another escape sequence.
+    let data = b"\x1b]0;title\x07\x1b[31mred\x1b]133;A\x1b[0m\x1b]133;B\x07";
+    assert_eq!(shell.feed(data), [ShellEvent::CommandStart]);
+}
@@ -0,0 +1,96 @@
+use pseudoterminal::TerminalSize;
+
+#[test]
+fn parse_and_display() {
+    let size: TerminalSize = "120x40".parse().unwrap();
+    assert_eq!(
+        size,
+        TerminalSize {
+            rows: 40,
+            columns: 120,
+        }
+    );
+    assert_eq!(size.to_string(), "120x40");
+
+    assert!("120".parse::<TerminalSize>().is_err());
+    assert!("120x".parse::<TerminalSize>().is_err());
+    assert!("-1x40".parse::<TerminalSize>().is_err());
+}
+
+#[cfg(unix)]
+#[test]
+fn from_current_terminal() {
+    use pseudoterminal::Terminal;
+
+    let size = TerminalSize {
+        rows: 21,
+        columns: 43,
+    };
+
+    // Runs the query inside a terminal of a known size, the test itself might have none.
+    let mut terminal = unsafe {
+        Terminal::spawn_fn(size, move || match TerminalSize::from_current_terminal() {
+            Ok(current) if current == size => 0,
+            _ => 1,
+        })
+    }
+    .expect("should be spawnable");
+
+    assert!(terminal.wait().unwrap().success());
+}
+
+#[test]
+fn new_and_clamp() {
+    assert_eq!(
+        TerminalSize::new(40, 120).unwrap(),
+        TerminalSize {
+            rows: 40,
+            columns: 120,
+        }
+    );
+
+    let err = TerminalSize::new(0, 120).unwrap_err();
+    assert_eq!(
+        err.size(),
+        TerminalSize {
+            rows: 0,
+            columns: 120,
+        }
+    );
+    assert!(TerminalSize::new(40, u16::MAX).is_err());
+
+    let clamped = TerminalSize {
+        rows: 0,
+        columns: u16::MAX,
+    }
+    .clamp();
+    assert_eq!(
+        clamped,
+        TerminalSize {
+            rows: TerminalSize::MIN.rows,
+            columns: TerminalSize::MAX.columns,
+        }
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn size_policy() {
+    use std::io;
+
+    use pseudoterminal::{Pty, SizeError, SizePolicy, TerminalOptions};
+
+    let empty = TerminalSize {
+        rows: 0,
+        columns: 0,
+    };
+
+    let pty = Pty::open_with(&TerminalOptions::new()).unwrap();
+    let err = pty.handle().set_term_size(empty).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    assert!(err.get_ref().is_some_and(|err| err.is::<SizeError>()));
+
+    let pty = Pty::open_with(TerminalOptions::new().size_policy(SizePolicy::Clamp)).unwrap();
+    pty.handle().set_term_size(empty).unwrap();
+    assert_eq!(pty.handle().get_term_size().unwrap(), TerminalSize::MIN);
+}
@@ -0,0 +1,98 @@
+#![cfg(all(unix, feature = "testing"))]
+
+use std::io;
+use std::process::Command;
+use std::time::Duration;
+
+use pseudoterminal::keys::{Key, Modifiers};
+use pseudoterminal::screen::assert_screen_contains;
+use pseudoterminal::testing::TuiTest;
+use pseudoterminal::TerminalSize;
+
+fn sh(script: &str, size: TerminalSize) -> TuiTest {
+    TuiTest::spawn(Command::new("sh").args(["-c", script]), size).expect("should be spawnable")
+}
+
+#[test]
+fn keys_and_resize() {
+    let mut tui = sh(
+        r#"printf '\033[?1049h\033[Hready'; read -r a; printf '\033[2;1Hgot %s' "$a";
+        read -r b; printf '\033[3;1H%s' "$(stty size)"; read -r c || exit 0"#,
+        TerminalSize {
+            rows: 10,
+            columns: 40,
+        },
+    );
+
+    let screen = tui.wait_for_text("ready").unwrap();
+    assert!(screen.alternate_screen());
+
+    tui.type_text("hi").unwrap();
+    tui.send_key(Key::Enter, Modifiers::NONE).unwrap();
+    assert_eq!(
+        tui.wait_for_text("got hi")
+            .unwrap()
+            .row_contents(1)
+            .unwrap(),
+        "got hi"
+    );
+
+    let size = TerminalSize {
+        rows: 30,
+        columns: 100,
+    };
+    tui.resize(size).unwrap();
+    tui.send_key(Key::Enter, Modifiers::NONE).unwrap();
+    assert_eq!(tui.wait_for_text("30 100").unwrap().size(), size);
+
+    tui.send_key(Key::Char('d'), Modifiers::CTRL).unwrap();
+    assert!(tui.wait().unwrap().success());
+}
+
+#[test]
+fn wait_stable() {
+    let mut tui = sh(
+        "printf a; sleep 0.2; printf b; sleep 5",
+        TerminalSize {
+            rows: 5,
+            columns: 20,
+        },
+    );
+    tui.set_settle_time(Duration::from_millis(1000));
+
+    assert_screen_contains(&tui.wait_stable().unwrap(), "ab");
+}
+
+#[test]
+fn timeout() {
+    let mut tui = sh(
+        "while :; do printf x; sleep 0.01; done",
+        TerminalSize {
+            rows: 5,
+            columns: 20,
+        },
+    );
+    tui.set_timeout(Duration::from_millis(300));
+
+    let err = tui.wait_stable().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    assert!(err.to_string().contains("xxx"));
+
+    let err = tui.wait_for_text("never").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+}
+
+#[test]
+fn output_ended() {
+    let mut tui = sh(
+        "printf done",
+        TerminalSize {
+            rows: 5,
+            columns: 20,
+        },
+    );
+
+    let err = tui.wait_for_text("never").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_screen_contains(&tui.wait_stable().unwrap(), "done");
+}
@@ -0,0 +1,61 @@
+#![cfg(feature = "title")]
+
+use pseudoterminal::title::TitleTracker;
+
+#[test]
+fn titles() {
+    let mut titles = TitleTracker::new();
+    assert_eq!(titles.title(), None);
+
+    let found =
+        titles.feed(b"\x1b]0;one\x07text\x1b]1;icon\x07\x1b]2;two\x1b\\\x1b]7;file:///\x07");
+    assert_eq!(found, ["one", "two"]);
+    assert_eq!(titles.title(), Some("two"));
+
+    assert!(titles.feed(b"no title").is_empty());
+    assert_eq!(titles.title(), Some("two"));
+
+    assert_eq!(titles.feed(b"\x1b]2;\x07"), [""]);
+    assert_eq!(titles.title(), Some(""));
+}
+
+#[test]
+fn split_sequences() {
+    let mut titles = TitleTracker::new();
+    let mut found = Vec::new();
+    for byte in "\x1b]2;grüße\x07".as_bytes() {
+        found.extend(titles.feed(&[*byte]));
+    }
+
+    assert_eq!(found, ["grüße"]);
+}
+
+#[cfg(unix)]
+#[test]
+fn terminal_titles() {
+    use std::io::Read;
+    use std::process::Command;
+    use std::sync::{Arc, Mutex};
+
+    use pseudoterminal::CommandExt;
+
+    let mut terminal = Command::new("printf")
+        .arg(r"\033]0;hello\007world\033]2;bye\007")
+        .spawn_terminal()
+        .expect("should be spawnable");
+
+    let titles = Arc::new(Mutex::new(Vec::new()));
+    let seen = titles.clone();
+    terminal.on_title_change(move |title| seen.lock().unwrap().push(title.to_owned()));
+
+    let mut output = Vec::new();
+    terminal
+        .termout
+        .as_mut()
+        .unwrap()
+        .read_to_end(&mut output)
+        .unwrap();
+
+    assert!(output.ends_with(b"world\x1b]2;bye\x07"));
+    assert_eq!(*titles.lock().unwrap(), ["hello", "bye"]);
+}
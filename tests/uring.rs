@@ -0,0 +1,60 @@
+#![cfg(all(target_os = "linux", feature = "io-uring"))]
+
+use std::process::Command;
+
+use pseudoterminal::uring::{CommandExt, TerminalOut};
+
+async fn read_until(termout: &TerminalOut, end: &[u8]) -> Vec<u8> {
+    let mut output = Vec::new();
+    let mut buf = vec![0; 64];
+
+    while !output.ends_with(end) {
+        let (read, returned) = termout.read(buf).await;
+        let read = read.unwrap();
+        assert_ne!(read, 0, "the output ended early");
+
+        output.extend_from_slice(&returned[..read]);
+        buf = returned;
+    }
+
+    output
+}
+
+#[test]
+fn read_to_end() {
+    tokio_uring::start(async {
+        let mut terminal = Command::new("echo")
+            .arg("Hello, World!")
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        let termout = terminal.termout.as_ref().unwrap();
+        assert_eq!(read_until(termout, b"\r\n").await, b"Hello, World!\r\n");
+
+        let (read, _) = termout.read(vec![0; 64]).await;
+        assert_eq!(read.unwrap(), 0);
+
+        assert!(terminal.wait().await.unwrap().success());
+    });
+}
+
+#[test]
+fn write_and_read() {
+    tokio_uring::start(async {
+        let mut terminal = Command::new("cat")
+            .spawn_terminal()
+            .expect("should be spawnable");
+
+        let termin = terminal.termin.take().unwrap();
+        let (written, _) = termin.write_all(b"ping\n".to_vec()).await;
+        written.unwrap();
+
+        // The terminal's echo, followed by cat's copy.
+        let termout = terminal.termout.as_ref().unwrap();
+        read_until(termout, b"ping\r\nping\r\n").await;
+
+        // Dropping the input delivers EOF, so cat exits.
+        drop(termin);
+        assert!(terminal.wait().await.unwrap().success());
+    });
+}
@@ -0,0 +1,65 @@
+#![cfg(feature = "utf8")]
+
+use std::io;
+
+use pseudoterminal::Utf8Decoder;
+
+#[test]
+fn split_characters() {
+    let mut decoder = Utf8Decoder::new();
+    let text = "grüße 日本 🦀";
+
+    let decoded: String = text
+        .as_bytes()
+        .chunks(1)
+        .map(|chunk| decoder.decode(chunk).unwrap())
+        .collect();
+
+    assert_eq!(decoded, text);
+    assert_eq!(decoder.finish().unwrap(), "");
+}
+
+#[test]
+fn lossy() {
+    let mut decoder = Utf8Decoder::new();
+
+    assert_eq!(decoder.decode(b"a\xffb\xe6\x97").unwrap(), "a\u{FFFD}b");
+    assert_eq!(decoder.decode(b"c").unwrap(), "\u{FFFD}c");
+    assert_eq!(decoder.decode(b"\xf0\x9f").unwrap(), "");
+    assert_eq!(decoder.finish().unwrap(), "\u{FFFD}");
+}
+
+#[test]
+fn strict() {
+    let mut decoder = Utf8Decoder::new();
+    decoder.set_lossy(false);
+
+    // The text before the error comes first, the error on the next call.
+    assert_eq!(decoder.decode(b"a\xffb").unwrap(), "a");
+    let err = decoder.decode(b"").unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(decoder.decode(b"c").unwrap(), "bc");
+
+    assert_eq!(decoder.decode(b"\xc3").unwrap(), "");
+    assert_eq!(
+        decoder.finish().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert_eq!(decoder.finish().unwrap(), "");
+}
+
+#[test]
+fn strict_finish() {
+    let mut decoder = Utf8Decoder::new();
+    decoder.set_lossy(false);
+
+    assert_eq!(decoder.decode(b"ab\xffcd\xc3").unwrap(), "ab");
+
+    // Each error comes on a call of its own, after the text before it.
+    let err = decoder.finish().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(decoder.finish().unwrap(), "cd");
+    let err = decoder.finish().unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert_eq!(decoder.finish().unwrap(), "");
+}